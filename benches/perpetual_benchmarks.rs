@@ -1,9 +1,9 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use perpetual::binning::bin_matrix;
+use perpetual::binning::{bin_matrix, BinnedMatrix};
 use perpetual::constraints::ConstraintMap;
 use perpetual::data::Matrix;
 use perpetual::histogram::{NodeHistogram, NodeHistogramOwned};
-use perpetual::objective::{LogLoss, ObjectiveFunction};
+use perpetual::objective::{loss_callables, LogLoss, Objective, ObjectiveFunction};
 use perpetual::splitter::{MissingImputerSplitter, SplitInfo, SplitInfoSlice};
 use perpetual::tree::Tree;
 use perpetual::utils::{fast_f64_sum, fast_sum, naive_sum};
@@ -19,8 +19,8 @@ pub fn tree_benchmarks(c: &mut Criterion) {
         fs::read_to_string("resources/performance_100k_samp_seed0.csv").expect("Something went wrong reading the file");
     let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap()).collect();
     let yhat = vec![0.5; y.len()];
-    let (mut g, mut h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
-    let loss = LogLoss::calc_loss(&y, &yhat, None, None);
+    let (mut g, mut h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
+    let loss = LogLoss::default().calc_loss(&y, &yhat, None, None);
 
     let v: Vec<f32> = vec![10.; 300000];
     c.bench_function("Niave Sum", |b| b.iter(|| naive_sum(black_box(&v))));
@@ -28,7 +28,7 @@ pub fn tree_benchmarks(c: &mut Criterion) {
     c.bench_function("fast f64 sum", |b| b.iter(|| fast_f64_sum(black_box(&v))));
 
     c.bench_function("calc_grad_hess", |b| {
-        b.iter(|| LogLoss::calc_grad_hess(black_box(&y), black_box(&yhat), black_box(None), black_box(None)))
+        b.iter(|| LogLoss::default().calc_grad_hess(black_box(&y), black_box(&yhat), black_box(None), black_box(None)))
     });
 
     let data = Matrix::new(&data_vec, y.len(), 5);
@@ -56,9 +56,10 @@ pub fn tree_benchmarks(c: &mut Criterion) {
     let split_info_slice = SplitInfoSlice::new(&mut split_info_vec);
 
     tree.fit(
-        &bdata,
+        &mut BinnedMatrix::Dense(&bdata),
         data.index.to_owned(),
         &col_index,
+        None,
         &mut g,
         h.as_deref_mut(),
         &splitter,
@@ -66,7 +67,7 @@ pub fn tree_benchmarks(c: &mut Criterion) {
         Some(f32::MAX),
         &loss,
         &y,
-        LogLoss::calc_loss,
+        &loss_callables(&Objective::LogLoss).unwrap(),
         &yhat,
         None,
         None,
@@ -83,9 +84,10 @@ pub fn tree_benchmarks(c: &mut Criterion) {
             let mut train_tree: Tree = Tree::new();
 
             train_tree.fit(
-                black_box(&bdata),
+                black_box(&mut BinnedMatrix::Dense(&bdata)),
                 black_box(data.index.to_owned()),
                 black_box(&col_index),
+                None,
                 black_box(&mut g),
                 black_box(h.as_deref_mut()),
                 black_box(&splitter),
@@ -93,7 +95,7 @@ pub fn tree_benchmarks(c: &mut Criterion) {
                 Some(f32::MAX),
                 black_box(&loss),
                 black_box(&y),
-                black_box(LogLoss::calc_loss),
+                black_box(&loss_callables(&Objective::LogLoss).unwrap()),
                 black_box(&yhat),
                 None,
                 None,
@@ -110,9 +112,10 @@ pub fn tree_benchmarks(c: &mut Criterion) {
             let mut train_tree: Tree = Tree::new();
 
             train_tree.fit(
-                black_box(&bdata),
+                black_box(&mut BinnedMatrix::Dense(&bdata)),
                 black_box(data.index.to_owned()),
                 black_box(&[1, 3, 4]),
+                None,
                 black_box(&mut g),
                 black_box(h.as_deref_mut()),
                 black_box(&splitter),
@@ -120,7 +123,7 @@ pub fn tree_benchmarks(c: &mut Criterion) {
                 Some(f32::MAX),
                 black_box(&loss),
                 black_box(&y),
-                black_box(LogLoss::calc_loss),
+                black_box(&loss_callables(&Objective::LogLoss).unwrap()),
                 black_box(&yhat),
                 None,
                 None,
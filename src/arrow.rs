@@ -0,0 +1,339 @@
+use crate::data::FloatData;
+use crate::errors::PerpetualError;
+use crate::Matrix;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float16Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, ArrowPrimitiveType, RecordBatch};
+use arrow_schema::DataType;
+use std::collections::{HashMap, HashSet};
+
+/// Column major data of Arrow record batches, such as the ones exported by Polars or PyArrow,
+/// with the names of the columns. The numeric and boolean columns are converted to `T`, f64 or f32,
+/// and the dictionary and string columns to the codes of their categories. Null values are missing, NaN.
+/// Float32 columns keep their values in `ArrowData<f32>`, at half the memory of f64.
+/// Available with the `arrow` feature.
+#[derive(Debug, Clone)]
+pub struct ArrowData<T = f64> {
+    /// Column major data, for `Matrix::new`.
+    pub data: Vec<T>,
+    pub rows: usize,
+    pub cols: usize,
+    /// Names of the columns.
+    pub feature_names: Vec<String>,
+    /// The dictionary and string columns, to pass to `set_categorical_features`.
+    pub categorical_features: HashSet<usize>,
+    /// Categories of each categorical column, sorted, the code of a category is its position.
+    pub categories: HashMap<usize, Vec<String>>,
+}
+
+impl<T: FloatData<T>> ArrowData<T> {
+    /// Convert record batches to the data to fit a booster on. The categories of the
+    /// categorical columns are the ones found in the batches.
+    ///
+    /// * `batches` - Record batches with the same schema.
+    pub fn from_record_batches(batches: &[RecordBatch]) -> Result<Self, PerpetualError> {
+        ArrowData::convert(batches, None)
+    }
+
+    /// Convert record batches to data with the same columns and categories as the data a
+    /// booster was fit on, so that the categories get the same codes. Categories that are
+    /// not in `fit_data` are missing. The categorical columns must be the ones of `fit_data`.
+    ///
+    /// * `batches` - Record batches with the same schema as the ones of `fit_data`.
+    /// * `fit_data` - The data the booster was fit on.
+    pub fn from_record_batches_like(batches: &[RecordBatch], fit_data: &ArrowData<T>) -> Result<Self, PerpetualError> {
+        let converted = ArrowData::convert(batches, Some(fit_data))?;
+        if converted.feature_names != fit_data.feature_names {
+            return Err(PerpetualError::InvalidParameter(
+                "batches".to_string(),
+                format!("columns {:?}", fit_data.feature_names),
+                format!("{:?}", converted.feature_names),
+            ));
+        }
+        Ok(converted)
+    }
+
+    /// Matrix over the data, with the names of the columns.
+    pub fn matrix(&self) -> Matrix<'_, T> {
+        Matrix::new(&self.data, self.rows, self.cols).with_feature_names(&self.feature_names)
+    }
+
+    fn convert(batches: &[RecordBatch], fit_data: Option<&ArrowData<T>>) -> Result<Self, PerpetualError> {
+        let schema = match batches.first() {
+            Some(batch) => batch.schema(),
+            None => {
+                return Err(PerpetualError::InvalidParameter(
+                    "batches".to_string(),
+                    "at least one record batch".to_string(),
+                    "none".to_string(),
+                ))
+            }
+        };
+        if let Some(batch) = batches.iter().find(|b| b.schema() != schema) {
+            return Err(PerpetualError::InvalidParameter(
+                "batches".to_string(),
+                format!("the schema {}", schema),
+                format!("{}", batch.schema()),
+            ));
+        }
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        let cols = schema.fields().len();
+
+        let mut data = Vec::with_capacity(rows * cols);
+        let mut categorical_features = HashSet::new();
+        let mut categories = HashMap::new();
+        for (col, field) in schema.fields().iter().enumerate() {
+            let arrays: Vec<&dyn Array> = batches.iter().map(|b| b.column(col).as_ref()).collect();
+            let is_categorical = matches!(
+                field.data_type(),
+                DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View | DataType::Dictionary(_, _)
+            );
+            // The kind of each column is the one it had in the data the booster was fit on.
+            if let Some(fit_data) = fit_data {
+                if is_categorical != fit_data.categorical_features.contains(&col) {
+                    return Err(PerpetualError::InvalidParameter(
+                        field.name().to_string(),
+                        if is_categorical {
+                            "a numeric column, as in the data the booster was fit on".to_string()
+                        } else {
+                            "a string or dictionary column, as in the data the booster was fit on".to_string()
+                        },
+                        field.data_type().to_string(),
+                    ));
+                }
+            }
+            match field.data_type() {
+                _ if is_categorical => {
+                    let values = arrays
+                        .iter()
+                        .map(|a| string_values(*a, field.name()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let col_categories = match fit_data {
+                        Some(fit_data) => fit_data.categories.get(&col).cloned().unwrap_or_default(),
+                        None => {
+                            let mut found: Vec<String> = values.iter().flatten().flatten().cloned().collect();
+                            found.sort_unstable();
+                            found.dedup();
+                            found
+                        }
+                    };
+                    // The categories are binned as u16 values.
+                    if col_categories.len() >= usize::from(u16::MAX) {
+                        return Err(PerpetualError::InvalidParameter(
+                            field.name().to_string(),
+                            format!("less than {} categories", u16::MAX),
+                            col_categories.len().to_string(),
+                        ));
+                    }
+                    let codes: HashMap<&str, T> = col_categories
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| (c.as_str(), T::from_usize(i)))
+                        .collect();
+                    data.extend(
+                        values
+                            .iter()
+                            .flatten()
+                            .map(|v| v.as_deref().and_then(|v| codes.get(v).copied()).unwrap_or(T::NAN)),
+                    );
+                    categorical_features.insert(col);
+                    categories.insert(col, col_categories);
+                }
+                data_type => {
+                    for array in arrays {
+                        numeric_values(array, field.name(), data_type, &mut data)?;
+                    }
+                }
+            }
+        }
+
+        Ok(ArrowData {
+            data,
+            rows,
+            cols,
+            feature_names: schema.fields().iter().map(|f| f.name().to_string()).collect(),
+            categorical_features,
+            categories,
+        })
+    }
+}
+
+/// Append the values of a numeric or boolean array to `data`, converted to `T` through f64,
+/// which keeps the Float32 values exact in f32 data.
+fn numeric_values<T: FloatData<T>>(
+    array: &dyn Array,
+    name: &str,
+    data_type: &DataType,
+    data: &mut Vec<T>,
+) -> Result<(), PerpetualError> {
+    match data_type {
+        DataType::Float64 => primitive_values::<Float64Type, T>(array, |v| v, data),
+        DataType::Float32 => primitive_values::<Float32Type, T>(array, f64::from, data),
+        DataType::Float16 => primitive_values::<Float16Type, T>(array, f64::from, data),
+        DataType::Int64 => primitive_values::<Int64Type, T>(array, |v| v as f64, data),
+        DataType::Int32 => primitive_values::<Int32Type, T>(array, f64::from, data),
+        DataType::Int16 => primitive_values::<Int16Type, T>(array, f64::from, data),
+        DataType::Int8 => primitive_values::<Int8Type, T>(array, f64::from, data),
+        DataType::UInt64 => primitive_values::<UInt64Type, T>(array, |v| v as f64, data),
+        DataType::UInt32 => primitive_values::<UInt32Type, T>(array, f64::from, data),
+        DataType::UInt16 => primitive_values::<UInt16Type, T>(array, f64::from, data),
+        DataType::UInt8 => primitive_values::<UInt8Type, T>(array, f64::from, data),
+        DataType::Boolean => data.extend(
+            array
+                .as_boolean()
+                .iter()
+                .map(|v| v.map_or(T::NAN, |v| if v { T::ONE } else { T::ZERO })),
+        ),
+        _ => {
+            return Err(PerpetualError::InvalidParameter(
+                name.to_string(),
+                "a numeric, boolean, string or dictionary column".to_string(),
+                data_type.to_string(),
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn primitive_values<A: ArrowPrimitiveType, T: FloatData<T>>(
+    array: &dyn Array,
+    f: impl Fn(A::Native) -> f64,
+    data: &mut Vec<T>,
+) {
+    data.extend(
+        array
+            .as_primitive::<A>()
+            .iter()
+            .map(|v| v.map_or(T::NAN, |v| T::from_f64(f(v)))),
+    );
+}
+
+/// The values of a string array, or of a dictionary array of strings, `None` for the null values.
+fn string_values(array: &dyn Array, name: &str) -> Result<Vec<Option<String>>, PerpetualError> {
+    let values = match array.data_type() {
+        DataType::Utf8 => array.as_string::<i32>().iter().map(|v| v.map(String::from)).collect(),
+        DataType::LargeUtf8 => array.as_string::<i64>().iter().map(|v| v.map(String::from)).collect(),
+        DataType::Utf8View => array.as_string_view().iter().map(|v| v.map(String::from)).collect(),
+        DataType::Dictionary(_, _) => {
+            let dictionary = array.as_any_dictionary();
+            if dictionary.values().is_empty() {
+                return Ok(vec![None; array.len()]);
+            }
+            let dictionary_values = string_values(dictionary.values().as_ref(), name)?;
+            let nulls = dictionary.logical_nulls();
+            dictionary
+                .normalized_keys()
+                .iter()
+                .enumerate()
+                .map(|(i, k)| match &nulls {
+                    Some(nulls) if nulls.is_null(i) => None,
+                    _ => dictionary_values.get(*k).cloned().flatten(),
+                })
+                .collect()
+        }
+        data_type => {
+            return Err(PerpetualError::InvalidParameter(
+                name.to_string(),
+                "a dictionary column of strings".to_string(),
+                data_type.to_string(),
+            ))
+        }
+    };
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{DictionaryArray, Float32Array, Int64Array, StringArray};
+    use arrow_schema::{Field, Schema};
+    use std::sync::Arc;
+
+    fn batch(x: Vec<Option<f32>>, n: Vec<i64>, color: Vec<Option<&str>>) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("x", DataType::Float32, true),
+            Field::new("n", DataType::Int64, false),
+            Field::new(
+                "color",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+        ]);
+        let color: DictionaryArray<Int32Type> = color.into_iter().collect();
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Float32Array::from(x)),
+                Arc::new(Int64Array::from(n)),
+                Arc::new(color),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_arrow_data() {
+        let batches = [
+            batch(vec![Some(0.5), None], vec![1, 2], vec![Some("red"), Some("blue")]),
+            batch(vec![Some(1.5)], vec![3], vec![None]),
+        ];
+        let arrow_data: ArrowData = ArrowData::from_record_batches(&batches).unwrap();
+        assert_eq!((arrow_data.rows, arrow_data.cols), (3, 3));
+        assert_eq!(arrow_data.feature_names, vec!["x", "n", "color"]);
+        assert_eq!(arrow_data.categorical_features, HashSet::from([2]));
+        assert_eq!(arrow_data.categories[&2], vec!["blue", "red"]);
+        let matrix = arrow_data.matrix();
+        assert_eq!(matrix.feature_names, Some(&arrow_data.feature_names[..]));
+        assert_eq!(matrix.get_col(1), &[1.0, 2.0, 3.0]);
+        assert!(matrix.get(1, 0).is_nan() && matrix.get(2, 2).is_nan());
+        assert_eq!(&matrix.get_col(2)[..2], &[1.0, 0.0]);
+
+        // New data gets the codes of the categories of the data the booster is fit on.
+        let new_batches = [batch(
+            vec![Some(2.0), Some(1.0)],
+            vec![4, 5],
+            vec![Some("green"), Some("red")],
+        )];
+        let new_data = ArrowData::from_record_batches_like(&new_batches, &arrow_data).unwrap();
+        assert!(new_data.data[4].is_nan());
+        assert_eq!(new_data.data[5], 1.0);
+
+        let strings = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("x", DataType::Utf8, false)])),
+            vec![Arc::new(StringArray::from(vec!["a"]))],
+        )
+        .unwrap();
+        assert!(ArrowData::from_record_batches_like(&[strings], &arrow_data).is_err());
+        assert!(ArrowData::<f64>::from_record_batches(&[]).is_err());
+
+        // A numeric column where the booster was fit on a categorical one.
+        let codes = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("x", DataType::Float32, true),
+                Field::new("n", DataType::Int64, false),
+                Field::new("color", DataType::Int64, false),
+            ])),
+            vec![
+                Arc::new(Float32Array::from(vec![1.0])),
+                Arc::new(Int64Array::from(vec![1])),
+                Arc::new(Int64Array::from(vec![0])),
+            ],
+        )
+        .unwrap();
+        assert!(matches!(
+            ArrowData::from_record_batches_like(&[codes], &arrow_data),
+            Err(PerpetualError::InvalidParameter(name, _, _)) if name == "color"
+        ));
+
+        // Float32 columns keep their values in f32 data.
+        let x = 0.1_f32;
+        let arrow_data: ArrowData<f32> =
+            ArrowData::from_record_batches(&[batch(vec![Some(x), None], vec![1, 2], vec![Some("red"), None])]).unwrap();
+        assert_eq!(arrow_data.data[0], x);
+        assert!(arrow_data.data[1].is_nan());
+        assert_eq!(arrow_data.matrix().get_col(2)[0], 0.0);
+    }
+}
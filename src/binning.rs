@@ -1,8 +1,8 @@
 use std::collections::HashSet;
 
-use crate::data::{FloatData, JaggedMatrix, Matrix};
+use crate::data::{FloatData, JaggedMatrix, Matrix, SparseMatrix};
 use crate::errors::PerpetualError;
-use crate::utils::{is_missing, map_bin, percentiles};
+use crate::utils::{is_missing, map_bin, missing_as, percentiles};
 
 /// If there are fewer unique values than their are
 /// percentiles, just return the unique values of the
@@ -38,9 +38,91 @@ where
 // a split that is [feature < 5] would translate to [feature < 31.0 ]
 #[derive(Debug)]
 pub struct BinnedData<T> {
+    /// The bins of every item, column major, empty when the data is sparse.
     pub binned_data: Vec<u16>,
     pub cuts: JaggedMatrix<T>,
     pub nunique: Vec<usize>,
+    /// The bins of the items outside of the bin of zero in each column, when the data is sparse.
+    pub sparse: Option<SparseBins>,
+}
+
+/// Binned sparse data, where only the items outside of the bin of zero of their column
+/// are kept. The rest of the rows of a column are in its zero bin.
+#[derive(Debug)]
+pub struct SparseBins {
+    /// The bin of zero in each column.
+    pub zero_bins: Vec<u16>,
+    /// The (row, bin) pairs of the items outside of the zero bin of each column, in increasing row order.
+    pub items: JaggedMatrix<(usize, u16)>,
+}
+
+/// The bins the trees of a fit are split on.
+pub enum BinnedMatrix<'a> {
+    /// The bins of every item, column major.
+    Dense(&'a Matrix<'a, u16>),
+    /// The bins of sparse data. The rows of a node are located among the items of a column
+    /// by their position in the index of the tree, `usize::MAX` for the rows outside of it,
+    /// and the bins of the rows of a node being split are gathered into `split_bins`.
+    /// Both buffers are indexed by row, and reused by every node of the fit.
+    Sparse {
+        bins: &'a SparseBins,
+        position: Vec<usize>,
+        split_bins: Vec<u16>,
+    },
+}
+
+impl<'a> BinnedMatrix<'a> {
+    /// Binned sparse data with `rows` rows, see `BinnedMatrix::Sparse`.
+    pub fn sparse(bins: &'a SparseBins, rows: usize) -> Self {
+        BinnedMatrix::Sparse {
+            bins,
+            position: vec![usize::MAX; rows],
+            split_bins: vec![0; rows],
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        match self {
+            BinnedMatrix::Dense(data) => data.rows,
+            BinnedMatrix::Sparse { position, .. } => position.len(),
+        }
+    }
+
+    /// Record the position of each row in the index of a new tree.
+    pub fn set_positions(&mut self, index: &[usize]) {
+        if let BinnedMatrix::Sparse { position, .. } = self {
+            position.fill(usize::MAX);
+            index.iter().enumerate().for_each(|(p, row)| position[*row] = p);
+        }
+    }
+
+    /// Record the position of the rows of a node, once these are pivoted on a split.
+    pub fn update_positions(&mut self, index: &[usize], start: usize, stop: usize) {
+        if let BinnedMatrix::Sparse { position, .. } = self {
+            (start..stop).for_each(|p| position[index[p]] = p);
+        }
+    }
+
+    /// The bins of a column, indexed by row, for the rows of the node between `start` and `stop` in the index.
+    pub fn split_col(&mut self, col: usize, index: &[usize], start: usize, stop: usize) -> &[u16] {
+        match self {
+            BinnedMatrix::Dense(data) => data.get_col(col),
+            BinnedMatrix::Sparse {
+                bins,
+                position,
+                split_bins,
+            } => {
+                let zero_bin = bins.zero_bins[col];
+                index[start..stop].iter().for_each(|row| split_bins[*row] = zero_bin);
+                for (row, bin) in bins.items.get_col(col) {
+                    if (start..stop).contains(&position[*row]) {
+                        split_bins[*row] = *bin;
+                    }
+                }
+                split_bins
+            }
+        }
+    }
 }
 
 /// Convert a matrix of data, into a binned matrix.
@@ -48,41 +130,51 @@ pub struct BinnedData<T> {
 /// * `data` - Numeric data to be binned.
 /// * `cuts` - A slice of Vectors, where the vectors are the corresponding
 ///     cut values for each of the columns.
-fn bin_matrix_from_cuts<T: FloatData<T>>(data: &Matrix<T>, cuts: &JaggedMatrix<T>, missing: &T) -> Vec<u16> {
-    // loop through the matrix, binning the data.
-    // We will determine the column we are in, by
-    // using the modulo operator, on the record value.
-    data.data
-        .iter()
-        .enumerate()
-        .map(|(i, v)| {
-            let col = i / data.rows;
-            // This will always be smaller than u16::MAX so we
-            // are good to just unwrap here.
-            map_bin(cuts.get_col(col), v, missing).unwrap()
-        })
-        .collect()
-}
-
-/// Bin a numeric matrix.
+/// * `missing` - Float value to consider as missing, rounded to the type of the data.
+fn bin_matrix_from_cuts<T: FloatData<T>>(data: &Matrix<T>, cuts: &JaggedMatrix<f64>, missing: &f64) -> Vec<u16> {
+    // This will always be smaller than u16::MAX so we
+    // are good to just unwrap here.
+    map_matrix_col_major(data, |col, v| map_bin(cuts.get_col(col), &v.to_f64(), missing).unwrap())
+}
+
+/// Map every item of a matrix, with the index of its column, into a column major vector.
+/// The data is read in the order of its layout, by column when the columns are contiguous,
+/// and by row otherwise.
+fn map_matrix_col_major<T, F>(data: &Matrix<T>, f: F) -> Vec<u16>
+where
+    F: Fn(usize, &T) -> u16,
+{
+    if data.is_col_contiguous() {
+        (0..data.cols)
+            .flat_map(|col| data.get_col(col).iter().map(move |v| (col, v)))
+            .map(|(col, v)| f(col, v))
+            .collect()
+    } else {
+        let mut mapped = vec![0; data.rows * data.cols];
+        for row in 0..data.rows {
+            for (col, v) in data.get_row_iter(row).enumerate() {
+                mapped[col * data.rows + row] = f(col, v);
+            }
+        }
+        mapped
+    }
+}
+
+/// Bin a numeric matrix, of f64 or f32 data. The cuts are f64 either way.
 ///
 /// * `data` - A numeric matrix, of data to be binned.
 /// * `sample_weight` - Instance weights for each row of the data.
 /// * `nbins` - The number of bins each column should be binned into.
 /// * `missing` - Float value to consider as missing.
-pub fn bin_matrix(
-    data: &Matrix<f64>,
+pub fn bin_matrix<T: FloatData<T>>(
+    data: &Matrix<T>,
     sample_weight: Option<&[f64]>,
     nbins: u16,
     missing: f64,
     cat_index: Option<&HashSet<usize>>,
 ) -> Result<BinnedData<f64>, PerpetualError> {
-    let mut pcts = Vec::new();
-    let nbins_ = f64::from_u16(nbins);
-    for i in 0..nbins {
-        let v = f64::from_u16(i) / nbins_;
-        pcts.push(v);
-    }
+    let missing = missing_as::<T>(&missing);
+    let pcts = bin_percentiles(nbins);
 
     let s_w = vec![1.0; data.rows];
     let weight = match sample_weight {
@@ -90,13 +182,7 @@ pub fn bin_matrix(
         None => &s_w,
     };
 
-    let to_remove = match cat_index {
-        Some(cat_index) => HashSet::from_iter(cat_index),
-        None => HashSet::new(),
-    };
-    let mut num_index: Vec<usize> = (0..data.cols).collect();
-    num_index.retain(|e| !to_remove.contains(&(*e)));
-    let num_index_set: HashSet<usize> = HashSet::from_iter(num_index);
+    let num_index_set = numeric_cols(data.cols, cat_index);
 
     // First we need to generate the bins for each of the columns.
     // We will loop through all of the columns, and generate the cuts.
@@ -104,47 +190,15 @@ pub fn bin_matrix(
     let mut nunique = Vec::new();
     for i in 0..data.cols {
         let (no_miss, w): (Vec<f64>, Vec<f64>) = data
-            .get_col(i)
-            .iter()
+            .get_col_iter(i)
+            .map(|v| v.to_f64())
             .zip(weight.iter())
             // It is unrecoverable if they have provided missing values in
             // the data other than the specificized missing.
             .filter(|(v, _)| !is_missing(v, &missing))
             .unzip();
         assert_eq!(no_miss.len(), w.len());
-
-        if num_index_set.contains(&i) {
-            let mut col_cuts = percentiles_or_value(&no_miss, &w, &pcts);
-            col_cuts.push(f64::MAX);
-            col_cuts.dedup();
-            // if col_cuts.len() < 2 {
-            //     return Err(PerpetualError::NoVariance(i));
-            // }
-            // There will be one less bins, then there are cuts.
-            // The first value will be for missing.
-            nunique.push(col_cuts.len());
-            let l = col_cuts.len();
-            cuts.data.extend(col_cuts);
-            let e = match cuts.ends.last() {
-                Some(v) => v + l,
-                None => l,
-            };
-            cuts.ends.push(e);
-        } else {
-            // There will be number of bins as many as number of categories. Number of bins for categorical features is not limited currently.
-            let col_categories: HashSet<u16> = HashSet::from_iter(no_miss.iter().map(|&e| e as u16));
-            let mut col_cuts: Vec<f64> = col_categories.iter().map(|&e| e as f64).collect();
-            col_cuts.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-            col_cuts.push(f64::MAX);
-            nunique.push(col_cuts.len());
-            let l = col_cuts.len();
-            cuts.data.extend(col_cuts);
-            let e = match cuts.ends.last() {
-                Some(v) => v + l,
-                None => l,
-            };
-            cuts.ends.push(e);
-        }
+        push_col_cuts(&mut cuts, &mut nunique, &no_miss, &w, &pcts, num_index_set.contains(&i));
     }
 
     cuts.cols = cuts.ends.len();
@@ -156,9 +210,199 @@ pub fn bin_matrix(
         binned_data,
         cuts,
         nunique,
+        sparse: None,
     })
 }
 
+/// Percentiles to cut a numeric column at, for `nbins` bins.
+fn bin_percentiles(nbins: u16) -> Vec<f64> {
+    let nbins_ = f64::from_u16(nbins);
+    (0..nbins).map(|i| f64::from_u16(i) / nbins_).collect()
+}
+
+/// The columns binned as numeric, the columns not in `cat_index`.
+fn numeric_cols(cols: usize, cat_index: Option<&HashSet<usize>>) -> HashSet<usize> {
+    (0..cols).filter(|i| cat_index.is_none_or(|c| !c.contains(i))).collect()
+}
+
+/// Calculate the cuts of a column from its values that are not missing, and add them to `cuts`.
+///
+/// * `no_miss` - The values of the column that are not missing.
+/// * `w` - Instance weights of the values.
+/// * `pcts` - Percentiles to cut numeric columns at.
+/// * `numeric` - Whether the column is numeric, or categorical.
+fn push_col_cuts(
+    cuts: &mut JaggedMatrix<f64>,
+    nunique: &mut Vec<usize>,
+    no_miss: &[f64],
+    w: &[f64],
+    pcts: &[f64],
+    numeric: bool,
+) {
+    let col_cuts = if numeric {
+        let mut col_cuts = percentiles_or_value(no_miss, w, pcts);
+        col_cuts.push(f64::MAX);
+        col_cuts.dedup();
+        // if col_cuts.len() < 2 {
+        //     return Err(PerpetualError::NoVariance(i));
+        // }
+        // There will be one less bins, then there are cuts.
+        // The first value will be for missing.
+        col_cuts
+    } else {
+        // There will be number of bins as many as number of categories. Number of bins for categorical features is not limited currently.
+        let col_categories: HashSet<u16> = HashSet::from_iter(no_miss.iter().map(|&e| e as u16));
+        let mut col_cuts: Vec<f64> = col_categories.iter().map(|&e| e as f64).collect();
+        col_cuts.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        col_cuts.push(f64::MAX);
+        col_cuts
+    };
+    nunique.push(col_cuts.len());
+    let l = col_cuts.len();
+    cuts.data.extend(col_cuts);
+    let e = match cuts.ends.last() {
+        Some(v) => v + l,
+        None => l,
+    };
+    cuts.ends.push(e);
+}
+
+/// Bin a sparse matrix, of f64 or f32 data, without materializing the items that are not stored.
+/// The zeros of a column are cut as a single value, with the total weight of their rows.
+///
+/// * `data` - A sparse matrix, of data to be binned.
+/// * `sample_weight` - Instance weights for each row of the data.
+/// * `nbins` - The number of bins each column should be binned into.
+/// * `missing` - Float value to consider as missing.
+/// * `cat_index` - The columns that are categorical.
+pub fn bin_sparse_matrix<T: FloatData<T>>(
+    data: &SparseMatrix<T>,
+    sample_weight: Option<&[f64]>,
+    nbins: u16,
+    missing: f64,
+    cat_index: Option<&HashSet<usize>>,
+) -> Result<BinnedData<f64>, PerpetualError> {
+    let missing = missing_as::<T>(&missing);
+    let pcts = bin_percentiles(nbins);
+    let num_index_set = numeric_cols(data.cols, cat_index);
+    let total_weight = sample_weight.map_or(data.rows as f64, |w| w.iter().sum());
+
+    let col_items = data.col_items();
+    let mut cuts = JaggedMatrix::new();
+    let mut nunique = Vec::new();
+    for (i, items) in col_items.iter().enumerate() {
+        let (mut no_miss, mut w): (Vec<f64>, Vec<f64>) = items
+            .iter()
+            .map(|(row, v)| (v.to_f64(), sample_weight.map_or(1.0, |w| w[*row])))
+            .filter(|(v, _)| !is_missing(v, &missing))
+            .unzip();
+        let zeros_weight = total_weight
+            - items
+                .iter()
+                .map(|(row, _)| sample_weight.map_or(1.0, |w| w[*row]))
+                .sum::<f64>();
+        if items.len() < data.rows && !is_missing(&0.0, &missing) {
+            no_miss.push(0.0);
+            w.push(zeros_weight);
+        }
+        push_col_cuts(&mut cuts, &mut nunique, &no_miss, &w, &pcts, num_index_set.contains(&i));
+    }
+    cuts.cols = cuts.ends.len();
+    cuts.n_records = cuts.ends.iter().sum();
+
+    let sparse = bin_sparse_items(&col_items, |col, v| map_bin(cuts.get_col(col), v, &missing).unwrap());
+    Ok(BinnedData {
+        binned_data: Vec::new(),
+        cuts,
+        nunique,
+        sparse: Some(sparse),
+    })
+}
+
+/// Bin a sparse matrix with the cuts of a previous call to `bin_sparse_matrix` or `bin_matrix`.
+///
+/// * `data` - A sparse matrix, of data to be binned.
+/// * `cuts` - The cuts of each of the columns.
+/// * `missing` - Float value to consider as missing.
+pub fn bin_sparse_matrix_with_cuts<T: FloatData<T>>(
+    data: &SparseMatrix<T>,
+    cuts: &JaggedMatrix<f64>,
+    missing: f64,
+) -> BinnedData<f64> {
+    let missing = missing_as::<T>(&missing);
+    let sparse = bin_sparse_items(&data.col_items(), |col, v| {
+        map_bin_with_cuts(cuts.get_col(col), v, &missing)
+    });
+    let nunique = (0..cuts.cols).map(|i| cuts.get_col(i).len()).collect();
+    BinnedData {
+        binned_data: Vec::new(),
+        cuts: cuts.clone(),
+        nunique,
+        sparse: Some(sparse),
+    }
+}
+
+/// Bin the stored items of each column of sparse data, keeping the items outside of the
+/// bin of zero, the rest of the rows of a column are in its zero bin.
+fn bin_sparse_items<T, F>(col_items: &[Vec<(usize, T)>], f: F) -> SparseBins
+where
+    T: FloatData<T>,
+    F: Fn(usize, &f64) -> u16,
+{
+    let mut zero_bins = Vec::with_capacity(col_items.len());
+    let mut nonzero_items = Vec::with_capacity(col_items.len());
+    for (col, items) in col_items.iter().enumerate() {
+        let zero_bin = f(col, &0.0);
+        let col_bins: Vec<(usize, u16)> = items
+            .iter()
+            .map(|(row, v)| (*row, f(col, &v.to_f64())))
+            .filter(|(_, bin)| *bin != zero_bin)
+            .collect();
+        zero_bins.push(zero_bin);
+        nonzero_items.push(col_bins);
+    }
+    SparseBins {
+        zero_bins,
+        items: JaggedMatrix::from_vecs(&nonzero_items),
+    }
+}
+
+/// Map a value to its bin, with the cuts of other data, see `bin_matrix_with_cuts`.
+fn map_bin_with_cuts(cuts: &[f64], v: &f64, missing: &f64) -> u16 {
+    let bin = map_bin(cuts, v, missing).unwrap();
+    // Values below the smallest cut, out of the range of the data the cuts were
+    // created from, belong to the lowest bin rather than to the missing bin.
+    if bin == 0 && !is_missing(v, missing) {
+        1
+    } else {
+        bin
+    }
+}
+
+/// Bin a numeric matrix with the cuts of a previous call to `bin_matrix`,
+/// so that new data shares the bins of the data the cuts were created from.
+///
+/// * `data` - A numeric matrix, of data to be binned.
+/// * `cuts` - The cuts of each of the columns.
+/// * `missing` - Float value to consider as missing.
+pub fn bin_matrix_with_cuts<T: FloatData<T>>(
+    data: &Matrix<T>,
+    cuts: &JaggedMatrix<f64>,
+    missing: f64,
+) -> BinnedData<f64> {
+    let missing = missing_as::<T>(&missing);
+    let binned_data = map_matrix_col_major(data, |col, v| {
+        map_bin_with_cuts(cuts.get_col(col), &v.to_f64(), &missing)
+    });
+    let nunique = (0..cuts.cols).map(|i| cuts.get_col(i).len()).collect();
+    BinnedData {
+        binned_data,
+        cuts: cuts.clone(),
+        nunique,
+        sparse: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,4 +478,98 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_bin_data_with_cuts() {
+        let data_vec: Vec<f64> = (0..200)
+            .map(|i| if i % 17 == 0 { f64::NAN } else { (i % 50) as f64 })
+            .collect();
+        let data = Matrix::new(&data_vec, 100, 2);
+        let b = bin_matrix(&data, None, 10, f64::NAN, None).unwrap();
+        let b_same = bin_matrix_with_cuts(&data, &b.cuts, f64::NAN);
+        assert_eq!(b_same.binned_data, b.binned_data);
+        assert_eq!(b_same.nunique, b.nunique);
+
+        // Values out of the range of the cuts go to the lowest and highest bins, missing values to bin zero.
+        let new_vec = vec![-5.0, f64::NAN, 1000.0, 0.0];
+        let new_data = Matrix::new(&new_vec, 2, 2);
+        let b_new = bin_matrix_with_cuts(&new_data, &b.cuts, f64::NAN);
+        assert_eq!(b_new.binned_data[0], 1);
+        assert_eq!(b_new.binned_data[1], 0);
+        assert_eq!(b_new.binned_data[2] as usize, b.cuts.get_col(1).len() - 1);
+        assert_eq!(b_new.binned_data[3], 1);
+    }
+
+    #[test]
+    fn test_bin_data_row_major() {
+        let data_vec: Vec<f64> = (0..300)
+            .map(|i| if i % 17 == 0 { f64::NAN } else { ((i * 7) % 50) as f64 })
+            .collect();
+        let data = Matrix::new(&data_vec, 100, 3);
+        let row_major_vec: Vec<f64> = (0..100).flat_map(|i| data.get_row(i)).collect();
+        let row_major = Matrix::new_row_major(&row_major_vec, 100, 3);
+        let b = bin_matrix(&data, None, 10, f64::NAN, None).unwrap();
+        let b_row_major = bin_matrix(&row_major, None, 10, f64::NAN, None).unwrap();
+        assert_eq!(b_row_major.binned_data, b.binned_data);
+        assert_eq!(b_row_major.cuts.data, b.cuts.data);
+        let b_with_cuts = bin_matrix_with_cuts(&row_major, &b.cuts, f64::NAN);
+        assert_eq!(b_with_cuts.binned_data, b.binned_data);
+    }
+
+    #[test]
+    fn test_bin_sparse_matrix() {
+        let (rows, cols) = (200, 3);
+        let dense: Vec<f64> = (0..rows * cols)
+            .map(|i| match i {
+                i if i % 7 == 0 => ((i * 13) % 11) as f64 - 5.0,
+                i if i % 31 == 0 => f64::NAN,
+                _ => 0.0,
+            })
+            .collect();
+        let data = Matrix::new(&dense, rows, cols);
+        let (mut values, mut indices, mut indptr) = (Vec::new(), Vec::new(), vec![0]);
+        for row in 0..rows {
+            for (col, v) in data.get_row(row).iter().enumerate() {
+                if *v != 0.0 {
+                    values.push(*v);
+                    indices.push(col);
+                }
+            }
+            indptr.push(values.len());
+        }
+        let sparse = SparseMatrix::new_csr(&values, &indices, &indptr, rows, cols);
+
+        let b = bin_matrix(&data, None, 16, f64::NAN, None).unwrap();
+        let b_sparse = bin_sparse_matrix(&sparse, None, 16, f64::NAN, None).unwrap();
+        assert_eq!(b_sparse.cuts.data, b.cuts.data);
+        assert!(b_sparse.binned_data.is_empty());
+        let bdata = Matrix::new(&b.binned_data, rows, cols);
+        let nonzero_items = |bins: &SparseBins, col: usize| {
+            assert_eq!(
+                bins.zero_bins[col],
+                map_bin(b.cuts.get_col(col), &0.0, &f64::NAN).unwrap()
+            );
+            let nonzero: Vec<(usize, u16)> = (0..rows)
+                .map(|i| (i, *bdata.get(i, col)))
+                .filter(|(_, bin)| *bin != bins.zero_bins[col])
+                .collect();
+            assert_eq!(bins.items.get_col(col), nonzero);
+        };
+        let b_with_cuts = bin_sparse_matrix_with_cuts(&sparse, &b.cuts, f64::NAN);
+        for col in 0..cols {
+            nonzero_items(b_sparse.sparse.as_ref().unwrap(), col);
+            nonzero_items(b_with_cuts.sparse.as_ref().unwrap(), col);
+        }
+
+        // The bins of a split column are gathered for the rows of the node.
+        let mut bmatrix = BinnedMatrix::sparse(b_sparse.sparse.as_ref().unwrap(), rows);
+        let index: Vec<usize> = (0..rows).rev().collect();
+        bmatrix.set_positions(&index);
+        for col in 0..cols {
+            let split_bins = bmatrix.split_col(col, &index, 50, 150);
+            for row in &index[50..150] {
+                assert_eq!(split_bins[*row], *bdata.get(*row, col));
+            }
+        }
+    }
 }
@@ -1,25 +1,28 @@
 use crate::bin::Bin;
-use crate::binning::bin_matrix;
-use crate::constants::{
-    FREE_MEM_ALLOC_FACTOR, GENERALIZATION_THRESHOLD_RELAXED, ITER_LIMIT, MIN_COL_AMOUNT, N_NODES_ALLOC_MAX,
-    N_NODES_ALLOC_MIN, STOPPING_ROUNDS,
-};
-use crate::constraints::ConstraintMap;
-use crate::data::Matrix;
+use crate::binning::BinnedData;
+use crate::booster::callback::TrainingCallback;
+use crate::booster::multi_output::MultiOutputBooster;
+use crate::booster::rounds::{fit_rounds, newton_offset, validate_eval_sets, RoundObjective, TreeLoss, TreeTarget};
+use crate::constants::{FREE_MEM_ALLOC_FACTOR, MIN_COL_AMOUNT, N_NODES_ALLOC_MAX, N_NODES_ALLOC_MIN};
+use crate::constraints::{ConstraintMap, InteractionConstraints};
+use crate::data::{DataMatrix, JaggedMatrix, Matrix};
 use crate::errors::PerpetualError;
-use crate::histogram::{update_cuts, NodeHistogram, NodeHistogramOwned};
-use crate::objective::{calc_init_callables, gradient_hessian_callables, loss_callables, Objective};
-use crate::splitter::{MissingBranchSplitter, MissingImputerSplitter, SplitInfo, SplitInfoSlice, Splitter};
+use crate::histogram::NodeHistogramOwned;
+use crate::metric::{evaluate_metric, metric_callables, Metric};
+use crate::objective::{
+    calc_init_callables, default_metric, gradient_hessian_callables, leaf_value_callables, loss_callables,
+    rank_callables, second_order_loss_callable, survival_callables, AFTLoss, Censoring, CrossEntropy, GammaLoss,
+    InitFn, LeafFn, LossFn, ObjFn, Objective, PoissonLoss, TweedieLoss,
+};
+use crate::sampler::SampleMethod;
+use crate::splitter::{MissingBranchSplitter, MissingImputerSplitter, Splitter};
 use crate::tree::{Tree, TreeStopper};
 use core::{f32, f64};
-use log::{info, warn};
-use rand::rngs::StdRng;
-use rand::seq::IteratorRandom;
-use rand::SeedableRng;
 use rayon::prelude::*;
+use rayon::ThreadPool;
 use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
-use std::time::Instant;
 use std::{fs, mem};
 use sysinfo::System;
 
@@ -70,13 +73,102 @@ pub enum MissingNodeTreatment {
     AverageNodeWeight,
 }
 
+/// Named dataset evaluated after every boosting round, when passed to `fit_with_eval`.
+pub struct EvalSet<'a, D = Matrix<'a, f64>> {
+    /// Name of the set in the evaluation history.
+    pub name: &'a str,
+    pub data: &'a D,
+    pub y: &'a [f64],
+    pub sample_weight: Option<&'a [f64]>,
+    /// Query id of each record, the ranking metrics are averaged over the queries.
+    pub group: Option<&'a [u64]>,
+    /// Whether the event was observed for each record, for the survival metrics. Every event is
+    /// observed if `None`.
+    pub event: Option<&'a [bool]>,
+}
+
+/// Reason the last fit stopped adding trees.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StopReason {
+    /// The generalization control stopped the trees from growing for `stopping_rounds` rounds.
+    AutoStopping,
+    /// The root node of the last tree could not be split.
+    NoSplit,
+    /// The evaluation metric did not improve for `early_stopping_rounds` rounds.
+    EarlyStopping,
+    /// A training callback requested the stop.
+    Callback,
+    /// The fit took longer than `timeout`.
+    Timeout,
+    /// The number of rounds reached `iteration_limit`.
+    IterationLimit,
+}
+
+/// State of a fit after a boosting round, besides the trees, to resume the fit from a checkpoint.
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct FitState {
+    /// Number of rounds completed.
+    pub(crate) round: usize,
+    pub(crate) stopping: usize,
+    pub(crate) n_low_loss_rounds: usize,
+    /// Number of trees and evaluation metric at the best round of the evaluation sets.
+    pub(crate) best_round: Option<(usize, f64)>,
+    /// Reason the fit stopped after the last round, if it did.
+    pub(crate) stop: Option<StopReason>,
+    /// Number of trees the booster had before the fit.
+    pub(crate) n_trees_start: usize,
+    /// Whether the fit continued the training of the booster.
+    pub(crate) continued: bool,
+}
+
+/// Stopping counters at the end of a fit, carried on by a fit that continues it on a new batch of data,
+/// so that the stopping rules span all the batches.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+pub(crate) struct StoppingState {
+    pub(crate) stopping: usize,
+    pub(crate) n_low_loss_rounds: usize,
+}
+
+/// Summary of a boosting round, recorded in the training history of the booster.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RoundRecord {
+    /// Index of the round, the index of its tree in the booster. The rounds of a continued fit
+    /// follow those of the fits before it.
+    pub round: usize,
+    /// Mean training loss, after the tree is added.
+    pub loss: f32,
+    /// Mean loss of the rows left out of the sample of the round, after the tree is added.
+    #[serde(default)]
+    pub oob_loss: Option<f32>,
+    /// Number of nodes of the tree.
+    pub n_nodes: usize,
+    /// Depth of the tree.
+    pub depth: usize,
+    /// The reason the tree stopped growing.
+    pub stopper: TreeStopper,
+    /// Seconds elapsed since the fit started.
+    pub elapsed: f32,
+    /// Evaluation metric of each evaluation set, keyed by the set name.
+    #[serde(default)]
+    pub eval_metrics: HashMap<String, f64>,
+}
+
 /// Perpetual Booster object
 #[derive(Deserialize, Serialize, Clone)]
 pub struct PerpetualBooster {
     /// The name of objective function used to optimize. Valid options are:
     /// "LogLoss" to use logistic loss as the objective function,
+    /// "FocalLoss" for imbalanced binary classification,
+    /// "CrossEntropy" for soft labels between 0 and 1,
+    /// "Distributional" for distributional regression, only valid with `MultiOutputBooster`,
     /// "SquaredLoss" to use Squared Error as the objective function,
-    /// "QuantileLoss" for quantile regression.
+    /// "QuantileLoss" for quantile regression,
+    /// "Softmax" for multiclass classification, only valid with `MultiOutputBooster`,
+    /// "PoissonLoss", "GammaLoss" and "TweedieLoss" for log link regression,
+    /// "AbsoluteLoss", "HuberLoss" and "PseudoHuberLoss" for regression robust to outliers,
+    /// "RankNetLoss" and "LambdaRankLoss" for learning to rank, with the query ids passed to `fit_ranking`,
+    /// "CoxLoss" and "AFTLoss" for survival times, with censoring passed to `fit_survival`,
+    /// "Custom" for a user defined objective, created with `Objective::custom`.
     pub objective: Objective,
     /// Budget to fit the model.
     #[serde(default = "default_budget")]
@@ -93,6 +185,10 @@ pub struct PerpetualBooster {
     /// Constraints that are used to enforce a specific relationship
     /// between the training features and the target variable.
     pub monotone_constraints: Option<ConstraintMap>,
+    /// Groups of features allowed to interact, that is to be split on along the same path of a tree.
+    /// A feature outside of every group only interacts with itself, any features may interact if `None`.
+    #[serde(default = "default_interaction_constraints")]
+    pub interaction_constraints: Option<InteractionConstraints>,
     /// Should the children nodes contain the parent node in their bounds, setting this to true, will result in no children being created that result in the higher and lower child values both being greater than, or less than the parent weight.
     #[serde(default = "default_force_children_to_bound_parent")]
     pub force_children_to_bound_parent: bool,
@@ -130,12 +226,43 @@ pub struct PerpetualBooster {
     /// Used only in quantile regression.
     #[serde(default = "default_quantile")]
     pub quantile: Option<f64>,
+    /// Weight of the positive class relative to the negative class, used only in binary classification.
+    #[serde(default = "default_scale_pos_weight")]
+    pub scale_pos_weight: Option<f64>,
+    /// Method to sample the rows each tree is fit on.
+    #[serde(default = "default_sample_method")]
+    pub sample_method: SampleMethod,
+    /// Fraction of the rows sampled for each tree, used with the `Random` sample method.
+    #[serde(default = "default_subsample")]
+    pub subsample: f32,
+    /// Fraction of the rows with the largest gradients kept for each tree, used with the `Goss` sample method.
+    #[serde(default = "default_top_rate")]
+    pub top_rate: f32,
+    /// Fraction of the rows sampled from the rest, used with the `Goss` sample method.
+    #[serde(default = "default_other_rate")]
+    pub other_rate: f32,
+    /// Fraction of the columns sampled for each tree, chosen from the budget and the shape of the data if `None`.
+    #[serde(default = "default_colsample_bytree")]
+    pub colsample_bytree: Option<f32>,
+    /// Fraction of the columns of the tree sampled for each node, all of them if `None`.
+    #[serde(default = "default_colsample_bynode")]
+    pub colsample_bynode: Option<f32>,
+    /// Sampling weight of every feature, used when the columns are sampled.
+    #[serde(default = "default_feature_weights")]
+    pub feature_weights: Option<Vec<f64>>,
+    /// Features part of every column sample, of the trees and of the nodes.
+    #[serde(default = "default_always_include_features")]
+    pub always_include_features: Option<HashSet<usize>>,
     /// Reset the model or continue training.
     #[serde(default = "default_reset")]
     pub reset: Option<bool>,
     /// Features to be treated as categorical.
     #[serde(default = "default_categorical_features")]
     pub categorical_features: Option<HashSet<usize>>,
+    /// Names of the features, taken from the data the booster is fit on if not set.
+    /// Data with named columns must have the same names to be predicted.
+    #[serde(default = "default_feature_names")]
+    pub feature_names: Option<Vec<String>>,
     /// Fit timeout limit in seconds.
     #[serde(default = "default_timeout")]
     pub timeout: Option<f32>,
@@ -152,12 +279,49 @@ pub struct PerpetualBooster {
     /// Optional limit for auto stopping rounds.
     #[serde(default = "default_stopping_rounds")]
     pub stopping_rounds: Option<usize>,
-    /// Calibration models for conformal prediction. Created with `calibrate` method.
-    #[serde(default = "default_cal_models")]
-    pub(crate) cal_models: HashMap<String, [(PerpetualBooster, f64); 2]>,
+    /// Metric computed on the evaluation sets passed to `fit_with_eval`.
+    /// The default metric of the objective is used if None.
+    #[serde(default = "default_eval_metric")]
+    pub eval_metric: Option<Metric>,
+    /// Stop fitting when the evaluation metric on the last evaluation set has not improved
+    /// for this many rounds, and keep only the trees up to its best round.
+    #[serde(default = "default_early_stopping_rounds")]
+    pub early_stopping_rounds: Option<usize>,
+    /// Evaluation metric of each evaluation set after every boosting round, keyed by the set name.
+    #[serde(default = "default_evaluation_history")]
+    pub evaluation_history: HashMap<String, Vec<f64>>,
+    /// Number of trees at the round with the best evaluation metric, set when early stopping.
+    #[serde(default = "default_best_iteration")]
+    pub best_iteration: Option<usize>,
+    /// Summary of every boosting round, in the order the trees were fit.
+    /// Rounds of trees removed by early stopping are kept.
+    #[serde(default = "default_training_history")]
+    pub training_history: Vec<RoundRecord>,
+    /// Reason the last fit stopped adding trees.
+    #[serde(default = "default_stop_reason")]
+    pub stop_reason: Option<StopReason>,
+    /// Bin cuts of the data the booster was first fit on, reused to bin the data of continued fits.
+    #[serde(default = "default_cuts")]
+    pub(crate) cuts: Option<JaggedMatrix<f64>>,
+    /// State of the fit in progress, written in checkpoints and used by `resume_fit`.
+    #[serde(default = "default_fit_state")]
+    pub(crate) fit_state: Option<FitState>,
+    /// Stopping counters at the end of the last fit, carried on by continued fits.
+    #[serde(default = "default_stopping_state")]
+    pub(crate) stopping_state: StoppingState,
+    /// Quantile model for conformal prediction, fit on the quantiles of all the calibrated alphas.
+    /// Created with `calibrate` method.
+    #[serde(default = "default_cal_model")]
+    pub(crate) cal_model: Option<Box<MultiOutputBooster>>,
+    /// Indices of the lower and upper quantiles of each alpha in `cal_model`, with their conformity scores.
+    #[serde(default = "default_cal_scores")]
+    pub(crate) cal_scores: HashMap<String, [(usize, f64); 2]>,
 }
 
-fn default_cal_models() -> HashMap<String, [(PerpetualBooster, f64); 2]> {
+fn default_cal_model() -> Option<Box<MultiOutputBooster>> {
+    None
+}
+fn default_cal_scores() -> HashMap<String, [(usize, f64); 2]> {
     HashMap::new()
 }
 fn default_budget() -> f32 {
@@ -166,12 +330,45 @@ fn default_budget() -> f32 {
 fn default_quantile() -> Option<f64> {
     None
 }
+fn default_scale_pos_weight() -> Option<f64> {
+    None
+}
+fn default_sample_method() -> SampleMethod {
+    SampleMethod::None
+}
+fn default_subsample() -> f32 {
+    1.0
+}
+fn default_top_rate() -> f32 {
+    0.2
+}
+fn default_other_rate() -> f32 {
+    0.1
+}
+fn default_interaction_constraints() -> Option<InteractionConstraints> {
+    None
+}
+fn default_colsample_bytree() -> Option<f32> {
+    None
+}
+fn default_colsample_bynode() -> Option<f32> {
+    None
+}
+fn default_feature_weights() -> Option<Vec<f64>> {
+    None
+}
+fn default_always_include_features() -> Option<HashSet<usize>> {
+    None
+}
 fn default_reset() -> Option<bool> {
     None
 }
 fn default_categorical_features() -> Option<HashSet<usize>> {
     None
 }
+fn default_feature_names() -> Option<Vec<String>> {
+    None
+}
 fn default_timeout() -> Option<f32> {
     None
 }
@@ -184,6 +381,33 @@ fn default_memory_limit() -> Option<f32> {
 fn default_stopping_rounds() -> Option<usize> {
     None
 }
+fn default_eval_metric() -> Option<Metric> {
+    None
+}
+fn default_early_stopping_rounds() -> Option<usize> {
+    None
+}
+fn default_evaluation_history() -> HashMap<String, Vec<f64>> {
+    HashMap::new()
+}
+fn default_best_iteration() -> Option<usize> {
+    None
+}
+fn default_training_history() -> Vec<RoundRecord> {
+    Vec::new()
+}
+fn default_stop_reason() -> Option<StopReason> {
+    None
+}
+fn default_cuts() -> Option<JaggedMatrix<f64>> {
+    None
+}
+fn default_fit_state() -> Option<FitState> {
+    None
+}
+fn default_stopping_state() -> StoppingState {
+    StoppingState::default()
+}
 fn default_terminate_missing_features() -> HashSet<usize> {
     HashSet::new()
 }
@@ -237,8 +461,17 @@ impl PerpetualBooster {
     ///
     /// * `objective` - The name of objective function used to optimize. Valid options are:
     ///      "LogLoss" to use logistic loss as the objective function,
+    ///      "FocalLoss" for imbalanced binary classification,
+    ///      "CrossEntropy" for soft labels between 0 and 1,
+    ///      "Distributional" for distributional regression, only valid with `MultiOutputBooster`,
     ///      "SquaredLoss" to use Squared Error as the objective function,
-    ///      "QuantileLoss" for quantile regression.
+    ///      "QuantileLoss" for quantile regression,
+    ///      "Softmax" for multiclass classification, only valid with `MultiOutputBooster`,
+    ///      "PoissonLoss", "GammaLoss" and "TweedieLoss" for log link regression,
+    ///      "AbsoluteLoss", "HuberLoss" and "PseudoHuberLoss" for regression robust to outliers,
+    ///      "RankNetLoss" and "LambdaRankLoss" for learning to rank, with the query ids passed to `fit_ranking`,
+    ///      "CoxLoss" and "AFTLoss" for survival times, with censoring passed to `fit_survival`,
+    ///      "Custom" for a user defined objective, created with `Objective::custom`.
     /// * `budget` - budget to fit the model.
     /// * `base_score` - The initial prediction value of the model. If set to None, it will be calculated based on the objective function at fit time.
     /// * `max_bin` - Number of bins to calculate to partition the data. Setting this to
@@ -295,6 +528,7 @@ impl PerpetualBooster {
             max_bin,
             num_threads,
             monotone_constraints,
+            interaction_constraints: None,
             force_children_to_bound_parent,
             missing,
             allow_missing_splits,
@@ -307,13 +541,33 @@ impl PerpetualBooster {
             eta: f32::NAN,
             seed,
             quantile,
+            scale_pos_weight: None,
+            sample_method: SampleMethod::None,
+            subsample: default_subsample(),
+            top_rate: default_top_rate(),
+            other_rate: default_other_rate(),
+            colsample_bytree: None,
+            colsample_bynode: None,
+            feature_weights: None,
+            always_include_features: None,
             reset,
             categorical_features,
+            feature_names: None,
             timeout,
             iteration_limit,
             memory_limit,
             stopping_rounds,
-            cal_models: HashMap::new(),
+            eval_metric: None,
+            early_stopping_rounds: None,
+            evaluation_history: HashMap::new(),
+            best_iteration: None,
+            training_history: Vec::new(),
+            stop_reason: None,
+            cuts: None,
+            fit_state: None,
+            stopping_state: StoppingState::default(),
+            cal_model: None,
+            cal_scores: HashMap::new(),
         };
 
         booster.validate_parameters()?;
@@ -322,11 +576,70 @@ impl PerpetualBooster {
     }
 
     pub fn validate_parameters(&self) -> Result<(), PerpetualError> {
+        self.objective.validate()?;
+        match self.sample_method {
+            SampleMethod::Random if !(self.subsample > 0.0 && self.subsample <= 1.0) => {
+                return Err(PerpetualError::InvalidParameter(
+                    "subsample".to_string(),
+                    "a fraction in (0, 1]".to_string(),
+                    self.subsample.to_string(),
+                ));
+            }
+            SampleMethod::Goss if !(self.top_rate >= 0.0 && self.top_rate < 1.0) => {
+                return Err(PerpetualError::InvalidParameter(
+                    "top_rate".to_string(),
+                    "a fraction in [0, 1)".to_string(),
+                    self.top_rate.to_string(),
+                ));
+            }
+            SampleMethod::Goss if !(self.other_rate > 0.0 && self.top_rate + self.other_rate <= 1.0) => {
+                return Err(PerpetualError::InvalidParameter(
+                    "other_rate".to_string(),
+                    format!("a fraction in (0, {}]", 1.0 - self.top_rate),
+                    self.other_rate.to_string(),
+                ));
+            }
+            _ => (),
+        }
+        for (name, rate) in [
+            ("colsample_bytree", self.colsample_bytree),
+            ("colsample_bynode", self.colsample_bynode),
+        ] {
+            if let Some(rate) = rate.filter(|r| !(*r > 0.0 && *r <= 1.0)) {
+                return Err(PerpetualError::InvalidParameter(
+                    name.to_string(),
+                    "a fraction in (0, 1]".to_string(),
+                    rate.to_string(),
+                ));
+            }
+        }
+        if let Some(scale_pos_weight) = self.scale_pos_weight {
+            if !matches!(self.objective, Objective::LogLoss | Objective::FocalLoss { .. }) {
+                return Err(PerpetualError::InvalidParameter(
+                    "scale_pos_weight".to_string(),
+                    "a binary classification objective".to_string(),
+                    format!("{:?}", self.objective),
+                ));
+            }
+            if !(scale_pos_weight.is_finite() && scale_pos_weight > 0.0) {
+                return Err(PerpetualError::InvalidParameter(
+                    "scale_pos_weight".to_string(),
+                    "a positive value".to_string(),
+                    scale_pos_weight.to_string(),
+                ));
+            }
+        }
         Ok(())
     }
 
     pub fn reset(&mut self) {
         self.trees = Vec::new();
+        self.evaluation_history = HashMap::new();
+        self.best_iteration = None;
+        self.training_history = Vec::new();
+        self.stop_reason = None;
+        self.cuts = None;
+        self.stopping_state = StoppingState::default();
     }
 
     /// Fit the gradient booster on a provided dataset.
@@ -334,291 +647,522 @@ impl PerpetualBooster {
     /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
     /// * `y` - Either a Polars or Pandas Series, or a 1 dimensional Numpy array.
     /// * `sample_weight` - Instance weights to use when training the model.
-    pub fn fit(&mut self, data: &Matrix<f64>, y: &[f64], sample_weight: Option<&[f64]>) -> Result<(), PerpetualError> {
-        let constraints_map = self
-            .monotone_constraints
-            .as_ref()
-            .unwrap_or(&ConstraintMap::new())
-            .to_owned();
+    pub fn fit<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+    ) -> Result<(), PerpetualError> {
+        self.fit_with_eval(data, y, sample_weight, &[])
+    }
 
-        self.set_eta(self.budget);
+    /// Fit the gradient booster, computing the evaluation metric on each evaluation set after every
+    /// boosting round. The metric values are recorded in `evaluation_history`, and with
+    /// `early_stopping_rounds` set, the last evaluation set decides when to stop.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Either a Polars or Pandas Series, or a 1 dimensional Numpy array.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    pub fn fit_with_eval<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+    ) -> Result<(), PerpetualError> {
+        self.fit_with_callbacks(data, y, sample_weight, eval_sets, &mut [])
+    }
 
-        if self.create_missing_branch {
-            let splitter = MissingBranchSplitter::new(
-                self.eta,
-                self.allow_missing_splits,
-                constraints_map,
-                self.terminate_missing_features.clone(),
-                self.missing_node_treatment,
-                self.force_children_to_bound_parent,
-            );
-            self.fit_trees(data, y, &splitter, sample_weight)?;
-        } else {
-            let splitter = MissingImputerSplitter::new(self.eta, self.allow_missing_splits, constraints_map);
-            self.fit_trees(data, y, &splitter, sample_weight)?;
-        };
+    /// Fit the gradient booster, invoking the callbacks before and after every boosting round.
+    /// Any callback can stop the fit, keeping the trees fit so far.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Either a Polars or Pandas Series, or a 1 dimensional Numpy array.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    /// * `callbacks` - Observers of the boosting rounds.
+    pub fn fit_with_callbacks<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        self.fit_state = None;
+        self.fit_grouped(data, y, sample_weight, None, eval_sets, callbacks)
+    }
 
-        Ok(())
+    /// Fit the gradient booster on the query groups of the records, with the `RankNetLoss` or
+    /// `LambdaRankLoss` objective.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Relevance of each record to its query.
+    /// * `group` - Query id of each record.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    pub fn fit_ranking<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        group: &[u64],
+        sample_weight: Option<&[f64]>,
+    ) -> Result<(), PerpetualError> {
+        self.fit_ranking_with_callbacks(data, y, group, sample_weight, &[], &mut [])
     }
 
-    fn fit_trees<T: Splitter>(
+    /// Fit the gradient booster on the query groups of the records, with evaluation sets and
+    /// callbacks, see `fit_with_callbacks`.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Relevance of each record to its query.
+    /// * `group` - Query id of each record.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    /// * `callbacks` - Observers of the boosting rounds.
+    pub fn fit_ranking_with_callbacks<D: DataMatrix>(
         &mut self,
-        data: &Matrix<f64>,
+        data: &D,
         y: &[f64],
-        splitter: &T,
+        group: &[u64],
         sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
     ) -> Result<(), PerpetualError> {
-        let start = Instant::now();
+        if !self.objective.is_ranking() {
+            return Err(PerpetualError::InvalidParameter(
+                "objective".to_string(),
+                "a ranking objective, RankNetLoss or LambdaRankLoss".to_string(),
+                format!("{:?}", self.objective),
+            ));
+        }
+        self.fit_state = None;
+        self.fit_grouped(data, y, sample_weight, Some(group), eval_sets, callbacks)
+    }
 
-        let n_threads_available = std::thread::available_parallelism().unwrap().get();
-        let num_threads = match self.num_threads {
-            Some(num_threads) => num_threads,
-            None => n_threads_available,
-        };
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(num_threads)
-            .build()
-            .unwrap();
+    /// Continue a fit interrupted after a checkpoint, written with the `Checkpoint` callback and loaded
+    /// with `load_booster`. The fit continues to the same trees an uninterrupted fit would have produced,
+    /// given the same arguments as the interrupted fit.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Either a Polars or Pandas Series, or a 1 dimensional Numpy array.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `group` - Query id of each record, as passed to `fit_ranking_with_callbacks`, `None` for other fits.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    /// * `callbacks` - Observers of the boosting rounds.
+    pub fn resume_fit<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        group: Option<&[u64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        self.validate_fit_state()?;
+        if self.objective.is_survival() {
+            return Err(PerpetualError::InvalidParameter(
+                "objective".to_string(),
+                "an objective fit with fit, survival fits are resumed with resume_survival_fit".to_string(),
+                format!("{:?}", self.objective),
+            ));
+        }
+        self.fit_grouped(data, y, sample_weight, group, eval_sets, callbacks)
+    }
+
+    /// Continue a survival fit interrupted after a checkpoint, see `resume_fit`. The censoring is
+    /// the one passed to the interrupted `fit_survival_with_callbacks`.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Survival time of each record, or its lower bound for censored records.
+    /// * `censoring` - Event indicators, or upper bounds of the survival times.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    /// * `callbacks` - Observers of the boosting rounds.
+    pub fn resume_survival_fit<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        censoring: Censoring,
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        self.validate_fit_state()?;
+        self.fit_censored(data, y, censoring, sample_weight, eval_sets, callbacks)
+    }
 
-        let calc_loss = loss_callables(&self.objective);
+    fn validate_fit_state(&self) -> Result<(), PerpetualError> {
+        if self.fit_state.is_none() {
+            return Err(PerpetualError::InvalidParameter(
+                "booster".to_string(),
+                "a checkpoint written during a fit".to_string(),
+                "a booster without fit state".to_string(),
+            ));
+        }
+        Ok(())
+    }
 
-        // If reset, reset the trees. Otherwise continue training.
-        let mut yhat;
-        if self.reset.unwrap_or(true) || self.trees.len() == 0 {
-            self.reset();
-            if self.base_score.is_nan() {
-                self.base_score = calc_init_callables(&self.objective)(y, sample_weight, self.quantile);
+    fn fit_grouped<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        group: Option<&[u64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        match group {
+            None if self.objective.is_ranking() => {
+                return Err(PerpetualError::InvalidParameter(
+                    "group".to_string(),
+                    "the query id of each record for a ranking objective, fit with fit_ranking".to_string(),
+                    "None".to_string(),
+                ));
             }
-            yhat = vec![self.base_score; y.len()];
-        } else {
-            yhat = self.predict(data, true);
+            Some(group) if group.len() != y.len() => {
+                return Err(PerpetualError::InvalidParameter(
+                    "group".to_string(),
+                    format!("{} query ids", y.len()),
+                    group.len().to_string(),
+                ));
+            }
+            _ => (),
         }
 
-        let calc_grad_hess = gradient_hessian_callables(&self.objective);
-        let (mut grad, mut hess) = calc_grad_hess(y, &yhat, sample_weight, self.quantile);
+        let label_callables = group.and_then(|g| rank_callables(&self.objective, g));
+        self.fit_objective(data, y, sample_weight, label_callables, eval_sets, callbacks)
+    }
 
-        let mut loss = calc_loss(y, &yhat, sample_weight, self.quantile);
+    /// Fit the gradient booster on censored survival times, with the `CoxLoss` or `AFTLoss` objective.
+    /// Records with an observed event can also be fit with `fit`.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Survival time of each record, or its lower bound for censored records.
+    /// * `censoring` - Event indicators, or upper bounds of the survival times.
+    ///   `CoxLoss` only supports right censoring.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    pub fn fit_survival<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        censoring: Censoring,
+        sample_weight: Option<&[f64]>,
+    ) -> Result<(), PerpetualError> {
+        self.fit_survival_with_callbacks(data, y, censoring, sample_weight, &[], &mut [])
+    }
 
-        let loss_base = calc_loss(y, &vec![self.base_score; y.len()], sample_weight, self.quantile);
-        let loss_avg = loss_base.iter().sum::<f32>() / loss_base.len() as f32;
+    /// Fit the gradient booster on censored survival times, with evaluation sets and callbacks,
+    /// see `fit_with_callbacks`. The survival metrics of an evaluation set account for its censored
+    /// records through `EvalSet::event`.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Survival time of each record, or its lower bound for censored records.
+    /// * `censoring` - Event indicators, or upper bounds of the survival times.
+    ///   `CoxLoss` only supports right censoring.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    /// * `callbacks` - Observers of the boosting rounds.
+    pub fn fit_survival_with_callbacks<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        censoring: Censoring,
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        self.fit_state = None;
+        self.fit_censored(data, y, censoring, sample_weight, eval_sets, callbacks)
+    }
 
-        let base = 10.0_f32;
-        let n = base / self.budget;
-        let reciprocals_of_powers = n / (n - 1.0);
-        let truncated_series_sum = reciprocals_of_powers - (1.0 + 1.0 / n);
-        let c = 1.0 / n - truncated_series_sum;
-        let target_loss_decrement = c * base.powf(-self.budget) * loss_avg;
+    fn fit_censored<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        censoring: Censoring,
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        if !self.objective.is_survival() {
+            return Err(PerpetualError::InvalidParameter(
+                "objective".to_string(),
+                "a survival objective, CoxLoss or AFTLoss".to_string(),
+                format!("{:?}", self.objective),
+            ));
+        }
+        let y_upper = censoring.upper_bounds(y);
+        if y_upper.len() != y.len() {
+            return Err(PerpetualError::InvalidParameter(
+                "censoring".to_string(),
+                format!("{} values", y.len()),
+                y_upper.len().to_string(),
+            ));
+        }
+        if let Some((y_, u_)) = y
+            .iter()
+            .zip(&y_upper)
+            .find(|(y_, u_)| u_.partial_cmp(y_).is_none_or(|o| o.is_lt()))
+        {
+            return Err(PerpetualError::InvalidParameter(
+                "censoring".to_string(),
+                format!("an upper bound not below the survival time {}", y_),
+                u_.to_string(),
+            ));
+        }
+        if let Objective::CoxLoss = self.objective {
+            if let Some(u_) = y_upper
+                .iter()
+                .zip(y)
+                .find(|(u_, y_)| u_.is_finite() && *u_ != *y_)
+                .map(|(u_, _)| u_)
+            {
+                return Err(PerpetualError::InvalidParameter(
+                    "censoring".to_string(),
+                    "right censoring, with infinite upper bounds for CoxLoss".to_string(),
+                    u_.to_string(),
+                ));
+            }
+        }
 
-        let is_const_hess = match sample_weight {
-            Some(_sample_weight) => false,
-            None => match &self.objective {
-                Objective::LogLoss => false,
-                _ => true,
-            },
-        };
+        let label_callables = survival_callables(&self.objective, &y_upper);
+        self.fit_objective(data, y, sample_weight, label_callables, eval_sets, callbacks)
+    }
 
-        // Generate binned data
-        //
-        // In scikit-learn, they sample 200_000 records for generating the bins.
-        // we could consider that, especially if this proved to be a large bottleneck...
-        let binned_data = bin_matrix(
-            data,
-            sample_weight,
-            self.max_bin,
-            self.missing,
-            self.categorical_features.as_ref(),
-        )?;
-        let bdata = Matrix::new(&binned_data.binned_data, data.rows, data.cols);
-
-        let col_index: Vec<usize> = (0..data.cols).collect();
-        let mut stopping = 0 as usize;
-        let mut n_low_loss_rounds = 0;
-
-        let mut rng = StdRng::seed_from_u64(self.seed);
-
-        // Column sampling is only applied when (n_rows / n_columns) < ROW_COLUMN_RATIO_LIMIT.
-        // ROW_COLUMN_RATIO_LIMIT is calculated using budget.
-        // budget = 1.0 -> ROW_COLUMN_RATIO_LIMIT = 100
-        // budget = 2.0 -> ROW_COLUMN_RATIO_LIMIT = 10
-        let row_column_ratio_limit = 10.0_f32.powf(-self.budget) * 1000.0;
-        let colsample_bytree = (data.rows as f32 / data.cols as f32) / row_column_ratio_limit;
-
-        let col_amount = (((col_index.len() as f32) * colsample_bytree).floor() as usize)
-            .clamp(usize::min(MIN_COL_AMOUNT, col_index.len()), col_index.len());
-
-        let mem_bin = mem::size_of::<Bin>();
-        let mem_hist: usize;
-        if col_amount == col_index.len() {
-            mem_hist = mem_bin * binned_data.nunique.iter().sum::<usize>();
-        } else {
-            mem_hist = mem_bin * self.max_bin as usize * col_amount;
+    /// Fit the trees, with the loss and gradient callables of objectives that need more than
+    /// the target of each record, or the callables of the objective otherwise.
+    fn fit_objective<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        label_callables: Option<(LossFn, ObjFn)>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        self.validate_parameters()?;
+        validate_eval_sets(self, data, eval_sets, 1)?;
+        self.validate_features(data.cols())?;
+        self.validate_feature_names(data)?;
+        if self.feature_names.is_none() {
+            self.feature_names = data.feature_names().map(|names| names.to_vec());
         }
-        let sys = System::new_all();
-        let mem_available = match self.memory_limit {
-            Some(mem_limit) => mem_limit * (1e9 as f32),
-            None => match sys.cgroup_limits() {
-                Some(limits) => limits.free_memory as f32,
-                None => sys.available_memory() as f32,
-            },
-        };
-
-        let mut n_nodes_alloc: usize;
-        if self.memory_limit.is_none() {
-            n_nodes_alloc = (FREE_MEM_ALLOC_FACTOR * (mem_available / (mem_hist as f32))) as usize;
-            n_nodes_alloc = n_nodes_alloc.clamp(N_NODES_ALLOC_MIN, N_NODES_ALLOC_MAX);
-        } else {
-            n_nodes_alloc = (FREE_MEM_ALLOC_FACTOR * (mem_available / (mem_hist as f32))) as usize;
+        if let Objective::Softmax | Objective::Distributional { .. } = self.objective {
+            return Err(PerpetualError::InvalidParameter(
+                "objective".to_string(),
+                "a single output objective, use MultiOutputBooster for Softmax and Distributional".to_string(),
+                format!("{:?}", self.objective),
+            ));
         }
-
-        let mut hist_tree_owned: Vec<NodeHistogramOwned>;
-        if col_amount == col_index.len() {
-            hist_tree_owned = (0..n_nodes_alloc)
-                .map(|_| NodeHistogramOwned::empty_from_cuts(&binned_data.cuts, &col_index, is_const_hess, false))
-                .collect();
-        } else {
-            hist_tree_owned = (0..n_nodes_alloc)
-                .map(|_| NodeHistogramOwned::empty(self.max_bin, col_amount, is_const_hess, false))
-                .collect();
+        if let Some(metric @ (Metric::NegativeLogLikelihood | Metric::CRPS)) = self.eval_metric {
+            return Err(PerpetualError::InvalidParameter(
+                "eval_metric".to_string(),
+                "a single output metric, NegativeLogLikelihood and CRPS need the Distributional objective".to_string(),
+                format!("{:?}", metric),
+            ));
         }
 
-        let mut hist_tree: Vec<NodeHistogram> = hist_tree_owned
-            .iter_mut()
-            .map(|node_hist| NodeHistogram::from_owned(node_hist))
-            .collect();
+        match self.objective {
+            Objective::CrossEntropy => CrossEntropy::validate_labels(y)?,
+            Objective::PoissonLoss => PoissonLoss::validate_labels(y)?,
+            Objective::GammaLoss => GammaLoss::validate_labels(y)?,
+            Objective::TweedieLoss { .. } => TweedieLoss::validate_labels(y)?,
+            Objective::AFTLoss { .. } => AFTLoss::validate_labels(y)?,
+            _ => (),
+        }
 
-        let mut split_info_vec: Vec<SplitInfo> = (0..col_amount).map(|_| SplitInfo::default()).collect();
-        let split_info_slice = SplitInfoSlice::new(&mut split_info_vec);
-
-        for i in 0..self.iteration_limit.unwrap_or(ITER_LIMIT) {
-            let verbose = if self.log_iterations == 0 {
-                false
-            } else {
-                i % self.log_iterations == 0
-            };
-
-            let tld = if n_low_loss_rounds > (self.stopping_rounds.unwrap_or(STOPPING_ROUNDS) + 1) {
-                None
-            } else {
-                Some(target_loss_decrement)
-            };
-
-            let col_index_sample: Vec<usize> = if col_amount == col_index.len() {
-                Vec::new()
-            } else {
-                let mut v: Vec<usize> = col_index
-                    .iter()
-                    .choose_multiple(&mut rng, col_amount)
-                    .iter()
-                    .map(|i| **i)
-                    .collect();
-                v.sort();
-                v
-            };
-
-            let col_index_fit = if col_amount == col_index.len() {
-                &col_index
-            } else {
-                &col_index_sample
-            };
-
-            if col_amount != col_index.len() {
-                hist_tree.iter().for_each(|h| {
-                    update_cuts(h, col_index_fit, &binned_data.cuts, true);
+        // The class weight is folded into the sample weight, so that it reaches the gradients,
+        // the loss and the base score alike.
+        let class_weight: Option<Vec<f64>> = self.scale_pos_weight.map(|scale_pos_weight| {
+            y.iter()
+                .enumerate()
+                .map(|(i, y_)| {
+                    let w = sample_weight.map_or(1.0, |w| w[i]);
+                    if *y_ >= 0.5 {
+                        w * scale_pos_weight
+                    } else {
+                        w
+                    }
                 })
-            }
-
-            let mut tree = Tree::new();
-            tree.fit(
-                &bdata,
-                data.index.to_owned(),
-                &col_index_fit,
-                &mut grad,
-                hess.as_deref_mut(),
-                splitter,
-                &pool,
-                tld,
-                &loss,
-                y,
-                calc_loss,
-                &yhat,
-                sample_weight,
-                self.quantile,
-                is_const_hess,
-                &mut hist_tree,
-                self.categorical_features.as_ref(),
-                &split_info_slice,
-                n_nodes_alloc,
-            );
+                .collect()
+        });
+        let sample_weight = class_weight.as_deref().or(sample_weight);
 
-            self.update_predictions_inplace(&mut yhat, &tree, data);
-
-            if tree.nodes.len() < 5 {
-                let generalization = tree
-                    .nodes
-                    .values()
-                    .map(|n| n.generalization.unwrap_or(0.0))
-                    .max_by(|a, b| a.total_cmp(b))
-                    .unwrap_or(0.0);
-                if generalization < GENERALIZATION_THRESHOLD_RELAXED && tree.stopper != TreeStopper::StepSize {
-                    stopping += 1;
-                    // If root node cannot be split due to no positive split gain, stop boosting.
-                    if tree.nodes.len() == 1 {
-                        break;
-                    }
-                }
-            }
+        let constraints_map = self
+            .monotone_constraints
+            .as_ref()
+            .unwrap_or(&ConstraintMap::new())
+            .to_owned();
 
-            if tree.stopper != TreeStopper::StepSize {
-                n_low_loss_rounds += 1;
-            } else {
-                n_low_loss_rounds = 0;
-            }
+        self.set_eta(self.budget);
 
-            (grad, hess) = calc_grad_hess(y, &yhat, sample_weight, self.quantile);
-            loss = calc_loss(y, &yhat, sample_weight, self.quantile);
-
-            if verbose {
-                info!(
-                    "round {:0?}, tree.nodes: {:1?}, tree.depth: {:2?}, tree.stopper: {:3?}, loss: {:4?}",
-                    i,
-                    tree.nodes.len(),
-                    tree.depth,
-                    tree.stopper,
-                    loss.iter().sum::<f32>() / loss.len() as f32,
-                );
-            }
+        if self.create_missing_branch {
+            let splitter = MissingBranchSplitter::new(
+                self.eta,
+                self.allow_missing_splits,
+                constraints_map,
+                self.terminate_missing_features.clone(),
+                self.missing_node_treatment,
+                self.force_children_to_bound_parent,
+            )
+            .set_interaction_constraints(self.interaction_constraints.clone());
+            self.fit_trees(data, y, &splitter, sample_weight, label_callables, eval_sets, callbacks)?;
+        } else {
+            let splitter = MissingImputerSplitter::new(self.eta, self.allow_missing_splits, constraints_map)
+                .set_interaction_constraints(self.interaction_constraints.clone());
+            self.fit_trees(data, y, &splitter, sample_weight, label_callables, eval_sets, callbacks)?;
+        };
 
-            self.trees.push(tree);
+        Ok(())
+    }
 
-            if stopping >= self.stopping_rounds.unwrap_or(STOPPING_ROUNDS) {
-                info!("Auto stopping since stopping round limit reached.");
-                break;
-            }
+    #[allow(clippy::too_many_arguments)]
+    fn fit_trees<S: Splitter, D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        splitter: &S,
+        sample_weight: Option<&[f64]>,
+        label_callables: Option<(LossFn, ObjFn)>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        // The loss of a record depends on more than its own target and prediction when the objective
+        // has label callables, or on the other records for objectives without a loss for each record.
+        // The trees then measure the loss decrement of their leaves with the second order expansion
+        // of the loss, from the gradient and hessian of each record, relative to the current loss.
+        let second_order_loss =
+            (label_callables.is_some() || !self.objective.has_record_loss()).then(second_order_loss_callable);
+        let (calc_loss, calc_grad_hess) = match label_callables {
+            Some(callables) => callables,
+            None => (
+                loss_callables(&self.objective)?,
+                gradient_hessian_callables(&self.objective)?,
+            ),
+        };
+        let metric = self.eval_metric.unwrap_or_else(|| default_metric(&self.objective));
+        let output = SingleOutput {
+            data,
+            y,
+            sample_weight,
+            quantile: self.quantile,
+            calc_loss,
+            calc_grad_hess,
+            calc_init: calc_init_callables(&self.objective)?,
+            second_order_loss,
+            calc_leaf_value: leaf_value_callables(&self.objective),
+            eta: self.eta,
+            missing: self.missing,
+            metric_alpha: self.metric_alpha(&metric),
+            metric,
+        };
+        fit_rounds(
+            std::slice::from_mut(self),
+            data,
+            sample_weight,
+            &output,
+            splitter,
+            eval_sets,
+            callbacks,
+        )
+    }
 
-            if let Some(t) = self.timeout {
-                if start.elapsed().as_secs_f32() > t {
-                    warn!("Reached timeout limit before auto stopping. Try to decrease the budget or increase the timeout for the best performance.");
-                    break;
-                }
+    /// Check the parameters given for each feature against the number of columns.
+    pub(crate) fn validate_features(&self, cols: usize) -> Result<(), PerpetualError> {
+        if let Some(weights) = &self.feature_weights {
+            if weights.len() != cols
+                || weights.iter().any(|w| !(w.is_finite() && *w >= 0.0))
+                || weights.iter().all(|w| *w == 0.0)
+            {
+                return Err(PerpetualError::InvalidParameter(
+                    "feature_weights".to_string(),
+                    format!("{} finite non negative weights, not all zero", cols),
+                    format!("{:?}", weights),
+                ));
             }
-
-            if i == self.iteration_limit.unwrap_or(ITER_LIMIT) - 1 {
-                warn!("Reached iteration limit before auto stopping. Try to decrease the budget for the best performance.");
+        }
+        if let Some(f) = self
+            .always_include_features
+            .as_ref()
+            .and_then(|features| features.iter().find(|f| **f >= cols))
+        {
+            return Err(PerpetualError::InvalidParameter(
+                "always_include_features".to_string(),
+                format!("feature indices below {}", cols),
+                f.to_string(),
+            ));
+        }
+        if let Some(f) = self
+            .interaction_constraints
+            .iter()
+            .flatten()
+            .flatten()
+            .find(|f| **f >= cols)
+        {
+            return Err(PerpetualError::InvalidParameter(
+                "interaction_constraints".to_string(),
+                format!("feature indices below {}", cols),
+                f.to_string(),
+            ));
+        }
+        if let Some(names) = &self.feature_names {
+            if names.len() != cols || names.iter().collect::<HashSet<_>>().len() != cols {
+                return Err(PerpetualError::InvalidParameter(
+                    "feature_names".to_string(),
+                    format!("{} unique feature names", cols),
+                    format!("{:?}", names),
+                ));
             }
         }
+        Ok(())
+    }
 
-        if self.log_iterations > 0 {
-            info!(
-                "Finished training a booster with {0} trees in {1} seconds.",
-                self.trees.len(),
-                start.elapsed().as_secs()
-            );
+    /// Check the names of the columns of the data, if it has any, against the feature names
+    /// of the booster. Data without names, or a booster without feature names, are not checked.
+    ///
+    /// * `data` - The data to fit or predict.
+    pub fn validate_feature_names<D: DataMatrix>(&self, data: &D) -> Result<(), PerpetualError> {
+        match (&self.feature_names, data.feature_names()) {
+            (Some(names), Some(data_names)) if names.as_slice() != data_names => Err(PerpetualError::InvalidParameter(
+                "data".to_string(),
+                format!("the columns {:?}", names),
+                format!("{:?}", data_names),
+            )),
+            _ => Ok(()),
         }
+    }
 
-        Ok(())
+    /// Index of a feature, given its name.
+    ///
+    /// * `name` - The name of the feature, one of the `feature_names` of the booster.
+    pub fn feature_index(&self, name: &str) -> Result<usize, PerpetualError> {
+        let names = self.feature_names.as_ref().ok_or_else(|| {
+            PerpetualError::InvalidParameter(
+                "feature_names".to_string(),
+                "the feature names, to refer to features by name".to_string(),
+                "None".to_string(),
+            )
+        })?;
+        names.iter().position(|n| n == name).ok_or_else(|| {
+            PerpetualError::InvalidParameter(
+                "feature name".to_string(),
+                format!("one of {:?}", names),
+                name.to_string(),
+            )
+        })
     }
 
-    fn update_predictions_inplace(&self, yhat: &mut [f64], tree: &Tree, data: &Matrix<f64>) {
+    /// The `alpha` passed to the evaluation metric, taken from the parameters of the objective.
+    pub(crate) fn metric_alpha(&self, metric: &Metric) -> Option<f32> {
+        match (metric, &self.objective) {
+            (Metric::QuantileLoss, _) => self.quantile.map(|q| q as f32),
+            (Metric::TweedieDeviance, Objective::TweedieLoss { variance_power }) => Some(*variance_power as f32),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn update_predictions_inplace<D: DataMatrix>(&self, yhat: &mut [f64], tree: &Tree, data: &D) {
         let preds = tree.predict(data, true, &self.missing);
         yhat.iter_mut().zip(preds).for_each(|(i, j)| *i += j);
     }
@@ -628,10 +1172,7 @@ impl PerpetualBooster {
     /// The smaller the value, the more conservative the weights will be.
     /// * `budget` - A positive number for fitting budget.
     pub fn set_eta(&mut self, budget: f32) {
-        let budget = f32::max(0.0, budget);
-        let power = budget * -1.0;
-        let base = 10_f32;
-        self.eta = base.powf(power);
+        self.eta = eta_from_budget(budget);
     }
 
     /// Get reference to the trees
@@ -695,6 +1236,38 @@ impl PerpetualBooster {
         }
     }
 
+    /// Calculate feature importance measure for the features in the model, keyed by
+    /// the feature names, or by the feature indices if the booster has no feature names.
+    /// - `method`: variable importance method to use.
+    /// - `normalize`: whether to divide the importance by the sum over the features.
+    pub fn calculate_feature_importance_by_name(
+        &self,
+        method: ImportanceMethod,
+        normalize: bool,
+    ) -> HashMap<String, f32> {
+        self.calculate_feature_importance(method, normalize)
+            .into_iter()
+            .map(|(k, v)| (self.feature_name(k), v))
+            .collect()
+    }
+
+    /// Name of a feature, its index if the booster has no feature names.
+    fn feature_name(&self, feature: usize) -> String {
+        match self.feature_names.as_ref().and_then(|names| names.get(feature)) {
+            Some(name) => name.to_string(),
+            None => feature.to_string(),
+        }
+    }
+
+    /// Dump the trees of the booster as text, one string per tree, with the
+    /// feature names in place of the feature indices if the booster has them.
+    pub fn dump_trees(&self) -> Vec<String> {
+        self.trees
+            .iter()
+            .map(|t| t.to_string_with_names(self.feature_names.as_deref()))
+            .collect()
+    }
+
     /// Save a booster as a json object to a file.
     ///
     /// * `path` - Path to save booster.
@@ -750,11 +1323,205 @@ impl PerpetualBooster {
     }
 }
 
+/// The single output of a `PerpetualBooster` fit, with the callables of its objective.
+struct SingleOutput<'a, D> {
+    data: &'a D,
+    y: &'a [f64],
+    sample_weight: Option<&'a [f64]>,
+    quantile: Option<f64>,
+    calc_loss: LossFn,
+    calc_grad_hess: ObjFn,
+    calc_init: InitFn,
+    second_order_loss: Option<LossFn>,
+    calc_leaf_value: Option<LeafFn>,
+    eta: f32,
+    missing: f64,
+    metric: Metric,
+    metric_alpha: Option<f32>,
+}
+
+impl<D: DataMatrix> RoundObjective<D> for SingleOutput<'_, D> {
+    fn base_scores(&self) -> Vec<f64> {
+        vec![(self.calc_init)(self.y, self.sample_weight, self.quantile)]
+    }
+
+    fn loss(&self, yhat: &[Vec<f64>]) -> Vec<f32> {
+        (self.calc_loss)(self.y, &yhat[0], self.sample_weight, self.quantile)
+    }
+
+    fn targets<'a>(&'a self, yhat: &'a [Vec<f64>]) -> Vec<TreeTarget<'a>> {
+        let (grad, hess) = (self.calc_grad_hess)(self.y, &yhat[0], self.sample_weight, self.quantile);
+        let loss = match &self.second_order_loss {
+            Some(calc_loss) => TreeLoss::SecondOrder(calc_loss),
+            None => TreeLoss::Records {
+                loss: self.loss(yhat),
+                y: Cow::Borrowed(self.y),
+                calc_loss: &self.calc_loss,
+                yhat: Cow::Borrowed(&yhat[0]),
+                sample_weight: self.sample_weight.map(Cow::Borrowed),
+                quantile: self.quantile,
+            },
+        };
+        vec![TreeTarget { grad, hess, loss }]
+    }
+
+    fn output_trees(
+        &self,
+        mut trees: Vec<Tree>,
+        yhat: &[Vec<f64>],
+        index: &[usize],
+        sample_weight: Option<&[f64]>,
+    ) -> Vec<Tree> {
+        if let Some(calc_leaf_value) = &self.calc_leaf_value {
+            for tree in trees.iter_mut() {
+                tree.refit_leaf_values(
+                    self.data,
+                    index,
+                    self.y,
+                    &yhat[0],
+                    sample_weight,
+                    self.eta,
+                    &self.missing,
+                    calc_leaf_value,
+                );
+            }
+        }
+        trees
+    }
+
+    fn base_offsets(&self, yhat: &[Vec<f64>]) -> Vec<f64> {
+        match &self.calc_leaf_value {
+            // The objectives with their own leaf values shift by the leaf value of all the records.
+            Some(calc_leaf_value) => vec![calc_leaf_value(self.y, &yhat[0], self.sample_weight)],
+            None => RoundObjective::<D>::targets(self, yhat)
+                .iter()
+                .map(newton_offset)
+                .collect(),
+        }
+    }
+
+    fn evaluate(&self, eval_set: &EvalSet<D>, yhat: &[Vec<f64>], sample_weight: &[f64]) -> f64 {
+        evaluate_metric(
+            &self.metric,
+            eval_set.y,
+            &yhat[0],
+            sample_weight,
+            self.metric_alpha,
+            eval_set.group,
+            eval_set.event,
+        )
+    }
+
+    fn maximize(&self) -> bool {
+        metric_callables(&self.metric).1
+    }
+}
+
+/// Step size for a given budget.
+pub(crate) fn eta_from_budget(budget: f32) -> f32 {
+    let budget = f32::max(0.0, budget);
+    let power = budget * -1.0;
+    let base = 10_f32;
+    base.powf(power)
+}
+
+/// Build the thread pool used during training.
+pub(crate) fn build_thread_pool(num_threads: Option<usize>) -> ThreadPool {
+    let n_threads_available = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let num_threads = match num_threads {
+        Some(num_threads) => num_threads,
+        None => n_threads_available,
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .unwrap()
+}
+
+/// Target loss decrement of a tree, given the budget and the average loss of the base score.
+pub(crate) fn target_loss_decrement(budget: f32, loss_avg: f32) -> f32 {
+    let base = 10.0_f32;
+    let n = base / budget;
+    let reciprocals_of_powers = n / (n - 1.0);
+    let truncated_series_sum = reciprocals_of_powers - (1.0 + 1.0 / n);
+    let c = 1.0 / n - truncated_series_sum;
+    c * base.powf(-budget) * loss_avg
+}
+
+/// Number of columns each tree is fit on.
+pub(crate) fn column_amount(budget: f32, rows: usize, cols: usize) -> usize {
+    // Column sampling is only applied when (n_rows / n_columns) < ROW_COLUMN_RATIO_LIMIT.
+    // ROW_COLUMN_RATIO_LIMIT is calculated using budget.
+    // budget = 1.0 -> ROW_COLUMN_RATIO_LIMIT = 100
+    // budget = 2.0 -> ROW_COLUMN_RATIO_LIMIT = 10
+    let row_column_ratio_limit = 10.0_f32.powf(-budget) * 1000.0;
+    let colsample_bytree = (rows as f32 / cols as f32) / row_column_ratio_limit;
+
+    (((cols as f32) * colsample_bytree).floor() as usize).clamp(usize::min(MIN_COL_AMOUNT, cols), cols)
+}
+
+/// Number of node histograms to allocate for a tree, given the available memory.
+pub(crate) fn nodes_to_allocate(
+    binned_data: &BinnedData<f64>,
+    max_bin: u16,
+    col_amount: usize,
+    memory_limit: Option<f32>,
+) -> usize {
+    let mem_bin = mem::size_of::<Bin>();
+    let mem_hist: usize = if col_amount == binned_data.nunique.len() {
+        mem_bin * binned_data.nunique.iter().sum::<usize>()
+    } else {
+        mem_bin * max_bin as usize * col_amount
+    };
+    let sys = System::new_all();
+    let mem_available = match memory_limit {
+        Some(mem_limit) => mem_limit * (1e9 as f32),
+        None => match sys.cgroup_limits() {
+            Some(limits) => limits.free_memory as f32,
+            None => sys.available_memory() as f32,
+        },
+    };
+
+    let n_nodes_alloc = (FREE_MEM_ALLOC_FACTOR * (mem_available / (mem_hist as f32))) as usize;
+    if memory_limit.is_none() {
+        n_nodes_alloc.clamp(N_NODES_ALLOC_MIN, N_NODES_ALLOC_MAX)
+    } else {
+        n_nodes_alloc
+    }
+}
+
+/// Allocate the histograms of every node of a tree.
+pub(crate) fn allocate_histograms(
+    binned_data: &BinnedData<f64>,
+    col_index: &[usize],
+    max_bin: u16,
+    col_amount: usize,
+    n_nodes_alloc: usize,
+    is_const_hess: bool,
+) -> Vec<NodeHistogramOwned> {
+    if col_amount == col_index.len() {
+        (0..n_nodes_alloc)
+            .map(|_| NodeHistogramOwned::empty_from_cuts(&binned_data.cuts, col_index, is_const_hess, false))
+            .collect()
+    } else {
+        (0..n_nodes_alloc)
+            .map(|_| NodeHistogramOwned::empty(max_bin, col_amount, is_const_hess, false))
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::utils::between;
 
     use super::*;
+    use crate::booster::callback::{CallbackAction, Checkpoint, RoundInfo};
+    use crate::booster::test_data::{noisy_sine, synthetic_columns, synthetic_features};
+    use crate::constraints::Constraint;
+    use crate::data::SparseMatrix;
+    use crate::metric::{concordance_index, cox_negative_log_likelihood, f1_score, ndcg_at_k, pr_auc_score, Metric};
+    use crate::objective::AFTDistribution;
+    use crate::objective::{quantile_leaf_callable, ObjectiveFunction, SquaredLoss};
     use approx::assert_relative_eq;
     use polars::io::SerReader;
     use polars::prelude::{CsvReadOptions, DataType};
@@ -1090,4 +1857,1071 @@ mod tests {
 
         Ok(())
     }
+
+    struct CustomSquaredLoss {}
+
+    impl ObjectiveFunction for CustomSquaredLoss {
+        fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> Vec<f32> {
+            SquaredLoss::default().calc_loss(y, yhat, sample_weight, quantile)
+        }
+        fn calc_grad_hess(
+            &self,
+            y: &[f64],
+            yhat: &[f64],
+            sample_weight: Option<&[f64]>,
+            quantile: Option<f64>,
+        ) -> (Vec<f32>, Option<Vec<f32>>) {
+            SquaredLoss::default().calc_grad_hess(y, yhat, sample_weight, quantile)
+        }
+        fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> f64 {
+            SquaredLoss::default().calc_init(y, sample_weight, quantile)
+        }
+        fn default_metric(&self) -> Metric {
+            Metric::RootMeanSquaredError
+        }
+    }
+
+    #[test]
+    fn test_booster_custom_objective() {
+        let n_rows = 500;
+        let data_vec: Vec<f64> = (0..n_rows)
+            .map(|i| i as f64 / n_rows as f64)
+            .chain((0..n_rows).map(|i| ((i * 7) % 13) as f64))
+            .collect();
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| 2.0 * data_vec[i] + (data_vec[n_rows + i] - 6.0).abs())
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_budget(0.5);
+        booster.fit(&data, &y, None).unwrap();
+
+        let mut custom_booster = PerpetualBooster::default()
+            .set_objective(Objective::custom(CustomSquaredLoss {}))
+            .set_budget(0.5);
+        custom_booster.fit(&data, &y, None).unwrap();
+
+        assert_eq!(booster.trees.len(), custom_booster.trees.len());
+        assert_eq!(booster.predict(&data, false), custom_booster.predict(&data, false));
+
+        // The objective function is not serialized, it needs to be registered again.
+        let json = custom_booster.json_dump().unwrap();
+        let mut loaded_booster = PerpetualBooster::from_json(&json).unwrap();
+        assert_eq!(
+            loaded_booster.predict(&data, false),
+            custom_booster.predict(&data, false)
+        );
+        assert!(matches!(
+            loaded_booster.fit(&data, &y, None),
+            Err(PerpetualError::UnregisteredObjective(_))
+        ));
+        loaded_booster = loaded_booster.set_objective(Objective::custom(CustomSquaredLoss {}));
+        loaded_booster.fit(&data, &y, None).unwrap();
+    }
+
+    #[test]
+    fn test_booster_log_link_objectives() {
+        let n_rows = 500;
+        let data_vec: Vec<f64> = (0..n_rows)
+            .map(|i| i as f64 / n_rows as f64)
+            .chain((0..n_rows).map(|i| ((i * 7) % 13) as f64))
+            .collect();
+        let counts: Vec<f64> = (0..n_rows)
+            .map(|i| ((2.0 * data_vec[i]).exp() * ((i % 3) as f64)).floor())
+            .collect();
+        let positive: Vec<f64> = counts.iter().map(|v| v + 0.5).collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        for (objective, y) in [
+            (Objective::PoissonLoss, &counts),
+            (Objective::GammaLoss, &positive),
+            (Objective::TweedieLoss { variance_power: 1.5 }, &counts),
+        ] {
+            let mut booster = PerpetualBooster::default().set_objective(objective).set_budget(0.5);
+            booster.fit(&data, y, None).unwrap();
+
+            let margins = booster.predict(&data, false);
+            let response = booster.predict_response(&data, false);
+            assert!(response
+                .iter()
+                .zip(&margins)
+                .all(|(r, m)| *r > 0.0 && (r.ln() - m).abs() < 1e-9));
+
+            // The log link keeps the mean of the predictions close to the mean of the target.
+            let y_avg = y.iter().sum::<f64>() / n_rows as f64;
+            let response_avg = response.iter().sum::<f64>() / n_rows as f64;
+            assert!((response_avg - y_avg).abs() / y_avg < 0.05);
+
+            // Targets outside the support of the objective are rejected.
+            let mut y_invalid = y.clone();
+            y_invalid[3] = -1.0;
+            assert!(booster.fit(&data, &y_invalid, None).is_err());
+        }
+        let mut booster = PerpetualBooster::default().set_objective(Objective::GammaLoss);
+        assert!(booster.fit(&data, &counts, None).is_err());
+
+        let mut booster = PerpetualBooster::default().set_objective(Objective::TweedieLoss { variance_power: 2.5 });
+        assert!(matches!(
+            booster.fit(&data, &counts, None),
+            Err(PerpetualError::InvalidParameter(..))
+        ));
+    }
+
+    #[test]
+    fn test_booster_robust_objectives() {
+        let n_rows = 500;
+        let data_vec: Vec<f64> = (0..n_rows)
+            .map(|i| i as f64 / n_rows as f64)
+            .chain((0..n_rows).map(|i| ((i * 7) % 13) as f64))
+            .collect();
+        let y_clean: Vec<f64> = (0..n_rows).map(|i| 10.0 * data_vec[i]).collect();
+        // Every tenth record is a large outlier.
+        let y: Vec<f64> = y_clean
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i % 10 == 0 { v + 100.0 } else { *v })
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        let clean_error = |objective: Objective| {
+            let mut booster = PerpetualBooster::default().set_objective(objective).set_budget(0.5);
+            booster.fit(&data, &y, None).unwrap();
+            let preds = booster.predict(&data, false);
+            assert!(preds.iter().all(|p| p.is_finite()));
+            preds.iter().zip(&y_clean).map(|(p, y_)| (p - y_).abs()).sum::<f64>() / n_rows as f64
+        };
+
+        let squared_error = clean_error(Objective::SquaredLoss);
+        for objective in [
+            Objective::AbsoluteLoss,
+            Objective::HuberLoss { delta: 1.0 },
+            Objective::PseudoHuberLoss { delta: 1.0 },
+        ] {
+            assert!(clean_error(objective) < squared_error);
+        }
+    }
+
+    #[test]
+    fn test_booster_refit_sampled_rows() {
+        let n_rows = 1000;
+        let data_vec = synthetic_features(n_rows);
+        let y: Vec<f64> = data_vec[..n_rows].to_vec();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::AbsoluteLoss)
+            .set_budget(0.5);
+        booster.fit(&data, &y, None).unwrap();
+
+        // The leaf values are refit on the rows of the index only, leaving out the outliers.
+        let y_outliers: Vec<f64> = y
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i % 4 == 0 { 1000.0 } else { *v })
+            .collect();
+        let index: Vec<usize> = (0..n_rows).filter(|i| i % 4 != 0).collect();
+        let yhat = vec![booster.base_score; n_rows];
+        let leaf_value = |index: &[usize]| {
+            let mut tree = booster.trees[0].clone();
+            tree.refit_leaf_values(
+                &data,
+                index,
+                &y_outliers,
+                &yhat,
+                None,
+                1.0,
+                &f64::NAN,
+                &quantile_leaf_callable(0.9),
+            );
+            tree.nodes
+                .values()
+                .filter(|n| n.is_leaf)
+                .map(|n| n.weight_value)
+                .fold(f32::MIN, f32::max)
+        };
+        assert!(leaf_value(&index) < 1.0);
+        assert!(leaf_value(&data.index) > 100.0);
+
+        // GOSS scales up the weights of the rows it samples from the rest in the refit.
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::AbsoluteLoss)
+            .set_budget(0.5)
+            .set_sample_method(SampleMethod::Goss);
+        booster.fit(&data, &y, None).unwrap();
+        let preds = booster.predict(&data, false);
+        let error = preds.iter().zip(&y).map(|(p, y_)| (p - y_).abs()).sum::<f64>() / n_rows as f64;
+        assert!(error < 0.1);
+    }
+
+    #[test]
+    fn test_booster_ranking_objectives() {
+        let n_rows = 1000;
+        let data_vec = synthetic_features(n_rows);
+        // The relevance grows with the first feature, with some noise.
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| {
+                (4.0 * data_vec[i] + ((i * 17) % 11) as f64 / 10.0 - 0.5)
+                    .floor()
+                    .clamp(0.0, 3.0)
+            })
+            .collect();
+        let group: Vec<u64> = (0..n_rows).map(|i| (i / 20) as u64).collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        let baseline = ndcg_at_k(&y, &vec![0.0; n_rows], &group, Some(5));
+        for objective in [Objective::RankNetLoss, Objective::LambdaRankLoss] {
+            let mut booster = PerpetualBooster::default().set_objective(objective).set_budget(0.5);
+            assert!(booster.fit(&data, &y, None).is_err());
+            booster.fit_ranking(&data, &y, &group, None).unwrap();
+            let preds = booster.predict(&data, false);
+            let ndcg = ndcg_at_k(&y, &preds, &group, Some(5));
+            assert!(ndcg > baseline);
+            assert!(ndcg > 0.9);
+        }
+
+        // The evaluation set is scored per query.
+        let eval_sets = [EvalSet {
+            name: "train",
+            data: &data,
+            y: &y,
+            sample_weight: None,
+            group: Some(&group),
+            event: None,
+        }];
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::LambdaRankLoss)
+            .set_eval_metric(Some(Metric::NDCG))
+            .set_budget(0.5);
+        booster
+            .fit_ranking_with_callbacks(&data, &y, &group, None, &eval_sets, &mut [])
+            .unwrap();
+        let preds = booster.predict(&data, false);
+        let history = &booster.evaluation_history["train"];
+        assert_relative_eq!(history[history.len() - 1], ndcg_at_k(&y, &preds, &group, None));
+        let bad_group = [EvalSet {
+            group: Some(&group[1..]),
+            ..eval_sets[0]
+        }];
+        assert!(booster
+            .fit_ranking_with_callbacks(&data, &y, &group, None, &bad_group, &mut [])
+            .is_err());
+
+        assert!(PerpetualBooster::default()
+            .fit_ranking(&data, &y, &group, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_booster_survival_objectives() {
+        let n_rows = 1000;
+        let data_vec = synthetic_features(n_rows);
+        // The survival time grows with the first feature, every third record is censored at half its time.
+        let time: Vec<f64> = (0..n_rows)
+            .map(|i| (2.0 * data_vec[i] + ((i * 17) % 11) as f64 / 20.0).exp())
+            .collect();
+        let event: Vec<bool> = (0..n_rows).map(|i| i % 3 != 0).collect();
+        let y: Vec<f64> = time
+            .iter()
+            .zip(&event)
+            .map(|(t, e)| if *e { *t } else { 0.5 * t })
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        for distribution in [
+            AFTDistribution::Normal,
+            AFTDistribution::Logistic,
+            AFTDistribution::Extreme,
+        ] {
+            let mut booster = PerpetualBooster::default()
+                .set_objective(Objective::AFTLoss {
+                    distribution,
+                    sigma: 1.0,
+                })
+                .set_budget(0.5);
+            booster.fit_survival(&data, &y, Censoring::Event(&event), None).unwrap();
+            let preds = booster.predict(&data, false);
+            assert!(concordance_index(&time, &preds, None) > 0.85);
+        }
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::CoxLoss)
+            .set_budget(0.5);
+        booster.fit_survival(&data, &y, Censoring::Event(&event), None).unwrap();
+        let risk: Vec<f64> = booster.predict(&data, false).iter().map(|p| -p).collect();
+        assert!(concordance_index(&time, &risk, None) > 0.85);
+
+        // The metric of an evaluation set accounts for its censored records.
+        let eval_sets = [EvalSet {
+            name: "train",
+            data: &data,
+            y: &y,
+            sample_weight: None,
+            group: None,
+            event: Some(&event),
+        }];
+        booster
+            .fit_survival_with_callbacks(&data, &y, Censoring::Event(&event), None, &eval_sets, &mut [])
+            .unwrap();
+        let preds = booster.predict(&data, false);
+        let history = &booster.evaluation_history["train"];
+        let w = vec![1.0; n_rows];
+        assert_relative_eq!(
+            history[history.len() - 1],
+            cox_negative_log_likelihood(&y, &preds, &w, Some(&event)),
+            max_relative = 1e-6
+        );
+        let bad_event = [EvalSet {
+            event: Some(&event[1..]),
+            ..eval_sets[0]
+        }];
+        assert!(booster
+            .fit_survival_with_callbacks(&data, &y, Censoring::Event(&event), None, &bad_event, &mut [])
+            .is_err());
+
+        let y_upper: Vec<f64> = y.iter().map(|v| v + 1.0).collect();
+        assert!(booster
+            .fit_survival(&data, &y, Censoring::UpperBound(&y_upper), None)
+            .is_err());
+        assert!(booster.fit_survival(&data, &y, Censoring::UpperBound(&y), None).is_ok());
+        let mut booster = PerpetualBooster::default();
+        assert!(booster.fit_survival(&data, &y, Censoring::Event(&event), None).is_err());
+
+        // The accelerated failure time model needs positive survival times.
+        let mut booster = PerpetualBooster::default().set_objective(Objective::AFTLoss {
+            distribution: AFTDistribution::Normal,
+            sigma: 1.0,
+        });
+        let mut y_zero = y.clone();
+        y_zero[1] = 0.0;
+        assert!(booster
+            .fit_survival(&data, &y_zero, Censoring::Event(&event), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_booster_imbalanced_classification() {
+        let n_rows = 2000;
+        let data_vec = synthetic_features(n_rows);
+        // About 3% positives, mostly at large values of the first feature.
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| {
+                let x = data_vec[i];
+                if (x > 0.95 && i % 5 != 0) || i % 97 == 0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let w = vec![1.0; n_rows];
+
+        let mut focal = PerpetualBooster::default()
+            .set_objective(Objective::FocalLoss {
+                gamma: 2.0,
+                alpha: 0.25,
+            })
+            .set_budget(0.5);
+        focal.fit(&data, &y, None).unwrap();
+        let preds = focal.predict(&data, false);
+        assert!(pr_auc_score(&y, &preds, &w) > 0.7);
+        let probas = focal.predict_response(&data, false);
+        assert!(probas.iter().all(|p| (0.0..=1.0).contains(p)));
+
+        let mut unweighted = PerpetualBooster::default().set_budget(0.5);
+        unweighted.fit(&data, &y, None).unwrap();
+        let mut weighted = PerpetualBooster::default()
+            .set_scale_pos_weight(Some(10.0))
+            .set_budget(0.5);
+        weighted.fit(&data, &y, None).unwrap();
+        // The class weight moves the base score and the predictions towards the positive class.
+        assert!((weighted.base_score - unweighted.base_score - 10.0_f64.ln()).abs() < 1e-9);
+        let preds_unweighted = unweighted.predict(&data, false);
+        let preds_weighted = weighted.predict(&data, false);
+        assert!(f1_score(&y, &preds_weighted, &w, 0.5) > 0.5);
+        assert!(preds_weighted.iter().sum::<f64>() > preds_unweighted.iter().sum::<f64>());
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_scale_pos_weight(Some(10.0));
+        assert!(booster.fit(&data, &y, None).is_err());
+        let mut booster = PerpetualBooster::default().set_scale_pos_weight(Some(0.0));
+        assert!(booster.fit(&data, &y, None).is_err());
+    }
+
+    #[test]
+    fn test_booster_cross_entropy() {
+        let n_rows = 1000;
+        let data_vec = synthetic_features(n_rows);
+        // Soft labels, as the averaged votes of several annotators.
+        let y: Vec<f64> = data_vec[..n_rows].iter().map(|x| (x * 4.0).round() / 4.0).collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::CrossEntropy)
+            .set_budget(0.5);
+        booster.fit(&data, &y, None).unwrap();
+        let probas = booster.predict_response(&data, false);
+        let mae = probas.iter().zip(&y).map(|(p, y_)| (p - y_).abs()).sum::<f64>() / n_rows as f64;
+        assert!(mae < 0.05);
+
+        let mut y_invalid = y.clone();
+        y_invalid[3] = 2.0;
+        assert!(booster.fit(&data, &y_invalid, None).is_err());
+    }
+
+    #[test]
+    fn test_booster_early_stopping() {
+        let n_rows = 1500;
+        let data_vec = synthetic_features(n_rows);
+        let y = noisy_sine(&data_vec[..n_rows]);
+        let (n_train, n_valid) = (1000, 500);
+        let train_vec: Vec<f64> = data_vec[..n_train]
+            .iter()
+            .chain(&data_vec[n_rows..n_rows + n_train])
+            .copied()
+            .collect();
+        let valid_vec: Vec<f64> = data_vec[n_train..n_rows]
+            .iter()
+            .chain(&data_vec[n_rows + n_train..])
+            .copied()
+            .collect();
+        let train = Matrix::new(&train_vec, n_train, 2);
+        let valid = Matrix::new(&valid_vec, n_valid, 2);
+        let eval_sets = [
+            EvalSet {
+                name: "train",
+                data: &train,
+                y: &y[..n_train],
+                sample_weight: None,
+                group: None,
+                event: None,
+            },
+            EvalSet {
+                name: "valid",
+                data: &valid,
+                y: &y[n_train..],
+                sample_weight: None,
+                group: None,
+                event: None,
+            },
+        ];
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_budget(1.5)
+            .set_eval_metric(Some(Metric::RootMeanSquaredError))
+            .set_early_stopping_rounds(Some(5));
+        booster.fit_with_eval(&train, &y[..n_train], None, &eval_sets).unwrap();
+
+        let history = &booster.evaluation_history["valid"];
+        assert_eq!(history.len(), booster.evaluation_history["train"].len());
+        let best_iteration = booster.best_iteration.unwrap();
+        assert_eq!(booster.trees.len(), best_iteration);
+        assert!(history.len() <= best_iteration + 5);
+        let best = history[best_iteration - 1];
+        assert!(history.iter().all(|v| *v >= best));
+        let preds = booster.predict(&valid, true);
+        let w = vec![1.0; n_valid];
+        assert_relative_eq!(
+            crate::metric::root_mean_squared_error(&y[n_train..], &preds, &w),
+            best,
+            max_relative = 1e-9
+        );
+
+        assert_eq!(booster.stop_reason, Some(StopReason::EarlyStopping));
+        let training_history = &booster.training_history;
+        assert_eq!(training_history.len(), history.len());
+        assert_eq!(training_history[best_iteration - 1].round, best_iteration - 1);
+        assert_eq!(training_history[best_iteration - 1].eval_metrics["valid"], best);
+        assert!(training_history.windows(2).all(|r| r[1].loss <= r[0].loss));
+        let loaded = PerpetualBooster::from_json(&booster.json_dump().unwrap()).unwrap();
+        assert_eq!(loaded.training_history.len(), training_history.len());
+        assert_eq!(loaded.stop_reason, booster.stop_reason);
+
+        let mut booster = PerpetualBooster::default().set_early_stopping_rounds(Some(5));
+        assert!(booster.fit(&train, &y[..n_train], None).is_err());
+    }
+
+    #[test]
+    fn test_booster_early_stopping_negative_targets() {
+        let n_rows = 1000;
+        let data_vec = synthetic_features(n_rows);
+        let y: Vec<f64> = noisy_sine(&data_vec[..n_rows]).iter().map(|v| v - 3.0).collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let eval_sets = [EvalSet {
+            name: "train",
+            data: &data,
+            y: &y,
+            sample_weight: None,
+            group: None,
+            event: None,
+        }];
+
+        // The default metric of SquaredLoss is defined for negative targets.
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_budget(1.0)
+            .set_early_stopping_rounds(Some(5));
+        booster.fit_with_eval(&data, &y, None, &eval_sets).unwrap();
+        let history = &booster.evaluation_history["train"];
+        assert!(history.iter().all(|v| v.is_finite()));
+        assert!(booster.best_iteration.unwrap() > 1);
+
+        // An undefined metric never makes a round the best one, so early stopping keeps all the trees.
+        let mut booster = booster.set_eval_metric(Some(Metric::RootMeanSquaredLogError));
+        booster.fit_with_eval(&data, &y, None, &eval_sets).unwrap();
+        assert!(booster.evaluation_history["train"].iter().all(|v| v.is_nan()));
+        assert_eq!(booster.best_iteration, None);
+        assert_eq!(booster.trees.len(), booster.evaluation_history["train"].len());
+        assert!(booster.trees.len() > 1);
+    }
+
+    #[test]
+    fn test_booster_callbacks() {
+        struct Recorder {
+            started: usize,
+            losses: Vec<f32>,
+            n_nodes: Vec<usize>,
+            max_rounds: usize,
+        }
+        impl TrainingCallback for Recorder {
+            fn before_iteration(&mut self, _round: usize) -> CallbackAction {
+                self.started += 1;
+                CallbackAction::Continue
+            }
+            fn after_iteration(&mut self, booster: &PerpetualBooster, info: &RoundInfo) -> CallbackAction {
+                assert_eq!(booster.trees.len(), info.round + 1);
+                assert_eq!(info.tree.stopper, *info.stopper);
+                self.losses.push(info.loss);
+                self.n_nodes.push(info.tree.nodes.len());
+                if info.round + 1 == self.max_rounds {
+                    CallbackAction::Stop
+                } else {
+                    CallbackAction::Continue
+                }
+            }
+        }
+
+        let n_rows = 1000;
+        let data_vec = synthetic_columns(n_rows, 1);
+        let y: Vec<f64> = data_vec.iter().map(|x| (x * 6.0).sin()).collect();
+        let data = Matrix::new(&data_vec, n_rows, 1);
+
+        let mut recorder = Recorder {
+            started: 0,
+            losses: Vec::new(),
+            n_nodes: Vec::new(),
+            max_rounds: 4,
+        };
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_budget(1.0);
+        booster
+            .fit_with_callbacks(&data, &y, None, &[], &mut [&mut recorder])
+            .unwrap();
+
+        assert_eq!(booster.trees.len(), 4);
+        assert_eq!(recorder.started, 4);
+        assert!(recorder.losses.windows(2).all(|l| l[1] <= l[0]));
+        let n_nodes: Vec<usize> = booster.trees.iter().map(|t| t.nodes.len()).collect();
+        assert_eq!(recorder.n_nodes, n_nodes);
+        assert_eq!(booster.stop_reason, Some(StopReason::Callback));
+        let history_n_nodes: Vec<usize> = booster.training_history.iter().map(|r| r.n_nodes).collect();
+        assert_eq!(history_n_nodes, n_nodes);
+    }
+
+    #[test]
+    fn test_booster_continued_fit() {
+        let n_rows = 1000;
+        let data_vec = synthetic_columns(n_rows, 1);
+        let y: Vec<f64> = data_vec.iter().map(|x| (x * 6.0).sin()).collect();
+        let data = Matrix::new(&data_vec, n_rows, 1);
+        // A later batch, drifted below the range of the first one.
+        let data_new_vec: Vec<f64> = data_vec.iter().map(|x| x - 0.2).collect();
+        let y_new: Vec<f64> = data_new_vec.iter().map(|x| (x * 6.0).sin()).collect();
+        let data_new = Matrix::new(&data_new_vec, n_rows, 1);
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_budget(0.5);
+        booster.fit(&data, &y, None).unwrap();
+        let n_trees_first = booster.trees.len();
+        let cuts = booster.cuts.clone().unwrap();
+        let preds_first = booster.predict(&data_new, true);
+        let (base_score_first, stopping_first) = (booster.base_score, booster.stopping_state.stopping);
+
+        // The rounds of the continued fit are numbered after those of the first fit.
+        struct Rounds(Vec<usize>);
+        impl TrainingCallback for Rounds {
+            fn after_iteration(&mut self, booster: &PerpetualBooster, info: &RoundInfo) -> CallbackAction {
+                assert_eq!(booster.trees.len(), info.round + 1);
+                self.0.push(info.round);
+                CallbackAction::Continue
+            }
+        }
+        let mut rounds = Rounds(Vec::new());
+        booster.reset = Some(false);
+        booster
+            .fit_with_callbacks(&data_new, &y_new, None, &[], &mut [&mut rounds])
+            .unwrap();
+        assert!(booster.trees.len() > n_trees_first);
+        assert_eq!(rounds.0, (n_trees_first..booster.trees.len()).collect::<Vec<_>>());
+        let history_rounds: Vec<usize> = booster.training_history.iter().map(|r| r.round).collect();
+        assert_eq!(history_rounds, (0..booster.trees.len()).collect::<Vec<_>>());
+        assert!(booster.trees[..n_trees_first].iter().all(|t| t.batch == 0));
+        assert!(booster.trees[n_trees_first..].iter().all(|t| t.batch == 1));
+        assert_eq!(booster.cuts.as_ref().unwrap().data, cuts.data);
+        // The base score is shifted by the mean residual of the new batch, and the stopping counters carry on.
+        let drift = y_new.iter().zip(&preds_first).map(|(y_, p)| y_ - p).sum::<f64>() / n_rows as f64;
+        assert!((booster.base_score - base_score_first - drift).abs() < 1e-6);
+        assert!(booster.stopping_state.stopping >= stopping_first);
+
+        let preds = booster.predict(&data_new, true);
+        let w = vec![1.0; n_rows];
+        assert!(
+            crate::metric::root_mean_squared_error(&y_new, &preds, &w)
+                < crate::metric::root_mean_squared_error(&y_new, &preds_first, &w)
+        );
+
+        let data_wide = Matrix::new(&data_vec, n_rows / 2, 2);
+        assert!(booster.fit(&data_wide, &y[..n_rows / 2], None).is_err());
+    }
+
+    #[test]
+    fn test_booster_resume_fit() {
+        struct Interrupt {
+            after: usize,
+        }
+        impl TrainingCallback for Interrupt {
+            fn after_iteration(&mut self, _booster: &PerpetualBooster, info: &RoundInfo) -> CallbackAction {
+                if info.round + 1 == self.after {
+                    CallbackAction::Stop
+                } else {
+                    CallbackAction::Continue
+                }
+            }
+        }
+
+        // Few rows for the number of columns, so that the columns are sampled.
+        let (n_rows, n_cols) = (100, 45);
+        let data_vec = synthetic_columns(n_rows, n_cols);
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| (data_vec[i] * 6.0).sin() + data_vec[n_rows + i] - data_vec[2 * n_rows + i])
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, n_cols);
+        assert!(column_amount(1.0, n_rows, n_cols) < n_cols);
+
+        let booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_budget(1.0)
+            .set_max_bin(32)
+            .set_memory_limit(Some(0.01))
+            .set_iteration_limit(Some(15));
+        let mut full = booster.clone();
+        full.fit(&data, &y, None).unwrap();
+        assert!(full.trees.len() > 7);
+
+        let path = std::env::temp_dir().join("perpetual_test_resume_fit.json");
+        let path = path.to_str().unwrap();
+        let mut checkpoint = Checkpoint::new(path, 3);
+        let mut interrupt = Interrupt { after: 7 };
+        let mut interrupted = booster.clone();
+        interrupted
+            .fit_with_callbacks(&data, &y, None, &[], &mut [&mut checkpoint, &mut interrupt])
+            .unwrap();
+        assert_eq!(interrupted.trees.len(), 7);
+
+        let mut resumed = PerpetualBooster::load_booster(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(resumed.trees.len(), 6);
+        resumed.resume_fit(&data, &y, None, None, &[], &mut []).unwrap();
+
+        assert_eq!(resumed.trees.len(), full.trees.len());
+        assert_eq!(resumed.stop_reason, full.stop_reason);
+        assert_eq!(resumed.training_history.len(), full.training_history.len());
+        assert_eq!(resumed.predict(&data, true), full.predict(&data, true));
+
+        assert!(full.resume_fit(&data, &y, None, None, &[], &mut []).is_err());
+    }
+
+    #[test]
+    fn test_booster_resume_survival_fit() {
+        struct Interrupt {
+            after: usize,
+        }
+        impl TrainingCallback for Interrupt {
+            fn after_iteration(&mut self, _booster: &PerpetualBooster, info: &RoundInfo) -> CallbackAction {
+                if info.round + 1 == self.after {
+                    CallbackAction::Stop
+                } else {
+                    CallbackAction::Continue
+                }
+            }
+        }
+
+        let n_rows = 500;
+        let data_vec = synthetic_features(n_rows);
+        // Every third record is censored at half its survival time.
+        let time: Vec<f64> = (0..n_rows)
+            .map(|i| (2.0 * data_vec[i] + ((i * 17) % 11) as f64 / 20.0).exp())
+            .collect();
+        let event: Vec<bool> = (0..n_rows).map(|i| i % 3 != 0).collect();
+        let y: Vec<f64> = time
+            .iter()
+            .zip(&event)
+            .map(|(t, e)| if *e { *t } else { 0.5 * t })
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        let booster = PerpetualBooster::default()
+            .set_objective(Objective::AFTLoss {
+                distribution: AFTDistribution::Normal,
+                sigma: 1.0,
+            })
+            .set_budget(1.0)
+            .set_iteration_limit(Some(15));
+        let mut full = booster.clone();
+        full.fit_survival(&data, &y, Censoring::Event(&event), None).unwrap();
+        assert!(full.trees.len() > 7);
+
+        let path = std::env::temp_dir().join("perpetual_test_resume_survival_fit.json");
+        let path = path.to_str().unwrap();
+        let mut checkpoint = Checkpoint::new(path, 3);
+        let mut interrupt = Interrupt { after: 7 };
+        let mut interrupted = booster.clone();
+        interrupted
+            .fit_survival_with_callbacks(
+                &data,
+                &y,
+                Censoring::Event(&event),
+                None,
+                &[],
+                &mut [&mut checkpoint, &mut interrupt],
+            )
+            .unwrap();
+        assert_eq!(interrupted.trees.len(), 7);
+
+        let mut resumed = PerpetualBooster::load_booster(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(resumed.trees.len(), 6);
+        assert!(resumed.resume_fit(&data, &y, None, None, &[], &mut []).is_err());
+        resumed
+            .resume_survival_fit(&data, &y, Censoring::Event(&event), None, &[], &mut [])
+            .unwrap();
+
+        assert_eq!(resumed.trees.len(), full.trees.len());
+        assert_eq!(resumed.stop_reason, full.stop_reason);
+        assert_eq!(resumed.predict(&data, true), full.predict(&data, true));
+    }
+
+    #[test]
+    fn test_booster_row_sampling() {
+        let n_rows = 2000;
+        let data_vec = synthetic_features(n_rows);
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| (data_vec[i] * 6.0).sin() + data_vec[n_rows + i] / 13.0)
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let w = vec![1.0; n_rows];
+
+        let mut random = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_sample_method(SampleMethod::Random)
+            .set_subsample(0.5);
+        random.fit(&data, &y, None).unwrap();
+        let preds = random.predict(&data, true);
+        assert!(crate::metric::root_mean_squared_error(&y, &preds, &w) < 0.1);
+        // Half of the rows are out of bag in every round.
+        assert!(random.training_history.iter().all(|r| r.oob_loss.is_some()));
+
+        let mut goss = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_sample_method(SampleMethod::Goss);
+        goss.fit(&data, &y, None).unwrap();
+        let preds = goss.predict(&data, true);
+        assert!(crate::metric::root_mean_squared_error(&y, &preds, &w) < 0.1);
+
+        let mut booster = PerpetualBooster::default()
+            .set_sample_method(SampleMethod::Random)
+            .set_subsample(0.0);
+        assert!(booster.fit(&data, &y, None).is_err());
+        let mut booster = PerpetualBooster::default()
+            .set_sample_method(SampleMethod::Goss)
+            .set_top_rate(0.6)
+            .set_other_rate(0.5);
+        assert!(booster.fit(&data, &y, None).is_err());
+    }
+
+    #[test]
+    fn test_booster_column_sampling() {
+        let (n_rows, n_cols) = (1000, 4);
+        let data_vec = synthetic_columns(n_rows, n_cols);
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| (data_vec[i] * 6.0).sin() + data_vec[n_rows + i])
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, n_cols);
+        let split_features = |booster: &PerpetualBooster| -> HashSet<usize> {
+            booster
+                .trees
+                .iter()
+                .flat_map(|t| t.nodes.values().filter(|n| !n.is_leaf).map(|n| n.split_feature))
+                .collect()
+        };
+
+        // A feature with a weight of zero is never sampled.
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_memory_limit(Some(0.01))
+            .set_colsample_bytree(Some(0.5))
+            .set_feature_weights(Some(vec![0.0, 1.0, 1.0, 1.0]));
+        booster.fit(&data, &y, None).unwrap();
+        let features = split_features(&booster);
+        assert!(!features.contains(&0) && features.contains(&1));
+
+        // Also when the trees are fit on all the columns.
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_feature_weights(Some(vec![0.0, 1.0, 1.0, 1.0]));
+        booster.fit(&data, &y, None).unwrap();
+        assert!(booster.colsample_bytree.is_none());
+        let features = split_features(&booster);
+        assert!(!features.contains(&0) && features.contains(&1));
+
+        // Unless it is always included, in the trees and in the nodes.
+        let mut booster = booster
+            .set_colsample_bynode(Some(0.5))
+            .set_always_include_features(Some(HashSet::from([0])));
+        booster.fit(&data, &y, None).unwrap();
+        assert!(split_features(&booster).contains(&0));
+
+        let mut booster = PerpetualBooster::default().set_colsample_bynode(Some(1.5));
+        assert!(booster.fit(&data, &y, None).is_err());
+        let mut booster = PerpetualBooster::default().set_feature_weights(Some(vec![1.0; 3]));
+        assert!(booster.fit(&data, &y, None).is_err());
+        let mut booster = PerpetualBooster::default().set_always_include_features(Some(HashSet::from([4])));
+        assert!(booster.fit(&data, &y, None).is_err());
+    }
+
+    #[test]
+    fn test_booster_interaction_constraints() {
+        let (n_rows, n_cols) = (1000, 4);
+        let data_vec = synthetic_columns(n_rows, n_cols);
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| data_vec[i] * data_vec[n_rows + i] + data_vec[2 * n_rows + i] + data_vec[3 * n_rows + i])
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, n_cols);
+
+        let interaction_constraints = vec![HashSet::from([0, 1]), HashSet::from([1, 2])];
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_interaction_constraints(Some(interaction_constraints.clone()));
+        booster.fit(&data, &y, None).unwrap();
+
+        // The features split on along every path of every tree are within a group,
+        // or are a single feature outside of every group.
+        for tree in &booster.trees {
+            for node in tree.nodes.values().filter(|n| !n.is_leaf) {
+                let mut path = HashSet::from([node.split_feature]);
+                let mut n = node;
+                while n.num != 0 {
+                    n = &tree.nodes[&n.parent_node];
+                    path.insert(n.split_feature);
+                }
+                assert!(path == HashSet::from([3]) || interaction_constraints.iter().any(|g| path.is_subset(g)));
+            }
+        }
+        let features: HashSet<usize> = booster
+            .trees
+            .iter()
+            .flat_map(|t| t.nodes.values().filter(|n| !n.is_leaf).map(|n| n.split_feature))
+            .collect();
+        assert_eq!(features.len(), n_cols);
+
+        let loaded = PerpetualBooster::from_json(&booster.json_dump().unwrap()).unwrap();
+        assert_eq!(loaded.interaction_constraints, Some(interaction_constraints));
+
+        let mut booster = booster.set_interaction_constraints(Some(vec![HashSet::from([0, 4])]));
+        assert!(booster.fit(&data, &y, None).is_err());
+    }
+
+    #[test]
+    fn test_booster_row_major_data() {
+        let (n_rows, n_cols) = (500, 3);
+        let data_vec = synthetic_columns(n_rows, n_cols);
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| (data_vec[i] * 6.0).sin() + data_vec[n_rows + i])
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, n_cols);
+        let row_major_vec: Vec<f64> = (0..n_rows).flat_map(|i| data.get_row(i)).collect();
+        let row_major = Matrix::new_row_major(&row_major_vec, n_rows, n_cols);
+
+        let mut booster = PerpetualBooster::default().set_objective(Objective::SquaredLoss);
+        booster.fit(&data, &y, None).unwrap();
+        let mut booster_row_major = booster.clone();
+        booster_row_major.fit(&row_major, &y, None).unwrap();
+
+        let preds = booster.predict(&data, true);
+        assert_eq!(booster_row_major.predict(&row_major, true), preds);
+        assert_eq!(booster.predict(&row_major, false), preds);
+        assert_eq!(
+            booster.predict_contributions(&row_major, ContributionsMethod::Average, true),
+            booster.predict_contributions(&data, ContributionsMethod::Average, true)
+        );
+    }
+
+    #[test]
+    fn test_booster_f32_data() {
+        let (n_rows, n_cols) = (500, 3);
+        // Every tenth value of the first column is the missing value 0.1, which has no exact f32 representation.
+        let data_f32: Vec<f32> = synthetic_columns(n_rows, n_cols)
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i < n_rows && i % 10 == 0 { 0.1 } else { *v as f32 })
+            .collect();
+        let data_f64: Vec<f64> = data_f32
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i < n_rows && i % 10 == 0 { f64::NAN } else { *v as f64 })
+            .collect();
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| (data_f32[n_rows + i] as f64 * 6.0).sin() + if i % 10 == 0 { 1.0 } else { 0.0 })
+            .collect();
+        let matrix_f32 = Matrix::new(&data_f32, n_rows, n_cols);
+        let matrix_f64 = Matrix::new(&data_f64, n_rows, n_cols);
+
+        let mut booster_f32 = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_missing(0.1);
+        booster_f32.fit(&matrix_f32, &y, None).unwrap();
+        let mut booster_f64 = PerpetualBooster::default().set_objective(Objective::SquaredLoss);
+        booster_f64.fit(&matrix_f64, &y, None).unwrap();
+
+        let preds = booster_f64.predict(&matrix_f64, true);
+        assert_eq!(booster_f32.predict(&matrix_f32, true), preds);
+        assert_eq!(
+            booster_f32.predict_contributions(&matrix_f32, ContributionsMethod::Weight, false),
+            booster_f64.predict_contributions(&matrix_f64, ContributionsMethod::Weight, false)
+        );
+    }
+
+    #[test]
+    fn test_booster_sparse_data() {
+        let (n_rows, n_cols) = (1000, 20);
+        // About one in twenty items is stored, the rest are zero.
+        let (mut values, mut indices, mut indptr) = (Vec::new(), Vec::new(), vec![0]);
+        for row in 0..n_rows {
+            for col in 0..n_cols {
+                if (row * 7 + col * 13) % 20 == 0 {
+                    values.push(((row * 31 + col) % 97) as f64 / 97.0 + 0.5);
+                    indices.push(col);
+                }
+            }
+            indptr.push(values.len());
+        }
+        let sparse = SparseMatrix::new_csr(&values, &indices, &indptr, n_rows, n_cols);
+        let dense_vec: Vec<f64> = (0..n_cols)
+            .flat_map(|col| (0..n_rows).map(move |row| (row, col)))
+            .map(|(row, col)| sparse.get(row, col))
+            .collect();
+        let dense = Matrix::new(&dense_vec, n_rows, n_cols);
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| 2.0 * sparse.get(i, 0) - sparse.get(i, 1) + sparse.get(i, 2) * sparse.get(i, 3))
+            .collect();
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_num_threads(Some(2))
+            .set_iteration_limit(Some(100));
+        booster.fit(&sparse, &y, None).unwrap();
+        let mut booster_dense = booster.clone();
+        booster_dense.fit(&dense, &y, None).unwrap();
+
+        let preds = booster.predict(&sparse, true);
+        assert_eq!(booster.predict(&dense, true), preds);
+        let preds_dense = booster_dense.predict(&dense, true);
+        let rmse = |p: &[f64]| (y.iter().zip(p).map(|(y, p)| (y - p).powi(2)).sum::<f64>() / n_rows as f64).sqrt();
+        assert!((rmse(&preds) - rmse(&preds_dense)).abs() < 1e-3);
+        assert!(rmse(&preds) < 0.1);
+        assert_eq!(
+            booster.predict_contributions(&sparse, ContributionsMethod::Weight, false),
+            booster.predict_contributions(&dense, ContributionsMethod::Weight, false)
+        );
+
+        // The rows left out of a tree by sampling are left out of its nodes.
+        let mut sampled = booster
+            .clone()
+            .set_sample_method(SampleMethod::Random)
+            .set_subsample(0.5);
+        sampled.fit(&sparse, &y, None).unwrap();
+        let mut sampled_dense = sampled.clone();
+        sampled_dense.fit(&dense, &y, None).unwrap();
+        let preds = sampled.predict(&sparse, true);
+        assert!((rmse(&preds) - rmse(&sampled_dense.predict(&dense, true))).abs() < 1e-3);
+        assert!(rmse(&preds) < 0.1);
+    }
+
+    #[test]
+    fn test_booster_feature_names() {
+        let n_rows = 500;
+        let data_vec = synthetic_columns(n_rows, 3);
+        let y: Vec<f64> = (0..n_rows).map(|i| 3.0 * data_vec[i] - data_vec[n_rows + i]).collect();
+        let names: Vec<String> = ["a", "b", "c"].iter().map(|n| n.to_string()).collect();
+        let data = Matrix::new(&data_vec, n_rows, 3).with_feature_names(&names);
+
+        let mut booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_iteration_limit(Some(20));
+        booster.fit(&data, &y, None).unwrap();
+        assert_eq!(booster.feature_names, Some(names.clone()));
+
+        // The names are kept with the model, and used in place of the feature indices.
+        let booster = PerpetualBooster::from_json(&booster.json_dump().unwrap()).unwrap();
+        let importance = booster.calculate_feature_importance_by_name(ImportanceMethod::Weight, false);
+        assert!(importance.contains_key("a") && importance.contains_key("b"));
+        assert!(booster.dump_trees()[0].starts_with("0:[a < ") || booster.dump_trees()[0].starts_with("0:[b < "));
+
+        // Data without names is predicted as is, and data with other names is rejected.
+        let preds = booster.predict(&data, false);
+        assert_eq!(booster.predict(&Matrix::new(&data_vec, n_rows, 3), false), preds);
+        let other_names: Vec<String> = ["a", "c", "b"].iter().map(|n| n.to_string()).collect();
+        let other_data = Matrix::new(&data_vec, n_rows, 3).with_feature_names(&other_names);
+        assert!(booster.validate_feature_names(&other_data).is_err());
+        assert!(booster.try_predict(&other_data, false).is_err());
+        assert!(booster
+            .try_predict_contributions(&other_data, ContributionsMethod::Average, false)
+            .is_err());
+        assert_eq!(booster.try_predict(&data, false).unwrap(), preds);
+        // Data without the columns the trees split on is rejected.
+        let narrow = Matrix::new(&data_vec[..n_rows], n_rows, 1);
+        assert!(booster.try_predict(&narrow, false).is_err());
+        assert!(booster
+            .try_predict_contributions(&data, ContributionsMethod::ProbabilityChange, false)
+            .is_err());
+        assert!(booster.clone().fit(&other_data, &y, None).is_err());
+
+        // Constraints are configured by name.
+        let booster = PerpetualBooster::default()
+            .set_feature_names(Some(names.clone()))
+            .set_monotone_constraints_by_name(HashMap::from([("b".to_string(), Constraint::Negative)]))
+            .unwrap()
+            .set_interaction_constraints_by_name(vec![HashSet::from(["a".to_string(), "c".to_string()])])
+            .unwrap()
+            .set_categorical_features_by_name(HashSet::from(["c".to_string()]))
+            .unwrap();
+        let monotone_constraints = booster.monotone_constraints.as_ref().unwrap();
+        assert!(monotone_constraints.len() == 1 && matches!(monotone_constraints[&1], Constraint::Negative));
+        assert_eq!(booster.interaction_constraints, Some(vec![HashSet::from([0, 2])]));
+        assert_eq!(booster.categorical_features, Some(HashSet::from([2])));
+        assert!(booster
+            .clone()
+            .set_categorical_features_by_name(HashSet::from(["d".to_string()]))
+            .is_err());
+        assert!(PerpetualBooster::default()
+            .set_categorical_features_by_name(HashSet::from(["a".to_string()]))
+            .is_err());
+        let mut booster = booster.set_feature_names(Some(vec!["a".to_string(), "a".to_string(), "b".to_string()]));
+        assert!(booster.fit(&Matrix::new(&data_vec, n_rows, 3), &y, None).is_err());
+    }
 }
@@ -0,0 +1,94 @@
+use crate::errors::PerpetualError;
+use crate::tree::{Tree, TreeStopper};
+use crate::PerpetualBooster;
+use log::warn;
+use std::fs;
+
+/// What the booster should do after a callback is invoked.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CallbackAction {
+    /// Keep fitting trees.
+    Continue,
+    /// Stop fitting, keeping the trees fit so far.
+    Stop,
+}
+
+/// State of a boosting round, passed to `TrainingCallback::after_iteration`.
+pub struct RoundInfo<'a> {
+    /// Index of the round, the index of its tree in the booster. The rounds of a continued fit
+    /// follow those of the fits before it, as in `RoundRecord::round`.
+    pub round: usize,
+    /// The tree fit in this round, the last tree of the booster, of the first booster in a
+    /// multi-output fit.
+    pub tree: &'a Tree,
+    /// The trees fit in this round, one per booster of a multi-output fit.
+    pub trees: &'a [&'a Tree],
+    /// The reason the tree stopped growing.
+    pub stopper: &'a TreeStopper,
+    /// Mean training loss, after the tree is added.
+    pub loss: f32,
+    /// Mean loss of the rows left out of the sample of the round, when the rows are sampled.
+    pub oob_loss: Option<f32>,
+    /// Seconds elapsed since fitting started.
+    pub elapsed: f32,
+}
+
+/// Observer of the boosting rounds, invoked before and after each tree is fit.
+/// Used for progress reporting, custom logging, checkpointing and experiment tracking.
+pub trait TrainingCallback {
+    /// Called before the tree of a round is fit, with the index of the round, see `RoundInfo::round`.
+    fn before_iteration(&mut self, _round: usize) -> CallbackAction {
+        CallbackAction::Continue
+    }
+
+    /// Called after the tree of a round is fit and added to the booster. A multi-output fit
+    /// passes its first booster, which records the history of the fit.
+    fn after_iteration(&mut self, _booster: &PerpetualBooster, _info: &RoundInfo) -> CallbackAction {
+        CallbackAction::Continue
+    }
+
+    /// Whether the callback can observe the joint fits of a `MultiOutputBooster`, which only pass
+    /// their first booster to `after_iteration`. Joint fits return an error for callbacks that can not.
+    fn supports_joint_fit(&self) -> bool {
+        true
+    }
+}
+
+/// Writes the booster to a file every `interval` rounds, with the state needed to continue
+/// an interrupted fit with `PerpetualBooster::resume_fit`. Single output fits only, the joint
+/// fits of a `MultiOutputBooster` can not be restored from their first booster.
+pub struct Checkpoint {
+    path: String,
+    interval: usize,
+}
+
+impl Checkpoint {
+    /// * `path` - File to write the checkpoints to, overwritten by every checkpoint.
+    /// * `interval` - Number of rounds between checkpoints.
+    pub fn new(path: &str, interval: usize) -> Self {
+        Checkpoint {
+            path: path.to_string(),
+            interval: interval.max(1),
+        }
+    }
+}
+
+impl TrainingCallback for Checkpoint {
+    fn after_iteration(&mut self, booster: &PerpetualBooster, info: &RoundInfo) -> CallbackAction {
+        if (info.round + 1).is_multiple_of(self.interval) {
+            // The checkpoint replaces the previous one only once it is written completely.
+            let tmp_path = format!("{}.tmp", self.path);
+            let written = booster.save_booster(&tmp_path).and_then(|_| {
+                fs::rename(&tmp_path, &self.path).map_err(|e| PerpetualError::UnableToWrite(e.to_string()))
+            });
+            if let Err(e) = written {
+                warn!("Unable to write the checkpoint to {}: {}", self.path, e);
+            }
+        }
+        CallbackAction::Continue
+    }
+
+    fn supports_joint_fit(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,246 @@
+use crate::booster::booster::{build_thread_pool, EvalSet};
+use crate::errors::PerpetualError;
+use crate::objective::Objective;
+use crate::{Matrix, PerpetualBooster};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Name of the held out fold in the evaluation history of the fold models.
+const CV_EVAL_NAME: &str = "cv";
+
+/// Model of a fold, with the rows held out and their predictions.
+type FoldFit = (PerpetualBooster, Vec<usize>, Vec<f64>);
+
+/// Results of k-fold cross validation, created with `PerpetualBooster::cv`.
+pub struct CVResult {
+    /// Evaluation metric of the held out fold after every boosting round, for each fold.
+    pub histories: Vec<Vec<f64>>,
+    /// Out of fold predictions of every record, from the model that did not see it.
+    pub oof_predictions: Vec<f64>,
+    /// Fold of every record.
+    pub folds: Vec<usize>,
+    /// Models fit with every fold held out, in fold order.
+    pub models: Vec<PerpetualBooster>,
+}
+
+impl PerpetualBooster {
+    /// Cross validate the booster parameters with k folds. A copy of the booster is fit on each set of
+    /// `n_folds - 1` folds, and evaluated with the evaluation metric on the held out fold, the folds are
+    /// fit in parallel, sharing `num_threads`. The folds are stratified for binary classification, and
+    /// keep the records of a query together when groups are given.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Either a Polars or Pandas Series, or a 1 dimensional Numpy array.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `group` - Query id of each record, required by the ranking objectives and not used otherwise.
+    /// * `n_folds` - Number of folds, at least 2.
+    pub fn cv(
+        &self,
+        data: &Matrix<f64>,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        group: Option<&[u64]>,
+        n_folds: usize,
+    ) -> Result<CVResult, PerpetualError> {
+        if y.len() != data.rows {
+            return Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                format!("{} values", data.rows),
+                y.len().to_string(),
+            ));
+        }
+        let folds = self.assign_folds(y, group, n_folds)?;
+
+        let n_threads = self
+            .num_threads
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+        let pool = build_thread_pool(Some(n_threads.min(n_folds)));
+        let threads_per_fold = (n_threads / n_folds).max(1);
+
+        let fold_fits: Vec<Result<FoldFit, PerpetualError>> = pool.install(|| {
+            (0..n_folds)
+                .into_par_iter()
+                .map(|k| {
+                    let (valid_rows, train_rows): (Vec<usize>, Vec<usize>) = (0..y.len()).partition(|i| folds[*i] == k);
+                    let train_vec = take_rows(data, &train_rows);
+                    let valid_vec = take_rows(data, &valid_rows);
+                    let train = Matrix::new(&train_vec, train_rows.len(), data.cols);
+                    let valid = Matrix::new(&valid_vec, valid_rows.len(), data.cols);
+                    let y_train = take_values(y, &train_rows);
+                    let y_valid = take_values(y, &valid_rows);
+                    let w_train = sample_weight.map(|w| take_values(w, &train_rows));
+                    let w_valid = sample_weight.map(|w| take_values(w, &valid_rows));
+                    let group_train = group.map(|g| take_values(g, &train_rows));
+                    let group_valid = group.map(|g| take_values(g, &valid_rows));
+
+                    let mut model = self.clone();
+                    model.num_threads = Some(threads_per_fold);
+                    model.reset = Some(true);
+                    let eval_sets = [EvalSet {
+                        name: CV_EVAL_NAME,
+                        data: &valid,
+                        y: &y_valid,
+                        sample_weight: w_valid.as_deref(),
+                        group: group_valid.as_deref(),
+                        event: None,
+                    }];
+                    match group_train.as_ref().filter(|_| self.objective.is_ranking()) {
+                        Some(g) => model.fit_ranking_with_callbacks(
+                            &train,
+                            &y_train,
+                            g,
+                            w_train.as_deref(),
+                            &eval_sets,
+                            &mut [],
+                        )?,
+                        None => model.fit_with_eval(&train, &y_train, w_train.as_deref(), &eval_sets)?,
+                    }
+                    let preds = model.predict(&valid, false);
+                    Ok((model, valid_rows, preds))
+                })
+                .collect()
+        });
+
+        let mut histories = Vec::with_capacity(n_folds);
+        let mut oof_predictions = vec![f64::NAN; y.len()];
+        let mut models = Vec::with_capacity(n_folds);
+        for fold_fit in fold_fits {
+            let (mut model, valid_rows, preds) = fold_fit?;
+            histories.push(model.evaluation_history.remove(CV_EVAL_NAME).unwrap_or_default());
+            valid_rows.iter().zip(preds).for_each(|(i, p)| oof_predictions[*i] = p);
+            models.push(model);
+        }
+
+        Ok(CVResult {
+            histories,
+            oof_predictions,
+            folds,
+            models,
+        })
+    }
+
+    /// Assign every record to a fold, at random with the seed of the booster.
+    fn assign_folds(&self, y: &[f64], group: Option<&[u64]>, n_folds: usize) -> Result<Vec<usize>, PerpetualError> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut folds = vec![0; y.len()];
+        match group {
+            Some(group) => {
+                if group.len() != y.len() {
+                    return Err(PerpetualError::InvalidParameter(
+                        "group".to_string(),
+                        format!("{} query ids", y.len()),
+                        group.len().to_string(),
+                    ));
+                }
+                let mut query_index: HashMap<u64, usize> = HashMap::new();
+                for g in group {
+                    let n_queries = query_index.len();
+                    query_index.entry(*g).or_insert(n_queries);
+                }
+                check_n_folds(n_folds, query_index.len())?;
+                let mut order: Vec<usize> = (0..query_index.len()).collect();
+                order.shuffle(&mut rng);
+                let mut query_fold = vec![0; query_index.len()];
+                order.iter().enumerate().for_each(|(k, q)| query_fold[*q] = k % n_folds);
+                folds
+                    .iter_mut()
+                    .zip(group)
+                    .for_each(|(f, g)| *f = query_fold[query_index[g]]);
+            }
+            None => {
+                check_n_folds(n_folds, y.len())?;
+                let strata: Vec<Vec<usize>> = match self.objective {
+                    Objective::LogLoss | Objective::FocalLoss { .. } => {
+                        let (positives, negatives) = (0..y.len()).partition(|i| y[*i] >= 0.5);
+                        vec![positives, negatives]
+                    }
+                    _ => vec![(0..y.len()).collect()],
+                };
+                // The folds are dealt in turn, continuing across the strata, so that the fold
+                // sizes differ by at most one record.
+                let mut k = 0;
+                for mut stratum in strata {
+                    stratum.shuffle(&mut rng);
+                    for i in stratum {
+                        folds[i] = k % n_folds;
+                        k += 1;
+                    }
+                }
+            }
+        }
+        Ok(folds)
+    }
+}
+
+fn check_n_folds(n_folds: usize, n_units: usize) -> Result<(), PerpetualError> {
+    if n_folds < 2 || n_folds > n_units {
+        return Err(PerpetualError::InvalidParameter(
+            "n_folds".to_string(),
+            format!("between 2 and {}", n_units),
+            n_folds.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Column major data of a subset of the rows of a matrix.
+fn take_rows(data: &Matrix<f64>, rows: &[usize]) -> Vec<f64> {
+    (0..data.cols)
+        .flat_map(|j| rows.iter().map(move |i| *data.get(*i, j)))
+        .collect()
+}
+
+fn take_values<T: Copy>(values: &[T], rows: &[usize]) -> Vec<T> {
+    rows.iter().map(|i| values[*i]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::booster::test_data::synthetic_features;
+    use crate::metric::roc_auc_score;
+
+    #[test]
+    fn test_cv() {
+        let n_rows = 1200;
+        let data_vec = synthetic_features(n_rows);
+        let y: Vec<f64> = (0..n_rows)
+            .map(|i| {
+                if data_vec[i] + ((i * 31) % 17) as f64 / 40.0 > 0.8 {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+
+        let booster = PerpetualBooster::default().set_budget(0.5).set_num_threads(Some(2));
+        let result = booster.cv(&data, &y, None, None, 4).unwrap();
+
+        assert_eq!(result.models.len(), 4);
+        assert_eq!(result.histories.len(), 4);
+        assert!(result.histories.iter().all(|h| !h.is_empty()));
+        assert!(result.oof_predictions.iter().all(|p| p.is_finite()));
+        // The folds are stratified, with nearly the same number of positives.
+        let positives: Vec<f64> = (0..4)
+            .map(|k| (0..n_rows).filter(|i| result.folds[*i] == k).map(|i| y[i]).sum())
+            .collect();
+        let (min, max) = positives
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), p| (a.min(*p), b.max(*p)));
+        assert!(max - min <= 1.0);
+        let w = vec![1.0; n_rows];
+        assert!(roc_auc_score(&y, &result.oof_predictions, &w) > 0.8);
+
+        // The records of a query are kept in the same fold.
+        let group: Vec<u64> = (0..n_rows).map(|i| (i / 10) as u64).collect();
+        let folds = booster.assign_folds(&y, Some(&group), 5).unwrap();
+        assert!((0..n_rows).all(|i| folds[i] == folds[i - i % 10]));
+
+        assert!(booster.cv(&data, &y, None, None, 1).is_err());
+    }
+}
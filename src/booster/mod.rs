@@ -1,4 +1,10 @@
 pub mod booster;
+pub mod callback;
+pub mod cv;
 pub mod multi_output;
 pub mod predict;
+mod rounds;
+pub mod search;
 pub mod setters;
+#[cfg(test)]
+pub(crate) mod test_data;
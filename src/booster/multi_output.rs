@@ -1,12 +1,23 @@
-use crate::constraints::ConstraintMap;
+use crate::booster::callback::TrainingCallback;
+use crate::booster::rounds::{fit_rounds, validate_eval_sets, RoundObjective, TreeLoss, TreeTarget};
+use crate::constraints::{ConstraintMap, InteractionConstraints};
+use crate::data::DataMatrix;
 use crate::errors::PerpetualError;
-use crate::objective::Objective;
+use crate::metric::{self, evaluate_metric, Metric};
+use crate::objective::{
+    default_metric, loss_callables, quantile_leaf_callable, Distribution, DistributionalLoss, LeafFn, LogScaleLoss,
+    LossFn, Objective, ObjectiveFunction, QuantileLoss, Softmax, SquaredLoss,
+};
+use crate::sampler::SampleMethod;
+use crate::splitter::{MissingBranchSplitter, MissingImputerSplitter};
+use crate::tree::Tree;
 use crate::{Matrix, PerpetualBooster};
 use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 
-use super::booster::MissingNodeTreatment;
+use super::booster::{eta_from_budget, EvalSet, MissingNodeTreatment};
 
 /// Perpetual Booster object
 #[derive(Deserialize, Serialize, Clone)]
@@ -15,12 +26,15 @@ pub struct MultiOutputBooster {
     pub n_boosters: usize,
     /// The name of objective function used to optimize.
     /// Valid options include "LogLoss" to use logistic loss as the objective function,
-    /// or "SquaredLoss" to use Squared Error as the objective function.
+    /// "SquaredLoss" to use Squared Error as the objective function,
+    /// "Softmax" to fit the boosters jointly for multiclass classification,
+    /// or "Distributional" to fit the location and the log scale of a distribution jointly.
     pub objective: Objective,
     /// Budget to fit the model.
     #[serde(default = "default_budget")]
     pub budget: f32,
     /// The initial prediction value of the model.
+    #[serde(deserialize_with = "parse_missing")]
     pub base_score: f64,
     /// Number of bins to calculate to partition the data. Setting this to
     /// a smaller number, will result in faster training time, while potentially sacrificing
@@ -65,6 +79,12 @@ pub struct MultiOutputBooster {
     /// Used only in quantile regression.
     #[serde(default = "default_quantile")]
     pub quantile: Option<f64>,
+    /// Weight of the positive class relative to the negative class, used only in binary classification.
+    #[serde(default = "default_scale_pos_weight")]
+    pub scale_pos_weight: Option<f64>,
+    /// Quantiles fit jointly in quantile regression, one booster per quantile, in increasing order.
+    #[serde(default = "default_quantiles")]
+    pub quantiles: Option<Vec<f64>>,
     /// Reset the model or continue training.
     #[serde(default = "default_reset")]
     pub reset: Option<bool>,
@@ -87,6 +107,43 @@ pub struct MultiOutputBooster {
     /// Optional limit for auto stopping rounds.
     #[serde(default = "default_stopping_rounds")]
     pub stopping_rounds: Option<usize>,
+    /// Groups of features allowed to interact, that is to be split on along the same path of a tree.
+    /// A feature outside of every group only interacts with itself, any features may interact if `None`.
+    #[serde(default = "default_interaction_constraints")]
+    pub interaction_constraints: Option<InteractionConstraints>,
+    /// Method to sample the rows each tree is fit on.
+    #[serde(default = "default_sample_method")]
+    pub sample_method: SampleMethod,
+    /// Fraction of the rows sampled for each tree, used with the `Random` sample method.
+    #[serde(default = "default_subsample")]
+    pub subsample: f32,
+    /// Fraction of the rows with the largest gradients kept for each tree, used with the `Goss` sample method.
+    #[serde(default = "default_top_rate")]
+    pub top_rate: f32,
+    /// Fraction of the rows sampled from the rest, used with the `Goss` sample method.
+    #[serde(default = "default_other_rate")]
+    pub other_rate: f32,
+    /// Fraction of the columns sampled for each tree, chosen from the budget and the shape of the data if `None`.
+    #[serde(default = "default_colsample_bytree")]
+    pub colsample_bytree: Option<f32>,
+    /// Fraction of the columns of the tree sampled for each node, all of them if `None`.
+    #[serde(default = "default_colsample_bynode")]
+    pub colsample_bynode: Option<f32>,
+    /// Sampling weight of every feature, used when the columns are sampled.
+    #[serde(default = "default_feature_weights")]
+    pub feature_weights: Option<Vec<f64>>,
+    /// Features part of every column sample, of the trees and of the nodes.
+    #[serde(default = "default_always_include_features")]
+    pub always_include_features: Option<HashSet<usize>>,
+    /// Stop fitting when the evaluation metric on the last evaluation set has not improved
+    /// for this many rounds, and keep only the trees up to its best round.
+    #[serde(default = "default_early_stopping_rounds")]
+    pub early_stopping_rounds: Option<usize>,
+    /// Metric computed on the evaluation sets, the default metric of the objective if `None`.
+    /// The Distributional objective is evaluated with `NegativeLogLikelihood` or `CRPS`, Softmax
+    /// and joint quantiles with their own loss.
+    #[serde(default = "default_eval_metric")]
+    pub eval_metric: Option<Metric>,
 }
 
 fn default_budget() -> f32 {
@@ -95,6 +152,12 @@ fn default_budget() -> f32 {
 fn default_quantile() -> Option<f64> {
     None
 }
+fn default_scale_pos_weight() -> Option<f64> {
+    None
+}
+fn default_quantiles() -> Option<Vec<f64>> {
+    None
+}
 fn default_reset() -> Option<bool> {
     None
 }
@@ -113,6 +176,39 @@ fn default_memory_limit() -> Option<f32> {
 fn default_stopping_rounds() -> Option<usize> {
     None
 }
+fn default_interaction_constraints() -> Option<InteractionConstraints> {
+    None
+}
+fn default_sample_method() -> SampleMethod {
+    SampleMethod::None
+}
+fn default_subsample() -> f32 {
+    1.0
+}
+fn default_top_rate() -> f32 {
+    0.2
+}
+fn default_other_rate() -> f32 {
+    0.1
+}
+fn default_colsample_bytree() -> Option<f32> {
+    None
+}
+fn default_colsample_bynode() -> Option<f32> {
+    None
+}
+fn default_feature_weights() -> Option<Vec<f64>> {
+    None
+}
+fn default_always_include_features() -> Option<HashSet<usize>> {
+    None
+}
+fn default_early_stopping_rounds() -> Option<usize> {
+    None
+}
+fn default_eval_metric() -> Option<Metric> {
+    None
+}
 fn default_terminate_missing_features() -> HashSet<usize> {
     HashSet::new()
 }
@@ -168,7 +264,9 @@ impl MultiOutputBooster {
     /// * `objective` - The name of objective function used to optimize. Valid options are:
     ///      "LogLoss" to use logistic loss as the objective function,
     ///      "SquaredLoss" to use Squared Error as the objective function,
-    ///      "QuantileLoss" for quantile regression.
+    ///      "QuantileLoss" for quantile regression,
+    ///      "Softmax" for multiclass classification, with one booster per class,
+    ///      "Distributional" for distributional regression, with a location and a log scale booster.
     /// * `budget` - budget to fit the model.
     /// * `base_score` - The initial prediction value of the model. If set to None, it will be calculated based on the objective function at fit time.
     /// * `max_bin` - Number of bins to calculate to partition the data. Setting this to
@@ -248,12 +346,25 @@ impl MultiOutputBooster {
             metadata: HashMap::new(),
             seed,
             quantile,
+            scale_pos_weight: None,
+            quantiles: None,
             reset,
             categorical_features,
             timeout,
             iteration_limit,
             memory_limit,
             stopping_rounds,
+            interaction_constraints: None,
+            sample_method: SampleMethod::None,
+            subsample: 1.0,
+            top_rate: 0.2,
+            other_rate: 0.1,
+            colsample_bytree: None,
+            colsample_bynode: None,
+            feature_weights: None,
+            always_include_features: None,
+            early_stopping_rounds: None,
+            eval_metric: None,
         };
 
         let booster = PerpetualBooster::default()
@@ -295,52 +406,381 @@ impl MultiOutputBooster {
         }
     }
 
-    pub fn fit(
+    /// Fit the multi-output booster on a provided dataset. With the Softmax objective, the
+    /// boosters are fit jointly, one tree per class at every boosting round. With the
+    /// QuantileLoss objective and `quantiles` set, the quantiles are fit jointly on a single
+    /// target column, see `fit_quantiles`.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Target matrix with one column per booster. One hot encoded classes for Softmax.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    pub fn fit<D: DataMatrix>(
         &mut self,
-        data: &Matrix<f64>,
+        data: &D,
         y: &Matrix<f64>,
         sample_weight: Option<&[f64]>,
     ) -> Result<(), PerpetualError> {
-        for i in 0..self.n_boosters {
-            let _ = self.boosters[i].fit(data, y.get_col(i), sample_weight);
+        self.fit_with_eval(data, y, sample_weight, &[])
+    }
+
+    /// Fit the multi-output booster, computing the evaluation metric on each evaluation set after every
+    /// boosting round. The targets of an evaluation set are column major, with the columns of `y`.
+    /// A joint fit evaluates the cross entropy over the classes for Softmax, the negative log likelihood
+    /// for Distributional, and the mean quantile loss of the joint quantiles. It records the evaluation
+    /// and training history, the best iteration and the stop reason on the first booster. Otherwise,
+    /// each booster is evaluated on its own column, with its own metric.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Target matrix with one column per booster. One hot encoded classes for Softmax.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    pub fn fit_with_eval<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &Matrix<f64>,
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+    ) -> Result<(), PerpetualError> {
+        self.fit_with_callbacks(data, y, sample_weight, eval_sets, &mut [])
+    }
+
+    /// Fit the multi-output booster, invoking the callbacks before and after every boosting round.
+    /// The callbacks of a joint fit are passed the first booster, with the trees of all the boosters
+    /// in `RoundInfo::trees`. Otherwise, they observe the fit of each booster in turn.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Target matrix with one column per booster. One hot encoded classes for Softmax.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `eval_sets` - Named datasets to evaluate, with the same columns as `data`.
+    /// * `callbacks` - Observers of the boosting rounds.
+    pub fn fit_with_callbacks<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &Matrix<f64>,
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        // The targets are read by column, targets with another layout are copied to column major.
+        let y_col_major = (!y.is_col_contiguous()).then(|| y.to_col_major());
+        let y_matrix = y_col_major.as_ref().map(|v| Matrix::new(v, y.rows, y.cols));
+        let y = y_matrix.as_ref().unwrap_or(y);
+        if let Objective::Softmax = self.objective {
+            return self.fit_softmax(data, y, sample_weight, eval_sets, callbacks);
+        }
+        if let Objective::Distributional { distribution } = self.objective {
+            return self.fit_distribution(data, y, sample_weight, distribution, eval_sets, callbacks);
+        }
+        if let (Objective::QuantileLoss, Some(_)) = (&self.objective, &self.quantiles) {
+            if y.cols != 1 {
+                return Err(PerpetualError::InvalidParameter(
+                    "y".to_string(),
+                    "a single target column for joint quantile regression".to_string(),
+                    format!("{} columns", y.cols),
+                ));
+            }
+            return self.fit_joint_quantiles(data, y.get_col(0), sample_weight, eval_sets, callbacks);
+        }
+        validate_eval_sets(&self.boosters[0], data, eval_sets, self.n_boosters)?;
+        for (i, b) in self.boosters.iter_mut().enumerate() {
+            let booster_eval_sets: Vec<EvalSet<D>> = eval_sets
+                .iter()
+                .map(|e| EvalSet {
+                    y: &e.y[i * e.data.rows()..(i + 1) * e.data.rows()],
+                    ..*e
+                })
+                .collect();
+            b.fit_with_callbacks(data, y.get_col(i), sample_weight, &booster_eval_sets, callbacks)?;
         }
         Ok(())
     }
 
-    pub fn prune(
+    fn fit_softmax<D: DataMatrix>(
         &mut self,
-        data: &Matrix<f64>,
+        data: &D,
         y: &Matrix<f64>,
         sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        if self.n_boosters < 2 || y.cols != self.n_boosters {
+            return Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                format!("one column per class, with n_boosters = {} >= 2", self.n_boosters),
+                format!("{} columns", y.cols),
+            ));
+        }
+        validate_eval_sets(&self.boosters[0], data, eval_sets, self.n_boosters)?;
+
+        let objective = SoftmaxOutputs {
+            y,
+            sample_weight,
+            calc_loss: loss_callables(&Objective::Softmax)?,
+        };
+        self.fit_joint(data, sample_weight, &objective, eval_sets, callbacks)
+    }
+
+    fn fit_distribution<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &Matrix<f64>,
+        sample_weight: Option<&[f64]>,
+        distribution: Distribution,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        if self.n_boosters != 2 || y.cols != 1 {
+            return Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                format!(
+                    "a single target column, with n_boosters = {} = 2 for the location and the log scale",
+                    self.n_boosters
+                ),
+                format!("{} columns", y.cols),
+            ));
+        }
+        let objective = DistributionalLoss::new(distribution);
+        objective.validate_labels(y.get_col(0))?;
+        validate_eval_sets(&self.boosters[0], data, eval_sets, 1)?;
+        let metric = self.eval_metric.unwrap_or_else(|| default_metric(&self.objective));
+        if !matches!(metric, Metric::NegativeLogLikelihood | Metric::CRPS) {
+            return Err(PerpetualError::InvalidParameter(
+                "eval_metric".to_string(),
+                "NegativeLogLikelihood or CRPS for Distributional".to_string(),
+                format!("{:?}", metric),
+            ));
+        }
+
+        let objective = DistributionOutputs {
+            y: y.get_col(0),
+            z: objective.transform(y.get_col(0)),
+            objective,
+            sample_weight,
+            metric,
+            location_loss: loss_callables(&Objective::SquaredLoss)?,
+            scale_loss: Box::new(|y, yhat, w, q| LogScaleLoss::default().calc_loss(y, yhat, w, q)),
+        };
+        self.fit_joint(data, sample_weight, &objective, eval_sets, callbacks)
+    }
+
+    /// Fit all the `quantiles` of a single target at once. Every boosting round grows one tree
+    /// shared by the quantiles, after which each quantile gets its own leaf values, the quantile
+    /// of its residuals in each leaf. Predictions of the quantiles do not cross, see `predict`.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Target variable.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    pub fn fit_quantiles<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
     ) -> Result<(), PerpetualError> {
+        self.fit_joint_quantiles(data, y, sample_weight, &[], &mut [])
+    }
+
+    fn fit_joint_quantiles<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        let quantiles = match &self.quantiles {
+            Some(quantiles) if !quantiles.is_empty() => quantiles.clone(),
+            _ => {
+                return Err(PerpetualError::InvalidParameter(
+                    "quantiles".to_string(),
+                    "at least one quantile".to_string(),
+                    "none".to_string(),
+                ))
+            }
+        };
+        if !matches!(self.objective, Objective::QuantileLoss) {
+            return Err(PerpetualError::InvalidParameter(
+                "objective".to_string(),
+                "QuantileLoss".to_string(),
+                format!("{:?}", self.objective),
+            ));
+        }
+        let in_range = quantiles.iter().all(|q| *q > 0.0 && *q < 1.0);
+        let increasing = quantiles.windows(2).all(|q| q[0] < q[1]);
+        if !in_range || !increasing {
+            return Err(PerpetualError::InvalidParameter(
+                "quantiles".to_string(),
+                "increasing values between 0 and 1".to_string(),
+                format!("{:?}", quantiles),
+            ));
+        }
+        if self.boosters.len() != quantiles.len() {
+            return Err(PerpetualError::InvalidParameter(
+                "n_boosters".to_string(),
+                format!("{}, one booster per quantile", quantiles.len()),
+                self.boosters.len().to_string(),
+            ));
+        }
+        validate_eval_sets(&self.boosters[0], data, eval_sets, 1)?;
+
+        for (b, q) in self.boosters.iter_mut().zip(&quantiles) {
+            b.quantile = Some(*q);
+        }
+        let central = (0..quantiles.len())
+            .min_by(|a, b| (quantiles[*a] - 0.5).abs().total_cmp(&(quantiles[*b] - 0.5).abs()))
+            .unwrap_or(0);
+        let objective = QuantileOutputs {
+            data,
+            y,
+            sample_weight,
+            leaf_fns: quantiles.iter().map(|q| quantile_leaf_callable(*q)).collect(),
+            quantiles,
+            central,
+            calc_loss: loss_callables(&Objective::QuantileLoss)?,
+            eta: eta_from_budget(self.budget),
+            missing: self.missing,
+        };
+        self.fit_joint(data, sample_weight, &objective, eval_sets, callbacks)
+    }
+
+    // Fit the trees of all the boosters together, one tree per booster in every boosting round.
+    // The rounds follow the parameters of the first booster, within the timeout of the multi-output booster.
+    fn fit_joint<D: DataMatrix, O: RoundObjective<D>>(
+        &mut self,
+        data: &D,
+        sample_weight: Option<&[f64]>,
+        objective: &O,
+        eval_sets: &[EvalSet<D>],
+        callbacks: &mut [&mut dyn TrainingCallback],
+    ) -> Result<(), PerpetualError> {
+        if callbacks.iter().any(|c| !c.supports_joint_fit()) {
+            return Err(PerpetualError::InvalidParameter(
+                "callbacks".to_string(),
+                "callbacks supporting joint fits, Checkpoint only restores single output fits".to_string(),
+                "a callback for single output fits".to_string(),
+            ));
+        }
+        let lead = &self.boosters[0];
+        lead.validate_parameters()?;
+        lead.validate_features(data.cols())?;
+        lead.validate_feature_names(data)?;
+        let feature_names = lead
+            .feature_names
+            .clone()
+            .or_else(|| data.feature_names().map(|names| names.to_vec()));
+
+        let constraints_map = self
+            .monotone_constraints
+            .as_ref()
+            .unwrap_or(&ConstraintMap::new())
+            .to_owned();
+
+        let eta = eta_from_budget(self.budget);
+        for b in self.boosters.iter_mut() {
+            b.set_eta(self.budget);
+            b.timeout = self.timeout;
+            b.feature_names.clone_from(&feature_names);
+        }
+        self.boosters[0].fit_state = None;
+
+        if self.create_missing_branch {
+            let splitter = MissingBranchSplitter::new(
+                eta,
+                self.allow_missing_splits,
+                constraints_map,
+                self.terminate_missing_features.clone(),
+                self.missing_node_treatment,
+                self.force_children_to_bound_parent,
+            )
+            .set_interaction_constraints(self.interaction_constraints.clone());
+            fit_rounds(
+                &mut self.boosters,
+                data,
+                sample_weight,
+                objective,
+                &splitter,
+                eval_sets,
+                callbacks,
+            )
+        } else {
+            let splitter = MissingImputerSplitter::new(eta, self.allow_missing_splits, constraints_map)
+                .set_interaction_constraints(self.interaction_constraints.clone());
+            fit_rounds(
+                &mut self.boosters,
+                data,
+                sample_weight,
+                objective,
+                &splitter,
+                eval_sets,
+                callbacks,
+            )
+        }
+    }
+
+    pub fn prune<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        y: &Matrix<f64>,
+        sample_weight: Option<&[f64]>,
+    ) -> Result<(), PerpetualError> {
+        let y_col_major = (!y.is_col_contiguous()).then(|| y.to_col_major());
+        let y_matrix = y_col_major.as_ref().map(|v| Matrix::new(v, y.rows, y.cols));
+        let y = y_matrix.as_ref().unwrap_or(y);
         for i in 0..self.n_boosters {
             let _ = self.boosters[i].prune(data, y.get_col(i), sample_weight);
         }
         Ok(())
     }
 
-    /// Generate predictions on data using the multi-output booster.
+    /// Generate predictions on data using the multi-output booster. When the quantiles were
+    /// fit jointly, the predictions of every row are sorted, so that the quantiles never cross.
     ///
     /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
     /// * `parallel` -  Predict in parallel.
-    pub fn predict(&self, data: &Matrix<f64>, parallel: bool) -> Vec<f64> {
-        self.boosters
+    pub fn predict<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
+        let mut preds = self
+            .boosters
             .iter()
             .map(|b| b.predict(data, parallel))
             .into_iter()
             .flatten()
-            .collect::<Vec<f64>>()
+            .collect::<Vec<f64>>();
+        if let (Objective::QuantileLoss, Some(_)) = (&self.objective, &self.quantiles) {
+            let mut row_preds = vec![0.0; self.n_boosters];
+            for row in 0..data.rows() {
+                for (q, p) in row_preds.iter_mut().enumerate() {
+                    *p = preds[q * data.rows() + row];
+                }
+                row_preds.sort_by(|a, b| a.total_cmp(b));
+                for (q, p) in row_preds.iter().enumerate() {
+                    preds[q * data.rows() + row] = *p;
+                }
+            }
+        }
+        preds
+    }
+
+    /// Generate the parameters of the predictive distribution of each row, with the Distributional
+    /// objective. Returned by parameter, the locations of all the rows followed by their scales, as
+    /// `predict` returns the predictions of each booster. These are the mean and the standard deviation
+    /// for Normal, and those of the log of the target for LogNormal.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `parallel` -  Predict in parallel.
+    pub fn predict_dist<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
+        let loc = self.boosters[0].predict(data, parallel);
+        let log_scale = self.boosters[1].predict(data, parallel);
+        loc.into_iter().chain(log_scale.into_iter().map(|s| s.exp())).collect()
     }
 
     /// Generate probabilities on data using the multi-output booster.
     ///
     /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
     /// * `parallel` -  Predict in parallel.
-    pub fn predict_proba(&self, data: &Matrix<f64>, parallel: bool) -> Vec<f64> {
+    pub fn predict_proba<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
         let log_odds = self.predict(data, parallel);
-        let data_log_odds = Matrix::new(&log_odds, data.rows, data.cols);
+        let data_log_odds = Matrix::new(&log_odds, data.rows(), self.n_boosters);
         let mut preds = Vec::with_capacity(log_odds.len());
-        for row in 0..data.rows {
+        for row in 0..data.rows() {
             let y_p_exp = data_log_odds.get_row(row).iter().map(|e| e.exp()).collect::<Vec<f64>>();
             let y_p_exp_sum = y_p_exp.iter().sum::<f64>();
             let probabilities = y_p_exp.iter().map(|e| e / y_p_exp_sum).collect::<Vec<f64>>();
@@ -578,6 +1018,32 @@ impl MultiOutputBooster {
         self
     }
 
+    /// Set the weight of the positive class on the booster.
+    /// * `scale_pos_weight` - used only in binary classification, with LogLoss or FocalLoss.
+    pub fn set_scale_pos_weight(mut self, scale_pos_weight: Option<f64>) -> Self {
+        self.scale_pos_weight = scale_pos_weight;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_scale_pos_weight(scale_pos_weight))
+            .collect();
+        self
+    }
+
+    /// Set the quantiles on the booster. This will also set one booster per quantile.
+    /// * `quantiles` - quantiles fit jointly in quantile regression, in increasing order.
+    pub fn set_quantiles(mut self, quantiles: Option<Vec<f64>>) -> Self {
+        if let Some(qs) = &quantiles {
+            self.n_boosters = qs.len();
+            self.boosters = qs
+                .iter()
+                .map(|q| self.boosters[0].clone().set_quantile(Some(*q)))
+                .collect();
+        }
+        self.quantiles = quantiles;
+        self
+    }
+
     /// Set the reset on the booster.
     /// * `reset` - Reset the model or continue training.
     pub fn set_reset(mut self, reset: Option<bool>) -> Self {
@@ -642,6 +1108,134 @@ impl MultiOutputBooster {
         self
     }
 
+    /// Set the interaction constraints on the booster.
+    /// * `interaction_constraints` - groups of features allowed to interact, that is to be split on along the same path of a tree.
+    pub fn set_interaction_constraints(mut self, interaction_constraints: Option<InteractionConstraints>) -> Self {
+        self.interaction_constraints = interaction_constraints.clone();
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_interaction_constraints(interaction_constraints.clone()))
+            .collect();
+        self
+    }
+
+    /// Set the sample method on the booster.
+    /// * `sample_method` - method to sample the rows each tree is fit on.
+    pub fn set_sample_method(mut self, sample_method: SampleMethod) -> Self {
+        self.sample_method = sample_method;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_sample_method(sample_method))
+            .collect();
+        self
+    }
+
+    /// Set the subsample on the booster.
+    /// * `subsample` - fraction of the rows sampled for each tree, used with the `Random` sample method.
+    pub fn set_subsample(mut self, subsample: f32) -> Self {
+        self.subsample = subsample;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_subsample(subsample))
+            .collect();
+        self
+    }
+
+    /// Set the top rate on the booster.
+    /// * `top_rate` - fraction of the rows with the largest gradients kept for each tree, used with the `Goss` sample method.
+    pub fn set_top_rate(mut self, top_rate: f32) -> Self {
+        self.top_rate = top_rate;
+        self.boosters = self.boosters.iter().map(|b| b.clone().set_top_rate(top_rate)).collect();
+        self
+    }
+
+    /// Set the other rate on the booster.
+    /// * `other_rate` - fraction of the rows sampled from the rest, used with the `Goss` sample method.
+    pub fn set_other_rate(mut self, other_rate: f32) -> Self {
+        self.other_rate = other_rate;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_other_rate(other_rate))
+            .collect();
+        self
+    }
+
+    /// Set the column sample rate of the trees on the booster.
+    /// * `colsample_bytree` - fraction of the columns sampled for each tree, chosen from the budget and the shape of the data if `None`.
+    pub fn set_colsample_bytree(mut self, colsample_bytree: Option<f32>) -> Self {
+        self.colsample_bytree = colsample_bytree;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_colsample_bytree(colsample_bytree))
+            .collect();
+        self
+    }
+
+    /// Set the column sample rate of the nodes on the booster.
+    /// * `colsample_bynode` - fraction of the columns of the tree sampled for each node, all of them if `None`.
+    pub fn set_colsample_bynode(mut self, colsample_bynode: Option<f32>) -> Self {
+        self.colsample_bynode = colsample_bynode;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_colsample_bynode(colsample_bynode))
+            .collect();
+        self
+    }
+
+    /// Set the feature weights on the booster.
+    /// * `feature_weights` - sampling weight of every feature, used when the columns are sampled.
+    pub fn set_feature_weights(mut self, feature_weights: Option<Vec<f64>>) -> Self {
+        self.feature_weights = feature_weights.clone();
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_feature_weights(feature_weights.clone()))
+            .collect();
+        self
+    }
+
+    /// Set the always included features on the booster.
+    /// * `always_include_features` - features part of every column sample.
+    pub fn set_always_include_features(mut self, always_include_features: Option<HashSet<usize>>) -> Self {
+        self.always_include_features = always_include_features.clone();
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_always_include_features(always_include_features.clone()))
+            .collect();
+        self
+    }
+
+    /// Set the early stopping rounds on the booster.
+    /// * `early_stopping_rounds` - optional number of rounds without improvement of the evaluation metric before stopping.
+    pub fn set_early_stopping_rounds(mut self, early_stopping_rounds: Option<usize>) -> Self {
+        self.early_stopping_rounds = early_stopping_rounds;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_early_stopping_rounds(early_stopping_rounds))
+            .collect();
+        self
+    }
+
+    /// Set the evaluation metric on the booster.
+    /// * `eval_metric` - metric computed on the evaluation sets, the default metric of the objective if None.
+    pub fn set_eval_metric(mut self, eval_metric: Option<Metric>) -> Self {
+        self.eval_metric = eval_metric;
+        self.boosters = self
+            .boosters
+            .iter()
+            .map(|b| b.clone().set_eval_metric(eval_metric))
+            .collect();
+        self
+    }
+
     /// Insert metadata
     /// * `key` - String value for the metadata key.
     /// * `value` - value to assign to the metadata key.
@@ -656,14 +1250,272 @@ impl MultiOutputBooster {
     }
 }
 
+/// The classes of a Softmax fit. Every round fits one tree per class, on the gradients of the cross
+/// entropy at the margins from the start of the round. The budget and the stopping rules are judged
+/// on the cross entropy over all classes.
+struct SoftmaxOutputs<'a> {
+    y: &'a Matrix<'a, f64>,
+    sample_weight: Option<&'a [f64]>,
+    calc_loss: LossFn,
+}
+
+impl<D: DataMatrix> RoundObjective<D> for SoftmaxOutputs<'_> {
+    fn base_scores(&self) -> Vec<f64> {
+        (0..self.y.cols)
+            .map(|k| Softmax::default().calc_init(self.y.get_col(k), self.sample_weight, None))
+            .collect()
+    }
+
+    fn loss(&self, yhat: &[Vec<f64>]) -> Vec<f32> {
+        Softmax::joint_loss(self.y, yhat, self.sample_weight)
+    }
+
+    fn targets<'a>(&'a self, yhat: &'a [Vec<f64>]) -> Vec<TreeTarget<'a>> {
+        let objective = Softmax::default();
+        let offsets = Softmax::offsets(yhat);
+        (0..self.y.cols)
+            .map(|k| {
+                let y_k = self.y.get_col(k);
+                let margin: Vec<f64> = yhat[k].iter().zip(&offsets[k]).map(|(z, o)| z - o).collect();
+                let (grad, hess) = objective.calc_grad_hess(y_k, &margin, self.sample_weight, None);
+                let loss = objective.calc_loss(y_k, &margin, self.sample_weight, None);
+                TreeTarget {
+                    grad,
+                    hess,
+                    loss: TreeLoss::Records {
+                        loss,
+                        y: Cow::Borrowed(y_k),
+                        calc_loss: &self.calc_loss,
+                        yhat: Cow::Owned(margin),
+                        sample_weight: self.sample_weight.map(Cow::Borrowed),
+                        quantile: None,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn evaluate(&self, eval_set: &EvalSet<D>, yhat: &[Vec<f64>], sample_weight: &[f64]) -> f64 {
+        let y = Matrix::new(eval_set.y, eval_set.data.rows(), yhat.len());
+        let loss = Softmax::joint_loss(&y, yhat, Some(sample_weight));
+        loss.iter().map(|l| *l as f64).sum::<f64>() / sample_weight.iter().sum::<f64>()
+    }
+
+    fn maximize(&self) -> bool {
+        false
+    }
+}
+
+/// The location and the log scale of a Distributional fit. Every round fits a tree for each parameter,
+/// with the other parameter held at its value from the start of the round. The budget is judged on the
+/// standardized squared residuals at the base scores, the starting loss of the location trees.
+struct DistributionOutputs<'a> {
+    objective: DistributionalLoss,
+    y: &'a [f64],
+    /// The targets on the scale of the location.
+    z: Vec<f64>,
+    sample_weight: Option<&'a [f64]>,
+    /// `NegativeLogLikelihood` or `CRPS`.
+    metric: Metric,
+    location_loss: LossFn,
+    scale_loss: LossFn,
+}
+
+impl<D: DataMatrix> RoundObjective<D> for DistributionOutputs<'_> {
+    fn base_scores(&self) -> Vec<f64> {
+        let (loc, log_scale) = self.objective.calc_init_params(&self.z, self.sample_weight);
+        vec![loc, log_scale]
+    }
+
+    fn loss(&self, yhat: &[Vec<f64>]) -> Vec<f32> {
+        self.objective
+            .negative_log_likelihood(self.y, &yhat[0], &yhat[1], self.sample_weight)
+            .iter()
+            .map(|l| *l as f32)
+            .collect()
+    }
+
+    fn budget_loss(&self, yhat: &[Vec<f64>]) -> Vec<f32> {
+        let weights = DistributionalLoss::location_weights(&yhat[1], self.sample_weight);
+        SquaredLoss::default().calc_loss(&self.z, &yhat[0], Some(&weights), None)
+    }
+
+    fn targets<'a>(&'a self, yhat: &'a [Vec<f64>]) -> Vec<TreeTarget<'a>> {
+        let location_weights = DistributionalLoss::location_weights(&yhat[1], self.sample_weight);
+        let (grad, hess) = SquaredLoss::default().calc_grad_hess(&self.z, &yhat[0], Some(&location_weights), None);
+        let loss = SquaredLoss::default().calc_loss(&self.z, &yhat[0], Some(&location_weights), None);
+        let location = TreeTarget {
+            grad,
+            hess,
+            loss: TreeLoss::Records {
+                loss,
+                y: Cow::Borrowed(&self.z),
+                calc_loss: &self.location_loss,
+                yhat: Cow::Borrowed(&yhat[0]),
+                sample_weight: Some(Cow::Owned(location_weights)),
+                quantile: None,
+            },
+        };
+
+        let scale_targets = DistributionalLoss::scale_targets(&self.z, &yhat[0]);
+        let (grad, hess) = LogScaleLoss::default().calc_grad_hess(&scale_targets, &yhat[1], self.sample_weight, None);
+        let loss = LogScaleLoss::default().calc_loss(&scale_targets, &yhat[1], self.sample_weight, None);
+        let scale = TreeTarget {
+            grad,
+            hess,
+            loss: TreeLoss::Records {
+                loss,
+                y: Cow::Owned(scale_targets),
+                calc_loss: &self.scale_loss,
+                yhat: Cow::Borrowed(&yhat[1]),
+                sample_weight: self.sample_weight.map(Cow::Borrowed),
+                quantile: None,
+            },
+        };
+        vec![location, scale]
+    }
+
+    fn evaluate(&self, eval_set: &EvalSet<D>, yhat: &[Vec<f64>], sample_weight: &[f64]) -> f64 {
+        let scale: Vec<f64> = yhat[1].iter().map(|s| s.exp()).collect();
+        let distribution = self.objective.distribution;
+        match self.metric {
+            Metric::CRPS => metric::crps(distribution, eval_set.y, &yhat[0], &scale, sample_weight),
+            _ => metric::negative_log_likelihood(distribution, eval_set.y, &yhat[0], &scale, sample_weight),
+        }
+    }
+
+    fn maximize(&self) -> bool {
+        false
+    }
+}
+
+/// The quantiles of a joint quantile fit. Every round grows one tree shared by the quantiles, on the
+/// mean of their gradients, after which each quantile gets its own leaf values. The budget and the
+/// stopping rules are judged on the loss of the quantile closest to the median, which is also the
+/// quantile the tree growth measures its loss decrement on.
+struct QuantileOutputs<'a, D> {
+    data: &'a D,
+    y: &'a [f64],
+    sample_weight: Option<&'a [f64]>,
+    quantiles: Vec<f64>,
+    leaf_fns: Vec<LeafFn>,
+    /// Index of the quantile closest to the median.
+    central: usize,
+    calc_loss: LossFn,
+    eta: f32,
+    missing: f64,
+}
+
+impl<D: DataMatrix> RoundObjective<D> for QuantileOutputs<'_, D> {
+    fn base_scores(&self) -> Vec<f64> {
+        self.quantiles
+            .iter()
+            .map(|q| QuantileLoss::default().calc_init(self.y, self.sample_weight, Some(*q)))
+            .collect()
+    }
+
+    fn loss(&self, yhat: &[Vec<f64>]) -> Vec<f32> {
+        let central_quantile = Some(self.quantiles[self.central]);
+        QuantileLoss::default().calc_loss(self.y, &yhat[self.central], self.sample_weight, central_quantile)
+    }
+
+    fn targets<'a>(&'a self, yhat: &'a [Vec<f64>]) -> Vec<TreeTarget<'a>> {
+        let n_quantiles = self.quantiles.len();
+        let mut grad = vec![0.0_f32; self.y.len()];
+        let mut hess = None;
+        for (yhat_q, q) in yhat.iter().zip(&self.quantiles) {
+            let (grad_q, hess_q) = QuantileLoss::default().calc_grad_hess(self.y, yhat_q, self.sample_weight, Some(*q));
+            grad.iter_mut()
+                .zip(grad_q)
+                .for_each(|(g, g_q)| *g += g_q / n_quantiles as f32);
+            hess = hess_q;
+        }
+        vec![TreeTarget {
+            grad,
+            hess,
+            loss: TreeLoss::Records {
+                loss: RoundObjective::<D>::loss(self, yhat),
+                y: Cow::Borrowed(self.y),
+                calc_loss: &self.calc_loss,
+                yhat: Cow::Borrowed(&yhat[self.central]),
+                sample_weight: self.sample_weight.map(Cow::Borrowed),
+                quantile: Some(self.quantiles[self.central]),
+            },
+        }]
+    }
+
+    fn output_trees(
+        &self,
+        trees: Vec<Tree>,
+        yhat: &[Vec<f64>],
+        index: &[usize],
+        sample_weight: Option<&[f64]>,
+    ) -> Vec<Tree> {
+        yhat.iter()
+            .zip(&self.leaf_fns)
+            .map(|(yhat_q, leaf_fn)| {
+                let mut tree_q = trees[0].clone();
+                tree_q.refit_leaf_values(
+                    self.data,
+                    index,
+                    self.y,
+                    yhat_q,
+                    sample_weight,
+                    self.eta,
+                    &self.missing,
+                    leaf_fn,
+                );
+                tree_q
+            })
+            .collect()
+    }
+
+    fn base_offsets(&self, yhat: &[Vec<f64>]) -> Vec<f64> {
+        yhat.iter()
+            .zip(&self.leaf_fns)
+            .map(|(yhat_q, leaf_fn)| leaf_fn(self.y, yhat_q, self.sample_weight))
+            .collect()
+    }
+
+    fn evaluate(&self, eval_set: &EvalSet<D>, yhat: &[Vec<f64>], sample_weight: &[f64]) -> f64 {
+        let total: f64 = yhat
+            .iter()
+            .zip(&self.quantiles)
+            .map(|(yhat_q, q)| {
+                evaluate_metric(
+                    &Metric::QuantileLoss,
+                    eval_set.y,
+                    yhat_q,
+                    sample_weight,
+                    Some(*q as f32),
+                    None,
+                    None,
+                )
+            })
+            .sum();
+        total / self.quantiles.len() as f64
+    }
+
+    fn maximize(&self) -> bool {
+        false
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::booster::booster::StopReason;
+    use crate::booster::callback::{CallbackAction, Checkpoint, RoundInfo};
+    use crate::booster::test_data::synthetic_features;
+    use crate::metric::{crps, negative_log_likelihood};
     use crate::utils::between;
+    use approx::assert_relative_eq;
     use polars::{
         io::SerReader,
         prelude::{CsvReadOptions, DataType},
     };
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
     use std::error::Error;
 
     #[test]
@@ -767,4 +1619,337 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_multi_output_booster_softmax() -> Result<(), Box<dyn Error>> {
+        let n_classes = 3;
+        let n_rows = 600;
+        let mut rng = StdRng::seed_from_u64(0);
+        let x0: Vec<f64> = (0..n_rows).map(|_| rng.random_range(0.0..1.0)).collect();
+        let x1: Vec<f64> = (0..n_rows).map(|_| rng.random_range(0.0..1.0)).collect();
+        let classes: Vec<usize> = x0.iter().map(|v| usize::min((v * 3.0) as usize, 2)).collect();
+
+        let data_vec: Vec<f64> = x0.iter().chain(x1.iter()).copied().collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let y_data: Vec<f64> = (0..n_classes)
+            .flat_map(|k| classes.iter().map(move |c| if *c == k { 1.0 } else { 0.0 }))
+            .collect();
+        let y = Matrix::new(&y_data, n_rows, n_classes);
+
+        let mut booster = MultiOutputBooster::default()
+            .set_objective(Objective::Softmax)
+            .set_n_boosters(n_classes)
+            .set_budget(0.5);
+        booster.fit(&data, &y, None)?;
+
+        // Every round grows one tree per class.
+        let n_trees = booster.get_boosters()[0].get_prediction_trees().len();
+        assert!(n_trees > 0);
+        assert!(booster
+            .get_boosters()
+            .iter()
+            .all(|b| b.get_prediction_trees().len() == n_trees));
+
+        // Probabilities are returned row by row.
+        let probas = booster.predict_proba(&data, true);
+        let mut n_correct = 0;
+        for (p, c) in probas.chunks(n_classes).zip(&classes) {
+            assert!(between(0.999, 1.001, p.iter().sum::<f64>() as f32));
+            let pred = (0..n_classes).max_by(|a, b| p[*a].total_cmp(&p[*b])).unwrap();
+            if pred == *c {
+                n_correct += 1;
+            }
+        }
+        assert!(n_correct as f64 / n_rows as f64 > 0.9);
+
+        // Row major and f32 data are fit without conversion, row major data to the same trees.
+        let row_major_vec: Vec<f64> = (0..n_rows).flat_map(|i| [x0[i], x1[i]]).collect();
+        let row_major = Matrix::new_row_major(&row_major_vec, n_rows, 2);
+        let mut booster_row_major = booster.clone();
+        booster_row_major.fit(&row_major, &y, None)?;
+        assert_eq!(booster_row_major.predict_proba(&row_major, true), probas);
+        let data_f32_vec: Vec<f32> = data_vec.iter().map(|v| *v as f32).collect();
+        let data_f32 = Matrix::new(&data_f32_vec, n_rows, 2);
+        let mut booster_f32 = booster.clone();
+        booster_f32.fit(&data_f32, &y, None)?;
+        let probas_f32 = booster_f32.predict_proba(&data_f32, true);
+        let n_correct_f32 = probas_f32
+            .chunks(n_classes)
+            .zip(&classes)
+            .filter(|(p, c)| (0..n_classes).max_by(|a, b| p[*a].total_cmp(&p[*b])) == Some(**c))
+            .count();
+        assert!(n_correct_f32 as f64 / n_rows as f64 > 0.9);
+
+        // Softmax needs one column of y per class, and all the classes together.
+        let y_single = Matrix::new(y.get_col(0), n_rows, 1);
+        assert!(booster.fit(&data, &y_single, None).is_err());
+        let mut single = PerpetualBooster::default().set_objective(Objective::Softmax);
+        assert!(single.fit(&data, y.get_col(0), None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_output_booster_quantiles() -> Result<(), Box<dyn Error>> {
+        let n_rows = 2000;
+        let mut rng = StdRng::seed_from_u64(0);
+        let x0: Vec<f64> = (0..n_rows).map(|_| rng.random_range(0.0..1.0)).collect();
+        let x1: Vec<f64> = (0..n_rows).map(|_| rng.random_range(0.0..1.0)).collect();
+        // The noise grows with x0, so the quantiles spread out.
+        let y: Vec<f64> = x0
+            .iter()
+            .map(|v| 5.0 * v + (0.1 + v) * rng.random_range(-1.0..1.0))
+            .collect();
+
+        let data_vec: Vec<f64> = x0.iter().chain(x1.iter()).copied().collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let y_matrix = Matrix::new(&y, n_rows, 1);
+
+        let quantiles = vec![0.1, 0.5, 0.9];
+        let mut booster = MultiOutputBooster::default()
+            .set_objective(Objective::QuantileLoss)
+            .set_quantiles(Some(quantiles.clone()))
+            .set_budget(0.5);
+        assert_eq!(booster.get_boosters().len(), quantiles.len());
+        booster.fit(&data, &y_matrix, None)?;
+
+        // The trees of the quantiles share their splits, with their own leaf values.
+        let trees: Vec<&[Tree]> = booster
+            .get_boosters()
+            .iter()
+            .map(|b| b.get_prediction_trees())
+            .collect();
+        assert!(!trees[0].is_empty());
+        for t in &trees[1..] {
+            assert_eq!(t.len(), trees[0].len());
+            for (a, b) in t.iter().zip(trees[0]) {
+                assert_eq!(a.nodes.len(), b.nodes.len());
+            }
+        }
+
+        // Quantiles do not cross, and cover about the expected share of the records.
+        let preds = booster.predict(&data, true);
+        let mut covered = vec![0; quantiles.len()];
+        for row in 0..n_rows {
+            for q in 0..quantiles.len() {
+                if q > 0 {
+                    assert!(preds[(q - 1) * n_rows + row] <= preds[q * n_rows + row]);
+                }
+                if y[row] <= preds[q * n_rows + row] {
+                    covered[q] += 1;
+                }
+            }
+        }
+        for (c, q) in covered.iter().zip(&quantiles) {
+            let share = *c as f64 / n_rows as f64;
+            assert!(
+                (share - q).abs() < 0.05,
+                "{} of the records below quantile {}",
+                share,
+                q
+            );
+        }
+
+        // Joint quantiles need a single target column and increasing quantiles.
+        let y_two = Matrix::new(&data_vec, n_rows, 2);
+        assert!(booster.fit(&data, &y_two, None).is_err());
+        let mut unordered = MultiOutputBooster::default()
+            .set_objective(Objective::QuantileLoss)
+            .set_quantiles(Some(vec![0.9, 0.1]));
+        assert!(unordered.fit(&data, &y_matrix, None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_output_booster_softmax_eval_sets() -> Result<(), Box<dyn Error>> {
+        struct RoundTrees(Vec<usize>);
+        impl TrainingCallback for RoundTrees {
+            fn after_iteration(&mut self, _booster: &PerpetualBooster, info: &RoundInfo) -> CallbackAction {
+                self.0.push(info.trees.len());
+                CallbackAction::Continue
+            }
+        }
+
+        let (n_classes, n_rows) = (3, 1200);
+        let data_vec = synthetic_features(n_rows);
+        // The class follows the first feature, with every seventh label flipped to the next class.
+        let classes: Vec<usize> = (0..n_rows)
+            .map(|i| (usize::min((data_vec[i] * 3.0) as usize, 2) + usize::from(i % 7 == 0)) % n_classes)
+            .collect();
+        let y_data: Vec<f64> = (0..n_classes)
+            .flat_map(|k| classes.iter().map(move |c| if *c == k { 1.0 } else { 0.0 }))
+            .collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let y = Matrix::new(&y_data, n_rows, n_classes);
+        let eval_sets = [EvalSet {
+            name: "train",
+            data: &data,
+            y: &y_data,
+            sample_weight: None,
+            group: None,
+            event: None,
+        }];
+
+        let mut booster = MultiOutputBooster::default()
+            .set_objective(Objective::Softmax)
+            .set_n_boosters(n_classes)
+            .set_budget(1.0)
+            .set_sample_method(SampleMethod::Goss)
+            .set_colsample_bytree(Some(0.5))
+            .set_interaction_constraints(Some(vec![HashSet::from([0]), HashSet::from([1])]))
+            .set_early_stopping_rounds(Some(3));
+        let mut callback = RoundTrees(Vec::new());
+        booster.fit_with_callbacks(&data, &y, None, &eval_sets, &mut [&mut callback])?;
+
+        // The history of the joint fit is recorded on the first booster.
+        let lead = &booster.get_boosters()[0];
+        let history = &lead.evaluation_history["train"];
+        assert_eq!(history.len(), callback.0.len());
+        assert_eq!(lead.training_history.len(), history.len());
+        assert!(callback.0.iter().all(|n| *n == n_classes));
+        assert!(history[0] < (n_classes as f64).ln());
+
+        // Early stopping keeps the trees of every booster up to the round with the lowest cross entropy.
+        let n_trees = lead.trees.len();
+        assert!(booster.get_boosters().iter().all(|b| b.trees.len() == n_trees));
+        assert_eq!(lead.stop_reason, Some(StopReason::EarlyStopping));
+        assert_eq!(lead.best_iteration, Some(n_trees));
+        assert_eq!(
+            history[n_trees - 1],
+            history.iter().copied().fold(f64::INFINITY, f64::min)
+        );
+
+        // A tree splits on a single feature, as the features are not allowed to interact.
+        for b in booster.get_boosters() {
+            for tree in &b.trees {
+                let features: HashSet<usize> = tree
+                    .nodes
+                    .values()
+                    .filter(|n| !n.is_leaf)
+                    .map(|n| n.split_feature)
+                    .collect();
+                assert!(features.len() <= 1);
+            }
+        }
+
+        // A checkpoint of the first booster can not restore the joint fit.
+        let path = std::env::temp_dir().join("perpetual_test_softmax_checkpoint.json");
+        let mut checkpoint = Checkpoint::new(path.to_str().unwrap(), 1);
+        assert!(matches!(
+            booster.fit_with_callbacks(&data, &y, None, &[], &mut [&mut checkpoint]),
+            Err(PerpetualError::InvalidParameter(..))
+        ));
+        assert!(!path.exists());
+
+        // The targets of an evaluation set have one column per class.
+        let eval_sets = [EvalSet {
+            y: y.get_col(0),
+            ..eval_sets[0]
+        }];
+        assert!(booster.fit_with_eval(&data, &y, None, &eval_sets).is_err());
+        assert!(booster.fit_with_eval(&data, &y, None, &[]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_output_booster_distributional() -> Result<(), Box<dyn Error>> {
+        let n_rows = 2000;
+        let mut rng = StdRng::seed_from_u64(0);
+        let x0: Vec<f64> = (0..n_rows).map(|_| rng.random_range(0.0..1.0)).collect();
+        let x1: Vec<f64> = (0..n_rows).map(|_| rng.random_range(0.0..1.0)).collect();
+        // The mean follows the first feature and the standard deviation the second one.
+        let sd: Vec<f64> = x1.iter().map(|v| 0.2 + 2.0 * v).collect();
+        let y: Vec<f64> = x0
+            .iter()
+            .zip(&sd)
+            .map(|(v, s)| {
+                let normal: f64 = (0..12).map(|_| rng.random_range(0.0..1.0)).sum::<f64>() - 6.0;
+                5.0 * v + s * normal
+            })
+            .collect();
+        let data_vec: Vec<f64> = x0.iter().chain(x1.iter()).copied().collect();
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let y_matrix = Matrix::new(&y, n_rows, 1);
+        let w = vec![1.0; n_rows];
+
+        let mut booster = MultiOutputBooster::default()
+            .set_objective(Objective::Distributional {
+                distribution: Distribution::Normal,
+            })
+            .set_n_boosters(2)
+            .set_budget(0.5);
+        booster.fit(&data, &y_matrix, None)?;
+
+        let dist = booster.predict_dist(&data, true);
+        let (loc, scale) = dist.split_at(n_rows);
+        let scale_error = scale.iter().zip(&sd).map(|(a, b)| (a - b).abs()).sum::<f64>() / n_rows as f64;
+        assert!(scale_error < 0.2);
+
+        // Better than a single Normal for all the records.
+        let nll = negative_log_likelihood(Distribution::Normal, &y, loc, scale, &w);
+        let mean = y.iter().sum::<f64>() / n_rows as f64;
+        let sd_all = (y.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n_rows as f64).sqrt();
+        let nll_all = negative_log_likelihood(Distribution::Normal, &y, &vec![mean; n_rows], &vec![sd_all; n_rows], &w);
+        assert!(nll < nll_all - 0.3);
+        assert!(
+            crps(Distribution::Normal, &y, loc, scale, &w)
+                < crps(Distribution::Normal, &y, &vec![mean; n_rows], &vec![sd_all; n_rows], &w)
+        );
+
+        // LogNormal needs positive targets.
+        let y_exp: Vec<f64> = y.iter().map(|v| v.exp()).collect();
+        let mut booster = MultiOutputBooster::default()
+            .set_objective(Objective::Distributional {
+                distribution: Distribution::LogNormal,
+            })
+            .set_n_boosters(2)
+            .set_budget(0.5);
+        booster.fit(&data, &Matrix::new(&y_exp, n_rows, 1), None)?;
+        let dist_log = booster.predict_dist(&data, true);
+        let scale_log = &dist_log[n_rows..];
+        let scale_error = scale_log.iter().zip(&sd).map(|(a, b)| (a - b).abs()).sum::<f64>() / n_rows as f64;
+        assert!(scale_error < 0.2);
+        assert!(booster.fit(&data, &y_matrix, None).is_err());
+
+        // The evaluation sets are scored with the metric of the booster, which can stop the fit early.
+        let eval_sets = [EvalSet {
+            name: "train",
+            data: &data,
+            y: &y,
+            sample_weight: None,
+            group: None,
+            event: None,
+        }];
+        let mut booster = MultiOutputBooster::default()
+            .set_objective(Objective::Distributional {
+                distribution: Distribution::Normal,
+            })
+            .set_n_boosters(2)
+            .set_budget(0.5)
+            .set_eval_metric(Some(Metric::CRPS))
+            .set_early_stopping_rounds(Some(5));
+        booster.fit_with_eval(&data, &y_matrix, None, &eval_sets)?;
+        let dist = booster.predict_dist(&data, true);
+        let history = &booster.boosters[0].evaluation_history["train"];
+        let best = booster.boosters[0].best_iteration.unwrap();
+        assert_relative_eq!(
+            history[best - 1],
+            crps(Distribution::Normal, &y, &dist[..n_rows], &dist[n_rows..], &w),
+            max_relative = 1e-9
+        );
+        let booster = booster.set_eval_metric(Some(Metric::RootMeanSquaredError));
+        assert!(booster
+            .clone()
+            .fit_with_eval(&data, &y_matrix, None, &eval_sets)
+            .is_err());
+
+        let mut single = MultiOutputBooster::default().set_objective(Objective::Distributional {
+            distribution: Distribution::Normal,
+        });
+        assert!(single.fit(&data, &y_matrix, None).is_err());
+
+        Ok(())
+    }
 }
@@ -3,7 +3,8 @@ use std::collections::HashMap;
 use rayon::prelude::*;
 
 use crate::{
-    objective::Objective, shapley::predict_contributions_row_shapley, tree::Tree, utils::odds, Matrix, PerpetualBooster,
+    data::DataMatrix, errors::PerpetualError, objective::Objective, shapley::predict_contributions_row_shapley,
+    tree::Tree, utils::odds, PerpetualBooster,
 };
 
 use super::booster::ContributionsMethod;
@@ -13,21 +14,55 @@ impl PerpetualBooster {
     ///
     /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
     /// * `parallel` -  Predict in parallel.
-    pub fn predict(&self, data: &Matrix<f64>, parallel: bool) -> Vec<f64> {
-        let mut init_preds = vec![self.base_score; data.rows];
+    ///
+    /// Panics if the data does not have the columns of the booster, see `try_predict`.
+    pub fn predict<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
+        self.try_predict(data, parallel).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Generate predictions on data using the gradient booster, or an error if the columns of the
+    /// data are named, with other names than the feature names of the booster, or if the trees
+    /// split on columns the data does not have.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `parallel` -  Predict in parallel.
+    pub fn try_predict<D: DataMatrix>(&self, data: &D, parallel: bool) -> Result<Vec<f64>, PerpetualError> {
+        self.validate_prediction_data(data)?;
+        let mut init_preds = vec![self.base_score; data.rows()];
         self.get_prediction_trees().iter().for_each(|tree| {
             for (p_, val) in init_preds.iter_mut().zip(tree.predict(data, parallel, &self.missing)) {
                 *p_ += val;
             }
         });
-        init_preds
+        Ok(init_preds)
+    }
+
+    // Check that the data has the columns the trees split on, with the names of the features.
+    fn validate_prediction_data<D: DataMatrix>(&self, data: &D) -> Result<(), PerpetualError> {
+        self.validate_feature_names(data)?;
+        let n_features = self
+            .get_prediction_trees()
+            .iter()
+            .flat_map(|t| t.nodes.values())
+            .filter(|n| !n.is_leaf)
+            .map(|n| n.split_feature + 1)
+            .max()
+            .unwrap_or(0);
+        if data.cols() < n_features {
+            return Err(PerpetualError::InvalidParameter(
+                "data".to_string(),
+                format!("at least {} columns, as split on by the trees", n_features),
+                data.cols().to_string(),
+            ));
+        }
+        Ok(())
     }
 
     /// Generate probabilities on data using the gradient booster.
     ///
     /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
     /// * `parallel` -  Predict in parallel.
-    pub fn predict_proba(&self, data: &Matrix<f64>, parallel: bool) -> Vec<f64> {
+    pub fn predict_proba<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
         let preds = self.predict(data, parallel);
         if parallel {
             preds.par_iter().map(|p| 1.0 / (1.0 + (-p).exp())).collect()
@@ -36,34 +71,91 @@ impl PerpetualBooster {
         }
     }
 
+    /// Generate predictions on the scale of the target, by applying the inverse link
+    /// of the objective to the raw margins returned by `predict`. This is the exponential for
+    /// the log link objectives (PoissonLoss, GammaLoss and TweedieLoss), the hazard ratios for
+    /// CoxLoss, the survival times for AFTLoss, the probability for LogLoss, FocalLoss and CrossEntropy, and the margins
+    /// themselves for any other objective.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `parallel` -  Predict in parallel.
+    pub fn predict_response<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
+        match self.objective {
+            Objective::LogLoss | Objective::FocalLoss { .. } | Objective::CrossEntropy => {
+                self.predict_proba(data, parallel)
+            }
+            Objective::PoissonLoss
+            | Objective::GammaLoss
+            | Objective::TweedieLoss { .. }
+            | Objective::CoxLoss
+            | Objective::AFTLoss { .. } => {
+                let preds = self.predict(data, parallel);
+                if parallel {
+                    preds.par_iter().map(|p| p.exp()).collect()
+                } else {
+                    preds.iter().map(|p| p.exp()).collect()
+                }
+            }
+            _ => self.predict(data, parallel),
+        }
+    }
+
     /// Predict the contributions matrix for the provided dataset.
-    pub fn predict_contributions(&self, data: &Matrix<f64>, method: ContributionsMethod, parallel: bool) -> Vec<f64> {
-        match method {
+    ///
+    /// Panics if the data does not have the columns of the booster, or if the method is
+    /// `ProbabilityChange` without the LogLoss objective, see `try_predict_contributions`.
+    pub fn predict_contributions<D: DataMatrix>(
+        &self,
+        data: &D,
+        method: ContributionsMethod,
+        parallel: bool,
+    ) -> Vec<f64> {
+        self.try_predict_contributions(data, method, parallel)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Predict the contributions matrix for the provided dataset, or an error if the data does not
+    /// have the columns of the booster, see `try_predict`, or if the method is `ProbabilityChange`
+    /// without the LogLoss objective.
+    pub fn try_predict_contributions<D: DataMatrix>(
+        &self,
+        data: &D,
+        method: ContributionsMethod,
+        parallel: bool,
+    ) -> Result<Vec<f64>, PerpetualError> {
+        self.validate_prediction_data(data)?;
+        let contribs = match method {
             ContributionsMethod::Average => self.predict_contributions_average(data, parallel),
             ContributionsMethod::ProbabilityChange => {
-                match self.objective {
-                    Objective::LogLoss => {}
-                    _ => panic!("ProbabilityChange contributions method is only valid when LogLoss objective is used."),
+                if !matches!(self.objective, Objective::LogLoss) {
+                    return Err(PerpetualError::InvalidParameter(
+                        "method".to_string(),
+                        "a contributions method other than ProbabilityChange, which needs the LogLoss objective"
+                            .to_string(),
+                        format!("{:?}", self.objective),
+                    ));
                 }
                 self.predict_contributions_probability_change(data, parallel)
             }
             _ => self.predict_contributions_tree_alone(data, parallel, method),
-        }
+        };
+        Ok(contribs)
     }
 
     // All of the contribution calculation methods, except for average are calculated
     // using just the model, so we don't need to have separate methods, we can instead
     // just have this one method, that dispatches to each one respectively.
-    fn predict_contributions_tree_alone(
+    fn predict_contributions_tree_alone<D: DataMatrix>(
         &self,
-        data: &Matrix<f64>,
+        data: &D,
         parallel: bool,
         method: ContributionsMethod,
     ) -> Vec<f64> {
-        let mut contribs = vec![0.; (data.cols + 1) * data.rows];
+        let missing = &data.missing_as(&self.missing);
+        let mut contribs = vec![0.; (data.cols() + 1) * data.rows()];
 
         // Add the bias term to every bias value...
-        let bias_idx = data.cols + 1;
+        let bias_idx = data.cols() + 1;
         contribs
             .iter_mut()
             .skip(bias_idx - 1)
@@ -83,23 +175,23 @@ impl PerpetualBooster {
         // trees seems to be the fastest approach (5X faster), we should test
         // something like this for normal predictions.
         if parallel {
-            data.index
+            data.index()
                 .par_iter()
-                .zip(contribs.par_chunks_mut(data.cols + 1))
+                .zip(contribs.par_chunks_mut(data.cols() + 1))
                 .for_each(|(row, c)| {
-                    let r_ = data.get_row(*row);
+                    let r_ = data.get_row_f64(*row);
                     self.get_prediction_trees().iter().for_each(|t| {
-                        row_pred_fn(t, &r_, c, &self.missing);
+                        row_pred_fn(t, &r_, c, missing);
                     });
                 });
         } else {
-            data.index
+            data.index()
                 .iter()
-                .zip(contribs.chunks_mut(data.cols + 1))
+                .zip(contribs.chunks_mut(data.cols() + 1))
                 .for_each(|(row, c)| {
-                    let r_ = data.get_row(*row);
+                    let r_ = data.get_row_f64(*row);
                     self.get_prediction_trees().iter().for_each(|t| {
-                        row_pred_fn(t, &r_, c, &self.missing);
+                        row_pred_fn(t, &r_, c, missing);
                     });
                 });
         }
@@ -110,7 +202,8 @@ impl PerpetualBooster {
     /// Generate predictions on data using the gradient booster.
     ///
     /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
-    fn predict_contributions_average(&self, data: &Matrix<f64>, parallel: bool) -> Vec<f64> {
+    fn predict_contributions_average<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
+        let missing = &data.missing_as(&self.missing);
         let weights: Vec<HashMap<usize, f64>> = if parallel {
             self.get_prediction_trees()
                 .par_iter()
@@ -122,10 +215,10 @@ impl PerpetualBooster {
                 .map(|t| t.distribute_leaf_weights())
                 .collect()
         };
-        let mut contribs = vec![0.0; (data.cols + 1) * data.rows];
+        let mut contribs = vec![0.0; (data.cols() + 1) * data.rows()];
 
         // Add the bias term to every bias value...
-        let bias_idx = data.cols + 1;
+        let bias_idx = data.cols() + 1;
         contribs
             .iter_mut()
             .skip(bias_idx - 1)
@@ -137,29 +230,29 @@ impl PerpetualBooster {
         // trees seems to be the fastest approach (5X faster), we should test
         // something like this for normal predictions.
         if parallel {
-            data.index
+            data.index()
                 .par_iter()
-                .zip(contribs.par_chunks_mut(data.cols + 1))
+                .zip(contribs.par_chunks_mut(data.cols() + 1))
                 .for_each(|(row, c)| {
-                    let r_ = data.get_row(*row);
+                    let r_ = data.get_row_f64(*row);
                     self.get_prediction_trees()
                         .iter()
                         .zip(weights.iter())
                         .for_each(|(t, w)| {
-                            t.predict_contributions_row_average(&r_, c, w, &self.missing);
+                            t.predict_contributions_row_average(&r_, c, w, missing);
                         });
                 });
         } else {
-            data.index
+            data.index()
                 .iter()
-                .zip(contribs.chunks_mut(data.cols + 1))
+                .zip(contribs.chunks_mut(data.cols() + 1))
                 .for_each(|(row, c)| {
-                    let r_ = data.get_row(*row);
+                    let r_ = data.get_row_f64(*row);
                     self.get_prediction_trees()
                         .iter()
                         .zip(weights.iter())
                         .for_each(|(t, w)| {
-                            t.predict_contributions_row_average(&r_, c, w, &self.missing);
+                            t.predict_contributions_row_average(&r_, c, w, missing);
                         });
                 });
         }
@@ -167,9 +260,10 @@ impl PerpetualBooster {
         contribs
     }
 
-    fn predict_contributions_probability_change(&self, data: &Matrix<f64>, parallel: bool) -> Vec<f64> {
-        let mut contribs = vec![0.; (data.cols + 1) * data.rows];
-        let bias_idx = data.cols + 1;
+    fn predict_contributions_probability_change<D: DataMatrix>(&self, data: &D, parallel: bool) -> Vec<f64> {
+        let missing = &data.missing_as(&self.missing);
+        let mut contribs = vec![0.; (data.cols() + 1) * data.rows()];
+        let bias_idx = data.cols() + 1;
         contribs
             .iter_mut()
             .skip(bias_idx - 1)
@@ -177,23 +271,23 @@ impl PerpetualBooster {
             .for_each(|v| *v += odds(self.base_score));
 
         if parallel {
-            data.index
+            data.index()
                 .par_iter()
-                .zip(contribs.par_chunks_mut(data.cols + 1))
+                .zip(contribs.par_chunks_mut(data.cols() + 1))
                 .for_each(|(row, c)| {
-                    let r_ = data.get_row(*row);
+                    let r_ = data.get_row_f64(*row);
                     self.get_prediction_trees().iter().fold(self.base_score, |acc, t| {
-                        t.predict_contributions_row_probability_change(&r_, c, &self.missing, acc)
+                        t.predict_contributions_row_probability_change(&r_, c, missing, acc)
                     });
                 });
         } else {
-            data.index
+            data.index()
                 .iter()
-                .zip(contribs.chunks_mut(data.cols + 1))
+                .zip(contribs.chunks_mut(data.cols() + 1))
                 .for_each(|(row, c)| {
-                    let r_ = data.get_row(*row);
+                    let r_ = data.get_row_f64(*row);
                     self.get_prediction_trees().iter().fold(self.base_score, |acc, t| {
-                        t.predict_contributions_row_probability_change(&r_, c, &self.missing, acc)
+                        t.predict_contributions_row_probability_change(&r_, c, missing, acc)
                     });
                 });
         }
@@ -0,0 +1,757 @@
+//! The boosting rounds shared by the fits of `PerpetualBooster` and `MultiOutputBooster`.
+
+use crate::binning::BinnedMatrix;
+use crate::booster::booster::{
+    allocate_histograms, build_thread_pool, column_amount, nodes_to_allocate, target_loss_decrement, EvalSet, FitState,
+    RoundRecord, StopReason, StoppingState,
+};
+use crate::booster::callback::{CallbackAction, RoundInfo, TrainingCallback};
+use crate::constants::{GENERALIZATION_THRESHOLD_RELAXED, ITER_LIMIT, STOPPING_ROUNDS};
+use crate::data::{DataMatrix, Matrix};
+use crate::errors::PerpetualError;
+use crate::histogram::{update_cuts, NodeHistogram};
+use crate::metric::is_comparison_better;
+use crate::objective::LossFn;
+use crate::sampler::{ColumnSampler, GossSampler, RandomSampler, SampleMethod, Sampler};
+use crate::splitter::{SplitInfo, SplitInfoSlice, Splitter};
+use crate::tree::{Tree, TreeStopper};
+use crate::PerpetualBooster;
+use log::{info, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::borrow::Cow;
+use std::time::Instant;
+
+/// Loss a tree measures the decrement of, when it is grown.
+pub(crate) enum TreeLoss<'a> {
+    /// The loss of the records, at their targets and predictions.
+    Records {
+        loss: Vec<f32>,
+        y: Cow<'a, [f64]>,
+        calc_loss: &'a LossFn,
+        yhat: Cow<'a, [f64]>,
+        sample_weight: Option<Cow<'a, [f64]>>,
+        quantile: Option<f64>,
+    },
+    /// The second order expansion of the loss, from the gradient and hessian of each record,
+    /// relative to the current loss.
+    SecondOrder(&'a LossFn),
+}
+
+/// Gradient, hessian and loss a tree of a round is grown on.
+pub(crate) struct TreeTarget<'a> {
+    pub(crate) grad: Vec<f32>,
+    /// `None` when the hessian is constant.
+    pub(crate) hess: Option<Vec<f32>>,
+    pub(crate) loss: TreeLoss<'a>,
+}
+
+/// The outputs of a fit, one per booster, and the way each boosting round grows their trees.
+pub(crate) trait RoundObjective<D> {
+    /// Initial prediction of each output.
+    fn base_scores(&self) -> Vec<f64>;
+
+    /// Training loss of each record, at the predictions of every output.
+    fn loss(&self, yhat: &[Vec<f64>]) -> Vec<f32>;
+
+    /// Loss of each record the budget is judged on, at the base scores of every output.
+    fn budget_loss(&self, yhat: &[Vec<f64>]) -> Vec<f32> {
+        self.loss(yhat)
+    }
+
+    /// Targets of the trees grown in a round, at the predictions from the start of the round.
+    fn targets<'a>(&'a self, yhat: &'a [Vec<f64>]) -> Vec<TreeTarget<'a>>;
+
+    /// The tree of each output, from the trees grown in a round on the rows of `index`. The
+    /// `sample_weight` of those rows are scaled up as their gradients, when GOSS samples them.
+    fn output_trees(
+        &self,
+        trees: Vec<Tree>,
+        _yhat: &[Vec<f64>],
+        _index: &[usize],
+        _sample_weight: Option<&[f64]>,
+    ) -> Vec<Tree> {
+        trees
+    }
+
+    /// Shift of the base score of each output that fits a new batch of data, at the predictions of the
+    /// booster a fit continues. Defaults to a Newton step on a constant, from the targets of the trees.
+    fn base_offsets(&self, yhat: &[Vec<f64>]) -> Vec<f64> {
+        self.targets(yhat).iter().map(newton_offset).collect()
+    }
+
+    /// Evaluation metric of an evaluation set, at the predictions of every output.
+    fn evaluate(&self, eval_set: &EvalSet<D>, yhat: &[Vec<f64>], sample_weight: &[f64]) -> f64;
+
+    /// Whether a larger evaluation metric is better.
+    fn maximize(&self) -> bool;
+}
+
+/// Constant that minimizes the second order approximation of the loss of a tree target, zero when
+/// the hessian does not allow for one.
+pub(crate) fn newton_offset(target: &TreeTarget) -> f64 {
+    let grad: f64 = target.grad.iter().map(|g| *g as f64).sum();
+    let hess: f64 = target
+        .hess
+        .as_ref()
+        .map_or(target.grad.len() as f64, |h| h.iter().map(|h| *h as f64).sum());
+    let offset = -grad / hess;
+    if hess > 0.0 && offset.is_finite() {
+        offset
+    } else {
+        0.0
+    }
+}
+
+/// Check the evaluation sets against the data, with `n_targets` target columns per set.
+pub(crate) fn validate_eval_sets<D: DataMatrix>(
+    booster: &PerpetualBooster,
+    data: &D,
+    eval_sets: &[EvalSet<D>],
+    n_targets: usize,
+) -> Result<(), PerpetualError> {
+    if booster.early_stopping_rounds.is_some() && eval_sets.is_empty() {
+        return Err(PerpetualError::InvalidParameter(
+            "eval_sets".to_string(),
+            "at least one evaluation set for early stopping".to_string(),
+            "none".to_string(),
+        ));
+    }
+    let mut names = std::collections::HashSet::new();
+    for e in eval_sets {
+        if !names.insert(e.name) {
+            return Err(PerpetualError::InvalidParameter(
+                "eval_sets".to_string(),
+                "unique evaluation set names".to_string(),
+                e.name.to_string(),
+            ));
+        }
+        if e.data.cols() != data.cols() {
+            return Err(PerpetualError::InvalidParameter(
+                format!("eval_sets[{}]", e.name),
+                format!("{} columns", data.cols()),
+                e.data.cols().to_string(),
+            ));
+        }
+        if e.y.len() != e.data.rows() * n_targets || e.sample_weight.is_some_and(|w| w.len() != e.data.rows()) {
+            return Err(PerpetualError::InvalidParameter(
+                format!("eval_sets[{}]", e.name),
+                format!(
+                    "{} targets and {} sample weights",
+                    e.data.rows() * n_targets,
+                    e.data.rows()
+                ),
+                e.y.len().to_string(),
+            ));
+        }
+        if e.group.is_some_and(|g| g.len() != e.data.rows()) {
+            return Err(PerpetualError::InvalidParameter(
+                format!("eval_sets[{}]", e.name),
+                format!("{} query ids", e.data.rows()),
+                e.group.map_or(0, |g| g.len()).to_string(),
+            ));
+        }
+        if e.event.is_some_and(|v| v.len() != e.data.rows()) {
+            return Err(PerpetualError::InvalidParameter(
+                format!("eval_sets[{}]", e.name),
+                format!("{} event indicators", e.data.rows()),
+                e.event.map_or(0, |v| v.len()).to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Fit the trees of the boosters round by round, adding one tree to each booster in every round.
+/// The parameters of the fit are those of the first booster, which also records the evaluation
+/// and training history, the bin cuts and the state of the fit.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fit_rounds<D: DataMatrix, S: Splitter, O: RoundObjective<D>>(
+    boosters: &mut [PerpetualBooster],
+    data: &D,
+    sample_weight: Option<&[f64]>,
+    objective: &O,
+    splitter: &S,
+    eval_sets: &[EvalSet<D>],
+    callbacks: &mut [&mut dyn TrainingCallback],
+) -> Result<(), PerpetualError> {
+    let start = Instant::now();
+
+    let lead = &boosters[0];
+    let pool = build_thread_pool(lead.num_threads);
+    let (budget, seed, log_iterations, timeout) = (lead.budget, lead.seed, lead.log_iterations, lead.timeout);
+    let (stopping_rounds, early_stopping_rounds) = (
+        lead.stopping_rounds.unwrap_or(STOPPING_ROUNDS),
+        lead.early_stopping_rounds,
+    );
+    let iteration_limit = lead.iteration_limit.unwrap_or(ITER_LIMIT);
+
+    // If reset, reset the trees. Otherwise continue training, on a new batch of data.
+    // A resumed fit picks up the state of the fit it continues.
+    let resume = boosters[0].fit_state.take();
+    let continued = match &resume {
+        Some(state) => state.continued,
+        None => !(boosters[0].reset.unwrap_or(true) || boosters.iter().all(|b| b.trees.is_empty())),
+    };
+    let n_trees_start = match &resume {
+        Some(state) => state.n_trees_start,
+        None if continued => boosters[0].trees.len(),
+        None => 0,
+    };
+    if let (true, Some(cuts)) = (continued || resume.is_some(), &boosters[0].cuts) {
+        if cuts.cols != data.cols() {
+            return Err(PerpetualError::InvalidParameter(
+                "data".to_string(),
+                format!("{} columns, as the data the booster was fit on", cuts.cols),
+                data.cols().to_string(),
+            ));
+        }
+    }
+    let batch = if continued {
+        boosters[0].trees[..n_trees_start].last().map_or(0, |t| t.batch + 1)
+    } else {
+        0
+    };
+    if !continued && resume.is_none() {
+        let base_scores = boosters
+            .iter()
+            .any(|b| b.base_score.is_nan())
+            .then(|| objective.base_scores());
+        for (k, b) in boosters.iter_mut().enumerate() {
+            b.reset();
+            if let (true, Some(base_scores)) = (b.base_score.is_nan(), &base_scores) {
+                b.base_score = base_scores[k];
+            }
+        }
+    }
+
+    // The predictions at the start of the fit, followed by the trees a resumed fit already has,
+    // added in the same order as in the rounds.
+    let mut yhat: Vec<Vec<f64>> = Vec::with_capacity(boosters.len());
+    let mut eval_yhat: Vec<Vec<Vec<f64>>> = eval_sets.iter().map(|_| Vec::with_capacity(boosters.len())).collect();
+    for b in boosters.iter_mut() {
+        let fit_trees = b.trees.split_off(n_trees_start);
+        let mut yhat_b = if continued {
+            b.predict(data, true)
+        } else {
+            vec![b.base_score; data.rows()]
+        };
+        let mut eval_yhat_b: Vec<Vec<f64>> = eval_sets.iter().map(|e| b.predict(e.data, true)).collect();
+        for tree in &fit_trees {
+            b.update_predictions_inplace(&mut yhat_b, tree, data);
+            for (e, yhat_) in eval_sets.iter().zip(eval_yhat_b.iter_mut()) {
+                b.update_predictions_inplace(yhat_, tree, e.data);
+            }
+        }
+        b.trees.extend(fit_trees);
+        yhat.push(yhat_b);
+        eval_yhat
+            .iter_mut()
+            .zip(eval_yhat_b)
+            .for_each(|(e, yhat_)| e.push(yhat_));
+    }
+
+    // The new batch may have drifted from the data the booster was fit on, so the base scores are
+    // shifted to fit it before the trees of the batch are added. A resumed fit was already shifted.
+    if continued && resume.is_none() {
+        let offsets = objective.base_offsets(&yhat);
+        for (k, (b, offset)) in boosters.iter_mut().zip(offsets).enumerate() {
+            info!("Shifting the base score by {} to fit the new batch of data.", offset);
+            b.base_score += offset;
+            yhat[k].iter_mut().for_each(|y_| *y_ += offset);
+            eval_yhat
+                .iter_mut()
+                .for_each(|e| e[k].iter_mut().for_each(|y_| *y_ += offset));
+        }
+    }
+
+    let maximize = objective.maximize();
+    let eval_weights: Vec<Vec<f64>> = eval_sets
+        .iter()
+        .map(|e| e.sample_weight.map_or_else(|| vec![1.0; e.data.rows()], |w| w.to_vec()))
+        .collect();
+    let mut stop_reason = StopReason::IterationLimit;
+    let FitState {
+        round: start_round,
+        mut stopping,
+        mut n_low_loss_rounds,
+        mut best_round,
+        stop: finished,
+        ..
+    } = resume.unwrap_or_else(|| {
+        // A continued fit carries on the stopping counters of the fit it continues.
+        let StoppingState {
+            stopping,
+            n_low_loss_rounds,
+        } = if continued {
+            boosters[0].stopping_state
+        } else {
+            StoppingState::default()
+        };
+        FitState {
+            round: 0,
+            stopping,
+            n_low_loss_rounds,
+            best_round: None,
+            stop: None,
+            n_trees_start,
+            continued,
+        }
+    });
+
+    // The hessian is only stored when it is not constant, this holds for
+    // custom objectives as well as the built in ones.
+    // GOSS scales the hessian of the rows it samples, so a constant hessian is stored as ones.
+    let lead = &boosters[0];
+    let is_const_hess =
+        lead.sample_method != SampleMethod::Goss && objective.targets(&yhat).iter().all(|t| t.hess.is_none());
+
+    let base_yhat: Vec<Vec<f64>> = boosters.iter().map(|b| vec![b.base_score; data.rows()]).collect();
+    let loss_base = objective.budget_loss(&base_yhat);
+    let loss_avg = loss_base.iter().sum::<f32>() / loss_base.len() as f32;
+
+    let target_loss_decrement = target_loss_decrement(budget, loss_avg);
+
+    let (yhat_zeros, loss_zeros) = (vec![0.0; data.rows()], vec![0.0; data.rows()]);
+
+    // Generate binned data
+    //
+    // In scikit-learn, they sample 200_000 records for generating the bins.
+    // we could consider that, especially if this proved to be a large bottleneck...
+    // Continued fits keep the cuts of the first fit, so that the splits of all the trees
+    // are made on the same bins.
+    let binned_data = match (continued || start_round > 0, &lead.cuts) {
+        (true, Some(cuts)) => data.bin_with_cuts(cuts, lead.missing),
+        _ => data.bin(
+            sample_weight,
+            lead.max_bin,
+            lead.missing,
+            lead.categorical_features.as_ref(),
+        )?,
+    };
+    // Sparse data keeps the bins of the items outside of the zero bin of each column, in place of
+    // the bins of every item.
+    let bdata;
+    let mut bmatrix = match binned_data.sparse.as_ref() {
+        Some(sparse) => BinnedMatrix::sparse(sparse, data.rows()),
+        None => {
+            bdata = Matrix::new(&binned_data.binned_data, data.rows(), data.cols());
+            BinnedMatrix::Dense(&bdata)
+        }
+    };
+
+    // Each round draws the seed of its own generator, so that a resumed fit
+    // restores the state of the generator by skipping the draws of the earlier rounds.
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut sampler: Option<Box<dyn Sampler>> = match lead.sample_method {
+        SampleMethod::None => None,
+        SampleMethod::Random => Some(Box::new(RandomSampler::new(lead.subsample))),
+        SampleMethod::Goss => Some(Box::new(GossSampler::new(lead.top_rate, lead.other_rate))),
+    };
+    (0..start_round).for_each(|_| {
+        rng.random::<u64>();
+    });
+
+    let col_sampler = ColumnSampler::new(
+        lead.colsample_bynode,
+        lead.feature_weights.clone(),
+        lead.always_include_features.as_ref(),
+    );
+    let col_index = col_sampler.columns(data.cols());
+    let n_always_included = lead.always_include_features.as_ref().map_or(0, |f| f.len());
+    let col_amount = match lead.colsample_bytree {
+        Some(colsample_bytree) => ((colsample_bytree * data.cols() as f32).ceil() as usize).clamp(1, data.cols()),
+        None => column_amount(budget, data.rows(), data.cols()),
+    }
+    .max(n_always_included)
+    .min(col_index.len());
+    let n_nodes_alloc = nodes_to_allocate(&binned_data, lead.max_bin, col_amount, lead.memory_limit);
+
+    // The histograms are shared by the trees of a round, since those are fit one after the other.
+    let mut hist_tree_owned = allocate_histograms(
+        &binned_data,
+        &col_index,
+        lead.max_bin,
+        col_amount,
+        n_nodes_alloc,
+        is_const_hess,
+    );
+
+    let mut hist_tree: Vec<NodeHistogram> = hist_tree_owned
+        .iter_mut()
+        .map(|node_hist| NodeHistogram::from_owned(node_hist))
+        .collect();
+
+    let mut split_info_vec: Vec<SplitInfo> = (0..col_amount).map(|_| SplitInfo::default()).collect();
+    let split_info_slice = SplitInfoSlice::new(&mut split_info_vec);
+
+    let categorical_features = lead.categorical_features.clone();
+    if !continued && start_round == 0 {
+        boosters[0].cuts = Some(binned_data.cuts.clone());
+    }
+
+    let end_round = match finished {
+        // The interrupted fit had already stopped after its last round.
+        Some(reason) => {
+            stop_reason = reason;
+            start_round
+        }
+        None => iteration_limit,
+    };
+    for i in start_round..end_round {
+        let mut round_rng = StdRng::seed_from_u64(rng.random::<u64>());
+        // Rounds are numbered by the index of their tree, counting the trees of the fits this one continues.
+        let round = n_trees_start + i;
+        let verbose = if log_iterations == 0 {
+            false
+        } else {
+            i % log_iterations == 0
+        };
+
+        // Every callback observes the round, even when an earlier one requests a stop.
+        let mut stop = false;
+        for c in callbacks.iter_mut() {
+            stop |= c.before_iteration(round) == CallbackAction::Stop;
+        }
+        if stop {
+            info!("Stopping since a callback requested it.");
+            stop_reason = StopReason::Callback;
+            break;
+        }
+
+        let tld = if n_low_loss_rounds > (stopping_rounds + 1) {
+            None
+        } else {
+            Some(target_loss_decrement)
+        };
+
+        let col_index_sample: Vec<usize> = if col_amount == col_index.len() {
+            Vec::new()
+        } else {
+            col_sampler.sample(&mut round_rng, &col_index, col_amount)
+        };
+
+        let col_index_fit = if col_amount == col_index.len() {
+            &col_index
+        } else {
+            &col_index_sample
+        };
+
+        if col_amount != col_index.len() {
+            hist_tree.iter().for_each(|h| {
+                update_cuts(h, col_index_fit, &binned_data.cuts, true);
+            })
+        }
+
+        let mut targets = objective.targets(&yhat);
+        if !is_const_hess {
+            targets.iter_mut().for_each(|t| {
+                t.hess.get_or_insert_with(|| vec![1.0; data.rows()]);
+            });
+        }
+
+        let mut row_scale = None;
+        let (index, oob_index) = match sampler.as_mut() {
+            Some(sampler) => {
+                // The rows of a round with several trees are sampled on the norm of their gradients.
+                let mut sample_grad = match targets.as_slice() {
+                    [t] => t.grad.clone(),
+                    _ => (0..data.rows())
+                        .map(|i| targets.iter().map(|t| t.grad[i] * t.grad[i]).sum::<f32>().sqrt())
+                        .collect(),
+                };
+                let scale = row_scale.insert(vec![1.0_f32; data.rows()]);
+                let sample = sampler.sample(&mut round_rng, data.index(), &mut sample_grad, Some(scale));
+                // GOSS scales up the gradient and hessian of the rows it samples from the rest.
+                for t in targets.iter_mut() {
+                    t.grad.iter_mut().zip(scale.iter()).for_each(|(g, s)| *g *= s);
+                    if let Some(h) = t.hess.as_mut() {
+                        h.iter_mut().zip(scale.iter()).for_each(|(h_, s)| *h_ *= s);
+                    }
+                }
+                match sample {
+                    (index, _) if index.is_empty() => (data.index().to_owned(), Vec::new()),
+                    sample => sample,
+                }
+            }
+            None => (data.index().to_owned(), Vec::new()),
+        };
+
+        let mut trees = Vec::with_capacity(targets.len());
+        for target in targets.iter_mut() {
+            let expansion: (Vec<f64>, Vec<f64>);
+            let (tree_loss, tree_y, tree_calc_loss, tree_yhat, tree_sample_weight, tree_quantile) = match &target.loss {
+                TreeLoss::SecondOrder(calc_loss) => {
+                    let h = target
+                        .hess
+                        .as_ref()
+                        .expect("the hessian of the objective is not constant");
+                    expansion = (
+                        target.grad.iter().map(|g_| *g_ as f64).collect(),
+                        h.iter().map(|h_| *h_ as f64).collect(),
+                    );
+                    (
+                        loss_zeros.as_slice(),
+                        expansion.0.as_slice(),
+                        *calc_loss,
+                        yhat_zeros.as_slice(),
+                        Some(expansion.1.as_slice()),
+                        None,
+                    )
+                }
+                TreeLoss::Records {
+                    loss,
+                    y,
+                    calc_loss,
+                    yhat,
+                    sample_weight,
+                    quantile,
+                } => (
+                    loss.as_slice(),
+                    y.as_ref(),
+                    *calc_loss,
+                    yhat.as_ref(),
+                    sample_weight.as_deref(),
+                    *quantile,
+                ),
+            };
+
+            // The splitter keeps the gradient and hessian in the order of the index, so the ones
+            // of the sampled rows are gathered into their own arrays.
+            let mut sampled_grad_hess = (index.len() != data.index().len()).then(|| {
+                let g: Vec<f32> = index.iter().map(|i| target.grad[*i]).collect();
+                let h: Option<Vec<f32>> = target.hess.as_ref().map(|h| index.iter().map(|i| h[*i]).collect());
+                (g, h)
+            });
+            let (tree_grad, tree_hess) = match sampled_grad_hess.as_mut() {
+                Some((g, h)) => (g.as_mut_slice(), h.as_deref_mut()),
+                None => (target.grad.as_mut_slice(), target.hess.as_deref_mut()),
+            };
+
+            // The nodes draw their columns from a generator seeded by the round.
+            let mut node_rng = col_sampler
+                .samples_nodes()
+                .then(|| StdRng::seed_from_u64(round_rng.random::<u64>()));
+
+            let mut tree = Tree::new();
+            tree.fit(
+                &mut bmatrix,
+                index.clone(),
+                col_index_fit,
+                node_rng.as_mut().map(|rng| (&col_sampler, rng)),
+                tree_grad,
+                tree_hess,
+                splitter,
+                &pool,
+                tld,
+                tree_loss,
+                tree_y,
+                tree_calc_loss,
+                tree_yhat,
+                tree_sample_weight,
+                tree_quantile,
+                is_const_hess,
+                &mut hist_tree,
+                categorical_features.as_ref(),
+                &split_info_slice,
+                n_nodes_alloc,
+            );
+            trees.push(tree);
+        }
+        drop(targets);
+
+        // A round only counts towards stopping, when none of its trees made progress.
+        let weak_round = trees.iter().all(|tree| {
+            let generalization = tree
+                .nodes
+                .values()
+                .map(|n| n.generalization.unwrap_or(0.0))
+                .max_by(|a, b| a.total_cmp(b))
+                .unwrap_or(0.0);
+            tree.nodes.len() < 5
+                && generalization < GENERALIZATION_THRESHOLD_RELAXED
+                && tree.stopper != TreeStopper::StepSize
+        });
+        if weak_round {
+            stopping += 1;
+            // If no root node can be split due to no positive split gain, stop boosting.
+            if trees.iter().all(|tree| tree.nodes.len() == 1) {
+                stop_reason = StopReason::NoSplit;
+                break;
+            }
+        }
+
+        if trees.iter().all(|tree| tree.stopper != TreeStopper::StepSize) {
+            n_low_loss_rounds += 1;
+        } else {
+            n_low_loss_rounds = 0;
+        }
+
+        let refit_weight: Option<Cow<[f64]>> = match &row_scale {
+            Some(scale) => Some(Cow::Owned(
+                scale
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| sample_weight.map_or(1.0, |w| w[i]) * *s as f64)
+                    .collect(),
+            )),
+            None => sample_weight.map(Cow::Borrowed),
+        };
+        let mut trees = objective.output_trees(trees, &yhat, &index, refit_weight.as_deref());
+        for ((b, yhat_b), tree) in boosters.iter().zip(yhat.iter_mut()).zip(&trees) {
+            b.update_predictions_inplace(yhat_b, tree, data);
+        }
+
+        let loss = objective.loss(&yhat);
+        let loss_mean = loss.iter().sum::<f32>() / loss.len() as f32;
+        let oob_loss =
+            (!oob_index.is_empty()).then(|| oob_index.iter().map(|i| loss[*i]).sum::<f32>() / oob_index.len() as f32);
+
+        if verbose {
+            info!(
+                "round {:0?}, tree.nodes: {:1?}, tree.depth: {:2?}, tree.stopper: {:3?}, loss: {:4?}",
+                i,
+                trees.iter().map(|t| t.nodes.len()).collect::<Vec<_>>(),
+                trees.iter().map(|t| t.depth).collect::<Vec<_>>(),
+                trees.iter().map(|t| t.stopper.clone()).collect::<Vec<_>>(),
+                loss_mean,
+            );
+        }
+
+        // Add the predictions of the new trees to each evaluation set, and record the evaluation metric.
+        // The metric of the last evaluation set decides the best round.
+        let mut eval_value = None;
+        for ((e, yhat_e), w) in eval_sets.iter().zip(eval_yhat.iter_mut()).zip(&eval_weights) {
+            for ((b, yhat_b), tree) in boosters.iter().zip(yhat_e.iter_mut()).zip(&trees) {
+                b.update_predictions_inplace(yhat_b, tree, e.data);
+            }
+            let v = objective.evaluate(e, yhat_e, w);
+            boosters[0]
+                .evaluation_history
+                .entry(e.name.to_string())
+                .or_default()
+                .push(v);
+            eval_value = Some(v);
+        }
+
+        let lead = &mut boosters[0];
+        lead.training_history.push(RoundRecord {
+            round,
+            loss: loss_mean,
+            oob_loss,
+            n_nodes: trees.iter().map(|t| t.nodes.len()).sum(),
+            depth: trees.iter().map(|t| t.depth).max().unwrap_or(0),
+            stopper: trees[0].stopper.clone(),
+            elapsed: start.elapsed().as_secs_f32(),
+            eval_metrics: eval_sets
+                .iter()
+                .filter_map(|e| Some((e.name.to_string(), *lead.evaluation_history.get(e.name)?.last()?)))
+                .collect(),
+        });
+        for (b, mut tree) in boosters.iter_mut().zip(trees.drain(..)) {
+            tree.batch = batch;
+            b.trees.push(tree);
+        }
+        let n_trees = boosters[0].trees.len();
+
+        // A round with an undefined metric is never the best round.
+        if let Some(value) = eval_value.filter(|v| !v.is_nan()) {
+            if best_round.is_none_or(|(_, best)| is_comparison_better(best, value, maximize)) {
+                best_round = Some((n_trees, value));
+            }
+        }
+
+        let mut round_stop = None;
+        if let (Some(rounds), Some((best_n_trees, _))) = (early_stopping_rounds, best_round) {
+            if n_trees - best_n_trees >= rounds {
+                info!(
+                    "Early stopping since the evaluation metric did not improve for {} rounds.",
+                    rounds
+                );
+                round_stop = Some(StopReason::EarlyStopping);
+            }
+        }
+        if round_stop.is_none() && stopping >= stopping_rounds {
+            info!("Auto stopping since stopping round limit reached.");
+            round_stop = Some(StopReason::AutoStopping);
+        }
+        if let (None, Some(t)) = (round_stop, timeout) {
+            if start.elapsed().as_secs_f32() > t {
+                warn!("Reached timeout limit before auto stopping. Try to decrease the budget or increase the timeout for the best performance.");
+                round_stop = Some(StopReason::Timeout);
+            }
+        }
+
+        // The state to resume the fit from, saved by checkpoints written in the callbacks.
+        boosters[0].fit_state = Some(FitState {
+            round: i + 1,
+            stopping,
+            n_low_loss_rounds,
+            best_round,
+            stop: round_stop,
+            n_trees_start,
+            continued,
+        });
+
+        if !callbacks.is_empty() {
+            let round_trees: Vec<&Tree> = boosters.iter().map(|b| &b.trees[b.trees.len() - 1]).collect();
+            let info = RoundInfo {
+                round,
+                tree: round_trees[0],
+                trees: &round_trees,
+                stopper: &round_trees[0].stopper,
+                loss: loss_mean,
+                oob_loss,
+                elapsed: start.elapsed().as_secs_f32(),
+            };
+            let mut stop = false;
+            for c in callbacks.iter_mut() {
+                stop |= c.after_iteration(&boosters[0], &info) == CallbackAction::Stop;
+            }
+            if stop {
+                info!("Stopping since a callback requested it.");
+                stop_reason = StopReason::Callback;
+                break;
+            }
+        }
+
+        if let Some(reason) = round_stop {
+            stop_reason = reason;
+            break;
+        }
+
+        if i == iteration_limit - 1 {
+            warn!("Reached iteration limit before auto stopping. Try to decrease the budget for the best performance.");
+        }
+    }
+
+    if let (Some(_), Some((best_n_trees, _))) = (early_stopping_rounds, best_round) {
+        boosters.iter_mut().for_each(|b| b.trees.truncate(best_n_trees));
+        boosters[0].best_iteration = Some(best_n_trees);
+    }
+    boosters[0].stop_reason = Some(stop_reason);
+    boosters[0].fit_state = None;
+    boosters[0].stopping_state = StoppingState {
+        stopping,
+        n_low_loss_rounds,
+    };
+
+    if log_iterations > 0 {
+        let n_trees = boosters[0].trees.len();
+        if boosters.len() == 1 {
+            info!(
+                "Finished training a booster with {0} trees in {1} seconds.",
+                n_trees,
+                start.elapsed().as_secs()
+            );
+        } else {
+            info!(
+                "Finished training a multi-output booster with {0} trees per output in {1} seconds.",
+                n_trees,
+                start.elapsed().as_secs()
+            );
+        }
+    }
+
+    Ok(())
+}
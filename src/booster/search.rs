@@ -0,0 +1,164 @@
+use crate::booster::booster::{EvalSet, StoppingState};
+use crate::errors::PerpetualError;
+use crate::metric::{evaluate_metric, is_comparison_better, metric_callables};
+use crate::objective::default_metric;
+use crate::{Matrix, PerpetualBooster};
+
+/// Results of a budget search, created with `PerpetualBooster::search_budget`.
+pub struct BudgetSearchResult {
+    /// Budgets tried, in increasing order.
+    pub budgets: Vec<f32>,
+    /// Evaluation metric on the validation set at each budget.
+    pub metrics: Vec<f64>,
+    /// Budget with the best evaluation metric.
+    pub best_budget: f32,
+    /// Booster fit up to the best budget.
+    pub best_booster: PerpetualBooster,
+}
+
+impl PerpetualBooster {
+    /// Pick the budget from validation data. The booster is fit at each budget in increasing order,
+    /// continuing from the trees of the previous budget, and evaluated with the evaluation metric on
+    /// the validation set after each one.
+    ///
+    /// * `data` -  Either a Polars or Pandas DataFrame, or a 2 dimensional Numpy array.
+    /// * `y` - Either a Polars or Pandas Series, or a 1 dimensional Numpy array.
+    /// * `sample_weight` - Instance weights to use when training the model.
+    /// * `group` - Query id of each record, required by the ranking objectives and not used otherwise.
+    /// * `valid` - Validation set, with the same columns as `data`.
+    /// * `budgets` - Positive budgets to try, in strictly increasing order.
+    pub fn search_budget(
+        &self,
+        data: &Matrix<f64>,
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+        group: Option<&[u64]>,
+        valid: &EvalSet,
+        budgets: &[f32],
+    ) -> Result<BudgetSearchResult, PerpetualError> {
+        if budgets.is_empty() || budgets[0] <= 0.0 || budgets.windows(2).any(|b| b[1] <= b[0]) {
+            return Err(PerpetualError::InvalidParameter(
+                "budgets".to_string(),
+                "positive budgets in strictly increasing order".to_string(),
+                format!("{:?}", budgets),
+            ));
+        }
+        if valid.y.len() != valid.data.rows || valid.group.is_some_and(|g| g.len() != valid.data.rows) {
+            return Err(PerpetualError::InvalidParameter(
+                "valid".to_string(),
+                format!("{} targets and query ids", valid.data.rows),
+                valid.y.len().to_string(),
+            ));
+        }
+
+        self.objective.validate()?;
+        let eval_metric = self.eval_metric.unwrap_or_else(|| default_metric(&self.objective));
+        let (_, maximize) = metric_callables(&eval_metric);
+        let metric_alpha = self.metric_alpha(&eval_metric);
+        let valid_weight = valid
+            .sample_weight
+            .map_or_else(|| vec![1.0; valid.y.len()], |w| w.to_vec());
+
+        let mut booster = self.clone();
+        booster.reset = Some(true);
+        // The budgets are compared on the validation set, so none of the fits stops early on it.
+        booster.early_stopping_rounds = None;
+        let mut metrics = Vec::with_capacity(budgets.len());
+        let mut best: Option<(usize, PerpetualBooster)> = None;
+        for (i, budget) in budgets.iter().enumerate() {
+            booster.budget = *budget;
+            match group.filter(|_| self.objective.is_ranking()) {
+                Some(group) => booster.fit_ranking(data, y, group, sample_weight)?,
+                None => booster.fit(data, y, sample_weight)?,
+            }
+            // The next budgets add trees to the ones fit so far, each with its own stopping rounds.
+            booster.reset = Some(false);
+            booster.stopping_state = StoppingState::default();
+
+            let preds = booster.predict(valid.data, true);
+            let value = evaluate_metric(
+                &eval_metric,
+                valid.y,
+                &preds,
+                &valid_weight,
+                metric_alpha,
+                valid.group,
+                valid.event,
+            );
+            metrics.push(value);
+            if best
+                .as_ref()
+                .is_none_or(|(b, _)| is_comparison_better(metrics[*b], value, maximize))
+            {
+                best = Some((i, booster.clone()));
+            }
+        }
+
+        let (best_index, mut best_booster) = best.expect("at least one budget is tried");
+        best_booster.reset = self.reset;
+        best_booster.early_stopping_rounds = self.early_stopping_rounds;
+        Ok(BudgetSearchResult {
+            budgets: budgets.to_vec(),
+            metrics,
+            best_budget: budgets[best_index],
+            best_booster,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::booster::test_data::{noisy_sine, synthetic_columns};
+    use crate::metric::{root_mean_squared_error, Metric};
+    use crate::objective::Objective;
+
+    #[test]
+    fn test_search_budget() {
+        let n_rows = 1500;
+        let (n_train, n_valid) = (1000, 500);
+        let data_vec = synthetic_columns(n_rows, 1);
+        let y = noisy_sine(&data_vec);
+        let train = Matrix::new(&data_vec[..n_train], n_train, 1);
+        let valid_data = Matrix::new(&data_vec[n_train..], n_valid, 1);
+        let valid = EvalSet {
+            name: "valid",
+            data: &valid_data,
+            y: &y[n_train..],
+            sample_weight: None,
+            group: None,
+            event: None,
+        };
+
+        let booster = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_eval_metric(Some(Metric::RootMeanSquaredError));
+        let budgets = [0.3, 0.6, 1.0];
+        let result = booster
+            .search_budget(&train, &y[..n_train], None, None, &valid, &budgets)
+            .unwrap();
+
+        assert_eq!(result.metrics.len(), budgets.len());
+        let best = result.metrics.iter().copied().fold(f64::INFINITY, f64::min);
+        assert!(budgets.contains(&result.best_budget));
+        assert_eq!(result.best_booster.budget, result.best_budget);
+        let preds = result.best_booster.predict(&valid_data, true);
+        let w = vec![1.0; n_valid];
+        assert_eq!(root_mean_squared_error(&y[n_train..], &preds, &w), best);
+
+        assert!(booster
+            .search_budget(&train, &y[..n_train], None, None, &valid, &[1.0, 0.5])
+            .is_err());
+
+        // Early stopping is left to the budget search, and kept on the best booster.
+        let booster = booster.set_early_stopping_rounds(Some(3));
+        let result = booster
+            .search_budget(&train, &y[..n_train], None, None, &valid, &budgets)
+            .unwrap();
+        assert_eq!(result.best_booster.early_stopping_rounds, Some(3));
+        let booster = booster.set_objective(Objective::TweedieLoss { variance_power: 3.0 });
+        assert!(booster
+            .search_budget(&train, &y[..n_train], None, None, &valid, &budgets)
+            .is_err());
+    }
+}
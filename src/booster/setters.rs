@@ -1,6 +1,13 @@
 use super::booster::MissingNodeTreatment;
-use crate::{constraints::ConstraintMap, objective::Objective, PerpetualBooster};
-use std::collections::HashSet;
+use crate::{
+    constraints::{Constraint, ConstraintMap, InteractionConstraints},
+    errors::PerpetualError,
+    metric::Metric,
+    objective::Objective,
+    sampler::SampleMethod,
+    PerpetualBooster,
+};
+use std::collections::{HashMap, HashSet};
 
 impl PerpetualBooster {
     // Set methods for paramters
@@ -50,6 +57,13 @@ impl PerpetualBooster {
         self
     }
 
+    /// Set the interaction constraints on the booster.
+    /// * `interaction_constraints` - groups of features allowed to interact, that is to be split on along the same path of a tree.
+    pub fn set_interaction_constraints(mut self, interaction_constraints: Option<InteractionConstraints>) -> Self {
+        self.interaction_constraints = interaction_constraints;
+        self
+    }
+
     /// Set the force_children_to_bound_parent on the booster.
     /// * `force_children_to_bound_parent` - Set force children to bound parent.
     pub fn set_force_children_to_bound_parent(mut self, force_children_to_bound_parent: bool) -> Self {
@@ -124,6 +138,13 @@ impl PerpetualBooster {
         self
     }
 
+    /// Set the weight of the positive class on the booster.
+    /// * `scale_pos_weight` - used only in binary classification, with LogLoss or FocalLoss.
+    pub fn set_scale_pos_weight(mut self, scale_pos_weight: Option<f64>) -> Self {
+        self.scale_pos_weight = scale_pos_weight;
+        self
+    }
+
     /// Set the reset on the booster.
     /// * `reset` - Reset the model or continue training.
     pub fn set_reset(mut self, reset: Option<bool>) -> Self {
@@ -138,6 +159,52 @@ impl PerpetualBooster {
         self
     }
 
+    /// Set the feature names on the booster.
+    /// * `feature_names` - name of each feature, taken from the data the booster is fit on if `None`.
+    pub fn set_feature_names(mut self, feature_names: Option<Vec<String>>) -> Self {
+        self.feature_names = feature_names;
+        self
+    }
+
+    /// Set the monotone_constraints on the booster, by feature name. The feature names must be set first.
+    /// * `monotone_constraints` - The monotone constraints of the booster, keyed by feature name.
+    pub fn set_monotone_constraints_by_name(
+        self,
+        monotone_constraints: HashMap<String, Constraint>,
+    ) -> Result<Self, PerpetualError> {
+        let monotone_constraints = monotone_constraints
+            .into_iter()
+            .map(|(name, c)| Ok((self.feature_index(&name)?, c)))
+            .collect::<Result<ConstraintMap, PerpetualError>>()?;
+        Ok(self.set_monotone_constraints(Some(monotone_constraints)))
+    }
+
+    /// Set the interaction constraints on the booster, by feature name. The feature names must be set first.
+    /// * `interaction_constraints` - groups of feature names allowed to interact.
+    pub fn set_interaction_constraints_by_name(
+        self,
+        interaction_constraints: Vec<HashSet<String>>,
+    ) -> Result<Self, PerpetualError> {
+        let interaction_constraints = interaction_constraints
+            .into_iter()
+            .map(|group| group.iter().map(|name| self.feature_index(name)).collect())
+            .collect::<Result<InteractionConstraints, PerpetualError>>()?;
+        Ok(self.set_interaction_constraints(Some(interaction_constraints)))
+    }
+
+    /// Set the categorical features on the booster, by feature name. The feature names must be set first.
+    /// * `categorical_features` - names of the categorical features.
+    pub fn set_categorical_features_by_name(
+        self,
+        categorical_features: HashSet<String>,
+    ) -> Result<Self, PerpetualError> {
+        let categorical_features = categorical_features
+            .iter()
+            .map(|name| self.feature_index(name))
+            .collect::<Result<HashSet<usize>, PerpetualError>>()?;
+        Ok(self.set_categorical_features(Some(categorical_features)))
+    }
+
     /// Set the timeout on the booster.
     /// * `timeout` - fit timeout limit in seconds.
     pub fn set_timeout(mut self, timeout: Option<f32>) -> Self {
@@ -165,4 +232,74 @@ impl PerpetualBooster {
         self.stopping_rounds = stopping_rounds;
         self
     }
+
+    /// Set the evaluation metric on the booster.
+    /// * `eval_metric` - metric computed on the evaluation sets, the default metric of the objective if None.
+    pub fn set_eval_metric(mut self, eval_metric: Option<Metric>) -> Self {
+        self.eval_metric = eval_metric;
+        self
+    }
+
+    /// Set the early stopping rounds on the booster.
+    /// * `early_stopping_rounds` - optional number of rounds without improvement of the evaluation metric before stopping.
+    pub fn set_early_stopping_rounds(mut self, early_stopping_rounds: Option<usize>) -> Self {
+        self.early_stopping_rounds = early_stopping_rounds;
+        self
+    }
+
+    /// Set the sample method on the booster.
+    /// * `sample_method` - method to sample the rows each tree is fit on.
+    pub fn set_sample_method(mut self, sample_method: SampleMethod) -> Self {
+        self.sample_method = sample_method;
+        self
+    }
+
+    /// Set the subsample on the booster.
+    /// * `subsample` - fraction of the rows sampled for each tree, used with the `Random` sample method.
+    pub fn set_subsample(mut self, subsample: f32) -> Self {
+        self.subsample = subsample;
+        self
+    }
+
+    /// Set the top rate on the booster.
+    /// * `top_rate` - fraction of the rows with the largest gradients kept for each tree, used with the `Goss` sample method.
+    pub fn set_top_rate(mut self, top_rate: f32) -> Self {
+        self.top_rate = top_rate;
+        self
+    }
+
+    /// Set the other rate on the booster.
+    /// * `other_rate` - fraction of the rows sampled from the rest, used with the `Goss` sample method.
+    pub fn set_other_rate(mut self, other_rate: f32) -> Self {
+        self.other_rate = other_rate;
+        self
+    }
+
+    /// Set the column sample rate of the trees on the booster.
+    /// * `colsample_bytree` - fraction of the columns sampled for each tree, chosen from the budget and the shape of the data if `None`.
+    pub fn set_colsample_bytree(mut self, colsample_bytree: Option<f32>) -> Self {
+        self.colsample_bytree = colsample_bytree;
+        self
+    }
+
+    /// Set the column sample rate of the nodes on the booster.
+    /// * `colsample_bynode` - fraction of the columns of the tree sampled for each node, all of them if `None`.
+    pub fn set_colsample_bynode(mut self, colsample_bynode: Option<f32>) -> Self {
+        self.colsample_bynode = colsample_bynode;
+        self
+    }
+
+    /// Set the feature weights on the booster.
+    /// * `feature_weights` - sampling weight of every feature, used when the columns are sampled.
+    pub fn set_feature_weights(mut self, feature_weights: Option<Vec<f64>>) -> Self {
+        self.feature_weights = feature_weights;
+        self
+    }
+
+    /// Set the always included features on the booster.
+    /// * `always_include_features` - features part of every column sample.
+    pub fn set_always_include_features(mut self, always_include_features: Option<HashSet<usize>>) -> Self {
+        self.always_include_features = always_include_features;
+        self
+    }
 }
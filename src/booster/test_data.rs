@@ -0,0 +1,24 @@
+//! Synthetic data shared by the booster tests.
+
+/// Column major features in [0, 1), each column a different permutation of the same grid.
+pub(crate) fn synthetic_columns(n_rows: usize, n_cols: usize) -> Vec<f64> {
+    (0..n_rows * n_cols)
+        .map(|i| ((i * 37 + i / n_rows * 11) % 1009) as f64 / 1009.0)
+        .collect()
+}
+
+/// Column major features: a column in [0, 1) and a column of 13 integer levels.
+pub(crate) fn synthetic_features(n_rows: usize) -> Vec<f64> {
+    synthetic_columns(n_rows, 1)
+        .into_iter()
+        .chain((0..n_rows).map(|i| ((i * 7) % 13) as f64))
+        .collect()
+}
+
+/// A smooth signal of `x` plus deterministic noise, so that the trees eventually overfit the noise.
+pub(crate) fn noisy_sine(x: &[f64]) -> Vec<f64> {
+    x.iter()
+        .enumerate()
+        .map(|(i, v)| (v * 6.0).sin() + (((i * 7919) % 101) as f64 / 101.0 - 0.5))
+        .collect()
+}
@@ -1,10 +1,13 @@
-use crate::{errors::PerpetualError, objective::Objective, utils::percentiles, Matrix, PerpetualBooster};
+use crate::{
+    errors::PerpetualError, objective::Objective, utils::percentiles, Matrix, MultiOutputBooster, PerpetualBooster,
+};
 use std::collections::HashMap;
 
 pub type CalData<'a> = (Matrix<'a, f64>, &'a [f64], &'a [f64]); // (x_flat_data, rows, cols), y, alpha
 
 impl PerpetualBooster {
-    /// Calibrate models to get prediction intervals
+    /// Calibrate models to get prediction intervals.
+    /// The quantiles of all the alphas are fit at once, with a single multi-quantile booster.
     /// * `alpha` - Alpha list to train calibration models for
     pub fn calibrate(
         &mut self,
@@ -14,50 +17,65 @@ impl PerpetualBooster {
         data_cal: CalData,
     ) -> Result<(), PerpetualError> {
         let (x_cal, y_cal, alpha) = data_cal;
+        if let Some(a) = alpha.iter().find(|a| !(**a > 0.0 && **a < 1.0)) {
+            return Err(PerpetualError::InvalidParameter(
+                "alpha".to_string(),
+                "values between 0 and 1".to_string(),
+                a.to_string(),
+            ));
+        }
+
+        let mut quantiles: Vec<f64> = alpha.iter().flat_map(|a| [a / 2.0, 1.0 - a / 2.0]).collect();
+        quantiles.sort_by(|a, b| a.total_cmp(b));
+        quantiles.dedup();
+
+        let mut model = MultiOutputBooster::default()
+            .set_objective(Objective::QuantileLoss)
+            .set_quantiles(Some(quantiles.clone()));
+        model.fit_quantiles(data, y, sample_weight)?;
 
+        let y_cal_preds = model.predict(&x_cal, true);
+        let rows = x_cal.rows;
         for alpha_ in alpha {
-            let lower_quantile = Some(alpha_ / 2.0);
-            let mut model_lower = PerpetualBooster::default()
-                .set_objective(Objective::QuantileLoss)
-                .set_quantile(lower_quantile);
-            model_lower.fit(&data, &y, sample_weight)?;
-
-            let upper_quantile = Some(1.0 - alpha_ / 2.0);
-            let mut model_upper = PerpetualBooster::default()
-                .set_objective(Objective::QuantileLoss)
-                .set_quantile(upper_quantile);
-            model_upper.fit(&data, &y, sample_weight)?;
-
-            let y_cal_pred_lower = model_lower.predict(&x_cal, true);
-            let y_cal_pred_upper = model_upper.predict(&x_cal, true);
+            let quantile_index = |q: f64| {
+                quantiles.iter().position(|q_| *q_ == q).ok_or_else(|| {
+                    PerpetualError::InvalidParameter(
+                        "alpha".to_string(),
+                        format!("a quantile among {:?}", quantiles),
+                        q.to_string(),
+                    )
+                })
+            };
+            let lower = quantile_index(alpha_ / 2.0)?;
+            let upper = quantile_index(1.0 - alpha_ / 2.0)?;
+
+            let y_cal_pred_lower = &y_cal_preds[lower * rows..(lower + 1) * rows];
+            let y_cal_pred_upper = &y_cal_preds[upper * rows..(upper + 1) * rows];
             let mut scores: Vec<f64> = Vec::with_capacity(y_cal.len());
             for i in 0..y_cal.len() {
                 scores.push(f64::max(y_cal_pred_lower[i] - y_cal[i], y_cal[i] - y_cal_pred_upper[i]));
             }
             let perc = (1.0 - (*alpha_ as f64)) * (1.0 + 1.0 * (1.0 / (scores.len() as f64)));
             let score = percentiles(&scores, &vec![1.0; scores.len()], &vec![perc])[0];
-            self.cal_models
-                .insert(alpha_.to_string(), [(model_lower, -score), (model_upper, score)]);
+            self.cal_scores
+                .insert(alpha_.to_string(), [(lower, -score), (upper, score)]);
         }
+        self.cal_model = Some(Box::new(model));
         Ok(())
     }
 
+    /// Predict the intervals of every calibrated alpha. The quantiles are predicted at once by the
+    /// calibration model, so the lower and upper bounds of the intervals do not cross.
     pub fn predict_intervals(&self, data: &Matrix<f64>, parallel: bool) -> HashMap<String, Vec<Vec<f64>>> {
         let mut intervals = HashMap::new();
-        for (alpha, value) in &self.cal_models {
-            let (model_lower, score_lower) = &value[0];
-            let (model_upper, score_upper) = &value[1];
-            let lower_preds = model_lower
-                .predict(data, parallel)
-                .iter()
-                .map(|p| p + score_lower)
-                .collect();
-            let upper_preds = model_upper
-                .predict(data, parallel)
-                .iter()
-                .map(|p| p + score_upper)
-                .collect();
-            intervals.insert(alpha.to_string(), vec![lower_preds, upper_preds]);
+        let Some(model) = &self.cal_model else {
+            return intervals;
+        };
+        let preds = model.predict(data, parallel);
+        let rows = data.rows;
+        for (alpha, value) in &self.cal_scores {
+            let bound = |(q, score): &(usize, f64)| preds[q * rows..(q + 1) * rows].iter().map(|p| p + score).collect();
+            intervals.insert(alpha.to_string(), vec![bound(&value[0]), bound(&value[1])]);
         }
         intervals
     }
@@ -66,6 +84,7 @@ impl PerpetualBooster {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::booster::test_data::{noisy_sine, synthetic_features};
     use crate::objective::Objective;
     use polars::io::SerReader;
     use polars::prelude::{CsvReadOptions, DataType};
@@ -171,8 +190,67 @@ mod tests {
         model.calibrate(&matrix_train, &y_train, None, data_cal)?;
 
         let matrix_test = Matrix::new(&data_test, y_test.len(), 8);
-        let _intervals = model.predict_intervals(&matrix_test, true);
+        let intervals = model.predict_intervals(&matrix_test, true);
+
+        // The intervals do not cross, and cover the test records at the requested level.
+        let interval = &intervals["0.1"];
+        let mut covered = 0;
+        for (i, y_) in y_test.iter().enumerate() {
+            assert!(interval[0][i] <= interval[1][i]);
+            if interval[0][i] <= *y_ && *y_ <= interval[1][i] {
+                covered += 1;
+            }
+        }
+        assert!(covered as f64 / y_test.len() as f64 >= 0.89);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cqr_saved_intervals() -> Result<(), Box<dyn Error>> {
+        let n_rows = 400;
+        let data_vec = synthetic_features(n_rows);
+        let y = noisy_sine(&data_vec[..n_rows]);
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let mut model = PerpetualBooster::default()
+            .set_objective(Objective::SquaredLoss)
+            .set_budget(0.3);
+        model.fit(&data, &y, None)?;
 
+        let alpha = vec![0.1, 0.5];
+        let data_cal = (Matrix::new(&data_vec, n_rows, 2), y.as_slice(), alpha.as_slice());
+        model.calibrate(&data, &y, None, data_cal)?;
+        let intervals = model.predict_intervals(&data, true);
+
+        // The bounds are the sorted quantiles of the calibration model, shifted by the conformity scores.
+        let quantiles = model.cal_model.as_ref().unwrap().predict(&data, true);
+        for (alpha, [(lower, score_lower), (upper, score_upper)]) in &model.cal_scores {
+            assert!(lower < upper);
+            for i in 0..n_rows {
+                assert!(quantiles[lower * n_rows + i] <= quantiles[upper * n_rows + i]);
+                assert_eq!(intervals[alpha][0][i], quantiles[lower * n_rows + i] + score_lower);
+                assert_eq!(intervals[alpha][1][i], quantiles[upper * n_rows + i] + score_upper);
+            }
+        }
+
+        let loaded = PerpetualBooster::from_json(&model.json_dump()?)?;
+        assert_eq!(loaded.predict_intervals(&data, true), intervals);
         Ok(())
     }
+
+    #[test]
+    fn test_cqr_invalid_alpha() {
+        let n_rows = 200;
+        let data_vec = synthetic_features(n_rows);
+        let y = noisy_sine(&data_vec[..n_rows]);
+        let data = Matrix::new(&data_vec, n_rows, 2);
+        let mut model = PerpetualBooster::default().set_objective(Objective::SquaredLoss);
+        for alpha in [vec![0.0], vec![0.1, 1.5], vec![f64::NAN]] {
+            let data_cal = (Matrix::new(&data_vec, n_rows, 2), y.as_slice(), alpha.as_slice());
+            assert!(matches!(
+                model.calibrate(&data, &y, None, data_cal),
+                Err(PerpetualError::InvalidParameter(..))
+            ));
+        }
+    }
 }
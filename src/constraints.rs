@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
 pub enum Constraint {
@@ -9,3 +9,40 @@ pub enum Constraint {
 }
 
 pub type ConstraintMap = HashMap<usize, Constraint>;
+
+/// Groups of features allowed to interact, that is to be split on along the same path of a tree.
+pub type InteractionConstraints = Vec<HashSet<usize>>;
+
+/// Whether a node, with the features in `path_features` split on along its path, may be split on `feature`.
+/// This needs a group with all of them, a feature outside of every group only interacts with itself.
+pub fn interaction_allowed(
+    interaction_constraints: &InteractionConstraints,
+    path_features: &HashSet<usize>,
+    feature: usize,
+) -> bool {
+    path_features.iter().all(|f| *f == feature)
+        || interaction_constraints
+            .iter()
+            .any(|group| group.contains(&feature) && path_features.is_subset(group))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interaction_allowed() {
+        let interaction_constraints = vec![HashSet::from([0, 1]), HashSet::from([1, 2])];
+        assert!(interaction_allowed(&interaction_constraints, &HashSet::new(), 3));
+        assert!(interaction_allowed(&interaction_constraints, &HashSet::from([0]), 1));
+        assert!(interaction_allowed(&interaction_constraints, &HashSet::from([1]), 2));
+        assert!(!interaction_allowed(&interaction_constraints, &HashSet::from([0]), 2));
+        assert!(!interaction_allowed(
+            &interaction_constraints,
+            &HashSet::from([0, 1]),
+            2
+        ));
+        assert!(interaction_allowed(&interaction_constraints, &HashSet::from([3]), 3));
+        assert!(!interaction_allowed(&interaction_constraints, &HashSet::from([3]), 0));
+    }
+}
@@ -1,4 +1,8 @@
+use crate::binning::{bin_matrix, bin_matrix_with_cuts, bin_sparse_matrix, bin_sparse_matrix_with_cuts, BinnedData};
+use crate::errors::PerpetualError;
+use crate::utils::missing_as;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fmt::{self, Debug, Display};
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
@@ -31,6 +35,8 @@ pub trait FloatData<T>:
     const INFINITY: T;
     fn from_usize(v: usize) -> T;
     fn from_u16(v: u16) -> T;
+    fn from_f64(v: f64) -> T;
+    fn to_f64(self) -> f64;
     fn is_nan(self) -> bool;
     fn ln(self) -> T;
     fn exp(self) -> T;
@@ -49,6 +55,12 @@ impl FloatData<f64> for f64 {
     fn from_u16(v: u16) -> f64 {
         f64::from(v)
     }
+    fn from_f64(v: f64) -> f64 {
+        v
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
     fn is_nan(self) -> bool {
         self.is_nan()
     }
@@ -74,6 +86,12 @@ impl FloatData<f32> for f32 {
     fn from_u16(v: u16) -> f32 {
         f32::from(v)
     }
+    fn from_f64(v: f64) -> f32 {
+        v as f32
+    }
+    fn to_f64(self) -> f64 {
+        f64::from(self)
+    }
     fn is_nan(self) -> bool {
         self.is_nan()
     }
@@ -85,14 +103,16 @@ impl FloatData<f32> for f32 {
     }
 }
 
-/// Contigious Column major matrix data container. This is
-/// used throughout the crate, to house both the user provided data
-/// as well as the binned data.
+/// Matrix data container, over a column major, row major, or strided
+/// view of the data. This is used throughout the crate, to house both the
+/// user provided data as well as the binned data, which is column major.
 pub struct Matrix<'a, T> {
     pub data: &'a [T],
     pub index: Vec<usize>,
     pub rows: usize,
     pub cols: usize,
+    /// Optional names of the columns, checked against the feature names of the booster.
+    pub feature_names: Option<&'a [String]>,
     stride1: usize,
     stride2: usize,
 }
@@ -105,11 +125,62 @@ impl<'a, T> Matrix<'a, T> {
             index: (0..rows).collect(),
             rows,
             cols,
+            feature_names: None,
             stride1: rows,
             stride2: 1,
         }
     }
 
+    /// Matrix over row major data, where the items of a row are contiguous.
+    pub fn new_row_major(data: &'a [T], rows: usize, cols: usize) -> Self {
+        Matrix::new_strided(data, rows, cols, cols, 1)
+    }
+
+    /// Matrix over a strided view of the data, the item in the ith row and jth column
+    /// is at `i * row_stride + j * col_stride`. This can be a block of rows or columns
+    /// of a larger buffer, or padded data.
+    ///
+    /// * `row_stride` - Distance between the items of two consecutive rows in a column.
+    /// * `col_stride` - Distance between the items of two consecutive columns in a row.
+    pub fn new_strided(data: &'a [T], rows: usize, cols: usize, row_stride: usize, col_stride: usize) -> Self {
+        assert!(
+            rows == 0 || cols == 0 || (rows - 1) * row_stride + (cols - 1) * col_stride < data.len(),
+            "The strides reach past the end of the data."
+        );
+        Matrix {
+            data,
+            index: (0..rows).collect(),
+            rows,
+            cols,
+            feature_names: None,
+            stride1: col_stride,
+            stride2: row_stride,
+        }
+    }
+
+    /// Name the columns of the matrix.
+    ///
+    /// * `feature_names` - The name of each column.
+    pub fn with_feature_names(mut self, feature_names: &'a [String]) -> Self {
+        assert_eq!(
+            feature_names.len(),
+            self.cols,
+            "There must be one feature name per column."
+        );
+        self.feature_names = Some(feature_names);
+        self
+    }
+
+    /// Whether the items of each column are contiguous, as in column major data.
+    pub fn is_col_contiguous(&self) -> bool {
+        self.stride2 == 1
+    }
+
+    /// Whether the items of each row are contiguous, as in row major data.
+    pub fn is_row_contiguous(&self) -> bool {
+        self.stride1 == 1
+    }
+
     /// Get a single reference to an item in the matrix.
     ///
     /// * `i` - The ith row of the data to get.
@@ -125,16 +196,29 @@ impl<'a, T> Matrix<'a, T> {
     }
 
     /// Get access to a row of the data, as an iterator.
-    pub fn get_row_iter(&self, row: usize) -> std::iter::StepBy<std::iter::Skip<std::slice::Iter<T>>> {
-        self.data.iter().skip(row).step_by(self.rows)
+    pub fn get_row_iter(&self, row: usize) -> std::iter::Take<std::iter::StepBy<std::slice::Iter<'_, T>>> {
+        self.data[self.item_index(row, 0)..]
+            .iter()
+            .step_by(self.stride1.max(1))
+            .take(self.cols)
     }
 
-    /// Get a slice of a column in the matrix.
+    /// Get access to a column of the data, as an iterator, for any layout of the data.
+    pub fn get_col_iter(&self, col: usize) -> std::iter::Take<std::iter::StepBy<std::slice::Iter<'_, T>>> {
+        self.data[self.item_index(0, col)..]
+            .iter()
+            .step_by(self.stride2.max(1))
+            .take(self.rows)
+    }
+
+    /// Get a slice of a column in the matrix, the items of the columns must be contiguous,
+    /// see `get_col_iter` otherwise.
     ///
     /// * `col` - The index of the column to select.
     /// * `start_row` - The index of the start of the slice.
     /// * `end_row` - The index of the end of the slice of the column to select.
     pub fn get_col_slice(&self, col: usize, start_row: usize, end_row: usize) -> &[T] {
+        assert!(self.is_col_contiguous(), "The items of the columns are not contiguous.");
         let i = self.item_index(start_row, col);
         let j = self.item_index(end_row, col);
         &self.data[i..j]
@@ -154,7 +238,305 @@ where
 {
     /// Get a row of the data as a vector.
     pub fn get_row(&self, row: usize) -> Vec<T> {
-        self.get_row_iter(row).copied().collect()
+        if self.is_row_contiguous() {
+            let start = self.item_index(row, 0);
+            self.data[start..start + self.cols].to_vec()
+        } else {
+            self.get_row_iter(row).copied().collect()
+        }
+    }
+
+    /// Copy the data to a column major vector, for use with `Matrix::new`.
+    pub fn to_col_major(&self) -> Vec<T> {
+        if self.is_col_contiguous() {
+            (0..self.cols)
+                .flat_map(|col| self.get_col(col).iter().copied())
+                .collect()
+        } else {
+            (0..self.cols).flat_map(|col| self.get_col_iter(col).copied()).collect()
+        }
+    }
+}
+
+/// Data the booster is fit on and predicts, either a dense `Matrix` or a `SparseMatrix`,
+/// of f64 or f32 values.
+pub trait DataMatrix: Sync {
+    /// Number of rows of the data.
+    fn rows(&self) -> usize;
+
+    /// Number of columns of the data.
+    fn cols(&self) -> usize;
+
+    /// Index of the rows of the data.
+    fn index(&self) -> &[usize];
+
+    /// Names of the columns of the data, if they are known.
+    fn feature_names(&self) -> Option<&[String]>;
+
+    /// Get an item of the matrix as an f64 value.
+    ///
+    /// * `i` - The ith row of the data to get.
+    /// * `j` - the jth column of the data to get.
+    fn get_f64(&self, i: usize, j: usize) -> f64;
+
+    /// Get a row of the data as a vector of f64 values.
+    fn get_row_f64(&self, row: usize) -> Vec<f64>;
+
+    /// The missing value, rounded to the type of the data.
+    fn missing_as(&self, missing: &f64) -> f64;
+
+    /// Bin the data, see `bin_matrix`.
+    fn bin(
+        &self,
+        sample_weight: Option<&[f64]>,
+        nbins: u16,
+        missing: f64,
+        cat_index: Option<&HashSet<usize>>,
+    ) -> Result<BinnedData<f64>, PerpetualError>;
+
+    /// Bin the data with the cuts of a previous call to `bin`, see `bin_matrix_with_cuts`.
+    fn bin_with_cuts(&self, cuts: &JaggedMatrix<f64>, missing: f64) -> BinnedData<f64>;
+}
+
+impl<T: FloatData<T>> DataMatrix for Matrix<'_, T> {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn index(&self) -> &[usize] {
+        &self.index
+    }
+
+    fn feature_names(&self) -> Option<&[String]> {
+        self.feature_names
+    }
+
+    fn get_f64(&self, i: usize, j: usize) -> f64 {
+        self.get(i, j).to_f64()
+    }
+
+    fn get_row_f64(&self, row: usize) -> Vec<f64> {
+        self.get_row_iter(row).map(|v| v.to_f64()).collect()
+    }
+
+    fn missing_as(&self, missing: &f64) -> f64 {
+        missing_as::<T>(missing)
+    }
+
+    fn bin(
+        &self,
+        sample_weight: Option<&[f64]>,
+        nbins: u16,
+        missing: f64,
+        cat_index: Option<&HashSet<usize>>,
+    ) -> Result<BinnedData<f64>, PerpetualError> {
+        bin_matrix(self, sample_weight, nbins, missing, cat_index)
+    }
+
+    fn bin_with_cuts(&self, cuts: &JaggedMatrix<f64>, missing: f64) -> BinnedData<f64> {
+        bin_matrix_with_cuts(self, cuts, missing)
+    }
+}
+
+/// Layout of the items of a sparse matrix.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SparseFormat {
+    /// Compressed sparse row, the stored items of each row are contiguous.
+    Csr,
+    /// Compressed sparse column, the stored items of each column are contiguous.
+    Csc,
+}
+
+/// Sparse matrix data container, over compressed sparse row or column data, as in
+/// `scipy.sparse`. The items that are not stored are zero, these are binned together,
+/// and never materialized.
+pub struct SparseMatrix<'a, T> {
+    /// The stored items.
+    pub data: &'a [T],
+    /// The column of each stored item for CSR data, or its row for CSC data,
+    /// increasing within each row or column.
+    pub indices: &'a [usize],
+    /// Where the stored items of each row for CSR data, or each column for CSC data,
+    /// start in `data`, followed by the number of stored items.
+    pub indptr: &'a [usize],
+    pub index: Vec<usize>,
+    pub rows: usize,
+    pub cols: usize,
+    pub format: SparseFormat,
+    /// Optional names of the columns, checked against the feature names of the booster.
+    pub feature_names: Option<&'a [String]>,
+}
+
+impl<'a, T> SparseMatrix<'a, T> {
+    /// Sparse matrix over compressed sparse row data.
+    pub fn new_csr(data: &'a [T], indices: &'a [usize], indptr: &'a [usize], rows: usize, cols: usize) -> Self {
+        SparseMatrix::new_compressed(data, indices, indptr, rows, cols, SparseFormat::Csr)
+    }
+
+    /// Sparse matrix over compressed sparse column data.
+    pub fn new_csc(data: &'a [T], indices: &'a [usize], indptr: &'a [usize], rows: usize, cols: usize) -> Self {
+        SparseMatrix::new_compressed(data, indices, indptr, rows, cols, SparseFormat::Csc)
+    }
+
+    fn new_compressed(
+        data: &'a [T],
+        indices: &'a [usize],
+        indptr: &'a [usize],
+        rows: usize,
+        cols: usize,
+        format: SparseFormat,
+    ) -> Self {
+        let (n_major, n_minor) = match format {
+            SparseFormat::Csr => (rows, cols),
+            SparseFormat::Csc => (cols, rows),
+        };
+        assert!(
+            indptr.len() == n_major + 1
+                && indptr[0] == 0
+                && indptr.windows(2).all(|w| w[0] <= w[1])
+                && data.len() == indptr[n_major]
+                && indices.len() == data.len(),
+            "The index pointers do not match the stored items."
+        );
+        assert!(
+            indptr
+                .windows(2)
+                .all(|w| indices[w[0]..w[1]].windows(2).all(|i| i[0] < i[1])
+                    && indices[w[0]..w[1]].last().is_none_or(|i| *i < n_minor)),
+            "The indices are not increasing, or out of bounds."
+        );
+        SparseMatrix {
+            data,
+            indices,
+            indptr,
+            index: (0..rows).collect(),
+            rows,
+            cols,
+            format,
+            feature_names: None,
+        }
+    }
+
+    /// Name the columns of the matrix.
+    ///
+    /// * `feature_names` - The name of each column.
+    pub fn with_feature_names(mut self, feature_names: &'a [String]) -> Self {
+        assert_eq!(
+            feature_names.len(),
+            self.cols,
+            "There must be one feature name per column."
+        );
+        self.feature_names = Some(feature_names);
+        self
+    }
+
+    /// Number of stored items.
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<T> SparseMatrix<'_, T>
+where
+    T: FloatData<T>,
+{
+    /// Get an item of the matrix, zero if it is not stored.
+    ///
+    /// * `i` - The ith row of the data to get.
+    /// * `j` - the jth column of the data to get.
+    pub fn get(&self, i: usize, j: usize) -> T {
+        let (major, minor) = match self.format {
+            SparseFormat::Csr => (i, j),
+            SparseFormat::Csc => (j, i),
+        };
+        let (start, end) = (self.indptr[major], self.indptr[major + 1]);
+        match self.indices[start..end].binary_search(&minor) {
+            Ok(k) => self.data[start + k],
+            Err(_) => T::ZERO,
+        }
+    }
+
+    /// The stored items of each column, as pairs of row and value, in row order.
+    pub fn col_items(&self) -> Vec<Vec<(usize, T)>> {
+        let mut items = vec![Vec::new(); self.cols];
+        match self.format {
+            SparseFormat::Csc => {
+                for (col, col_items) in items.iter_mut().enumerate() {
+                    let (start, end) = (self.indptr[col], self.indptr[col + 1]);
+                    col_items.extend(
+                        self.indices[start..end]
+                            .iter()
+                            .copied()
+                            .zip(self.data[start..end].iter().copied()),
+                    );
+                }
+            }
+            SparseFormat::Csr => {
+                for row in 0..self.rows {
+                    for k in self.indptr[row]..self.indptr[row + 1] {
+                        items[self.indices[k]].push((row, self.data[k]));
+                    }
+                }
+            }
+        }
+        items
+    }
+}
+
+impl<T: FloatData<T>> DataMatrix for SparseMatrix<'_, T> {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn index(&self) -> &[usize] {
+        &self.index
+    }
+
+    fn feature_names(&self) -> Option<&[String]> {
+        self.feature_names
+    }
+
+    fn get_f64(&self, i: usize, j: usize) -> f64 {
+        self.get(i, j).to_f64()
+    }
+
+    fn get_row_f64(&self, row: usize) -> Vec<f64> {
+        match self.format {
+            SparseFormat::Csr => {
+                let mut values = vec![0.0; self.cols];
+                for k in self.indptr[row]..self.indptr[row + 1] {
+                    values[self.indices[k]] = self.data[k].to_f64();
+                }
+                values
+            }
+            SparseFormat::Csc => (0..self.cols).map(|col| self.get_f64(row, col)).collect(),
+        }
+    }
+
+    fn missing_as(&self, missing: &f64) -> f64 {
+        missing_as::<T>(missing)
+    }
+
+    fn bin(
+        &self,
+        sample_weight: Option<&[f64]>,
+        nbins: u16,
+        missing: f64,
+        cat_index: Option<&HashSet<usize>>,
+    ) -> Result<BinnedData<f64>, PerpetualError> {
+        bin_sparse_matrix(self, sample_weight, nbins, missing, cat_index)
+    }
+
+    fn bin_with_cuts(&self, cuts: &JaggedMatrix<f64>, missing: f64) -> BinnedData<f64> {
+        bin_sparse_matrix_with_cuts(self, cuts, missing)
     }
 }
 
@@ -370,6 +752,29 @@ mod tests {
         assert_eq!(m.get_row(1), vec![2, 6]);
     }
 
+    #[test]
+    fn test_matrix_row_major() {
+        let v = vec![1, 2, 3, 5, 6, 7];
+        let m = Matrix::new_row_major(&v, 2, 3);
+        assert_eq!(m.get(1, 0), &5);
+        assert_eq!(m.get(0, 2), &3);
+        assert_eq!(m.get_row(1), vec![5, 6, 7]);
+        assert_eq!(m.get_col_iter(2).copied().collect::<Vec<_>>(), vec![3, 7]);
+        assert_eq!(m.to_col_major(), vec![1, 5, 2, 6, 3, 7]);
+        assert!(m.is_row_contiguous() && !m.is_col_contiguous());
+    }
+
+    #[test]
+    fn test_matrix_strided() {
+        // The last two columns of column major data, with a padding row.
+        let v = vec![1, 2, 0, 3, 5, 0, 6, 7, 0];
+        let m = Matrix::new_strided(&v[3..], 2, 2, 1, 3);
+        assert_eq!(m.get(1, 1), &7);
+        assert_eq!(m.get_col(0), &vec![3, 5]);
+        assert_eq!(m.get_row(0), vec![3, 6]);
+        assert_eq!(m.to_col_major(), vec![3, 5, 6, 7]);
+    }
+
     #[test]
     fn test_jaggedmatrix_get_col() {
         let vecs = vec![vec![0], vec![5, 4, 3, 2], vec![4, 5]];
@@ -378,4 +783,29 @@ mod tests {
         assert_eq!(jmatrix.get_col(0), vec![0]);
         assert_eq!(jmatrix.get_col(2), vec![4, 5]);
     }
+
+    #[test]
+    fn test_sparse_matrix() {
+        // [[1, 0, 2], [0, 0, 3], [4, 5, 0]]
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let indices = vec![0, 2, 2, 0, 1];
+        let indptr = vec![0, 2, 3, 5];
+        let csr = SparseMatrix::new_csr(&data, &indices, &indptr, 3, 3);
+        let csc_data = vec![1.0, 4.0, 5.0, 2.0, 3.0];
+        let csc = SparseMatrix::new_csc(&csc_data, &indices, &indptr, 3, 3);
+        for m in [&csr, &csc] {
+            assert_eq!(m.nnz(), 5);
+            assert_eq!(m.get(0, 2), 2.0);
+            assert_eq!(m.get(1, 0), 0.0);
+            assert_eq!(m.get_row_f64(2), vec![4.0, 5.0, 0.0]);
+        }
+        assert_eq!(csr.col_items(), csc.col_items());
+        assert_eq!(csr.col_items()[2], vec![(0, 2.0), (1, 3.0)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sparse_matrix_unsorted_indices() {
+        SparseMatrix::new_csr(&[1.0, 2.0], &[1, 0], &[0, 2], 1, 2);
+    }
 }
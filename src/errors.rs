@@ -15,4 +15,6 @@ pub enum PerpetualError {
     /// First value is the name of the parameter, second is expected, third is what was passed.
     #[error("Invalid parameter value passed for {0}, expected {1} but {2} provided.")]
     InvalidParameter(String, String, String),
+    #[error("Custom objective {0} is not registered, set the objective again with `Objective::custom` after loading the model.")]
+    UnregisteredObjective(String),
 }
@@ -1,6 +1,6 @@
 use crate::bin::Bin;
+use crate::binning::{BinnedMatrix, SparseBins};
 use crate::data::{FloatData, JaggedMatrix};
-use crate::Matrix;
 use rayon::{prelude::*, ThreadPool};
 use std::cell::UnsafeCell;
 
@@ -101,6 +101,72 @@ impl<'a> FeatureHistogram<'a> {
         }
     }
 
+    /// Update the histogram from the rows of the node outside of the zero bin of a sparse
+    /// feature, the zero bin holds the rest of the node totals.
+    ///
+    /// * `items` - The (row, bin) pairs of the feature outside of its zero bin.
+    /// * `zero_bin` - The bin of zero of the feature.
+    /// * `position` - Position of each row in the index, the rows of the node are between `start` and `stop`.
+    /// * `sorted_grad` - The gradients of the rows of the node, in the order of the index.
+    /// * `totals` - The gradients, hessians and counts of all of the rows of the node.
+    ///
+    /// # Safety
+    ///
+    /// The bins of the feature must be in the histogram, and the rows must be in `position`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn update_sparse(
+        &self,
+        items: &[(usize, u16)],
+        zero_bin: u16,
+        position: &[usize],
+        start: usize,
+        stop: usize,
+        sorted_grad: &[f32],
+        sorted_hess: Option<&[f32]>,
+        totals: &Bin,
+    ) {
+        self.data.iter().for_each(|b| {
+            let bin = b.get().as_mut().unwrap();
+            bin.g_folded = [f32::ZERO; 5];
+            bin.h_folded = sorted_hess.map(|_| [f32::ZERO; 5]);
+            bin.counts = [0; 5];
+        });
+        for (i, b) in items {
+            let p = *position.get_unchecked(*i);
+            if p < start || p >= stop {
+                continue;
+            }
+            let p = p - start;
+            let bin = self.data.get_unchecked(*b as usize).get().as_mut().unwrap_unchecked();
+            let fold = i % 5;
+            bin.g_folded[fold] += *sorted_grad.get_unchecked(p);
+            if let Some(h) = sorted_hess {
+                bin.h_folded.as_mut().unwrap()[fold] += *h.get_unchecked(p);
+            }
+            bin.counts[fold] += 1;
+        }
+
+        let zero = self.data[zero_bin as usize].get().as_mut().unwrap();
+        zero.g_folded = totals.g_folded;
+        zero.h_folded = totals.h_folded;
+        zero.counts = totals.counts;
+        for (k, b) in self.data.iter().enumerate() {
+            if k == zero_bin as usize {
+                continue;
+            }
+            let bin = b.get().as_ref().unwrap();
+            for fold in 0..5 {
+                zero.g_folded[fold] -= bin.g_folded[fold];
+                zero.counts[fold] -= bin.counts[fold];
+            }
+            if let (Some(zh), Some(bh)) = (zero.h_folded.as_mut(), bin.h_folded) {
+                for fold in 0..5 {
+                    zh[fold] -= bh[fold];
+                }
+            }
+        }
+    }
+
     pub unsafe fn update_cuts(&self, cuts: &[f64]) {
         let cuts_mod = &cuts[..(cuts.len() - 1)];
         self.data.iter().enumerate().for_each(|(i, b)| {
@@ -257,7 +323,7 @@ pub fn update_histogram(
     hist: &NodeHistogram,
     start: usize,
     stop: usize,
-    data: &Matrix<u16>,
+    data: &BinnedMatrix,
     grad: &[f32],
     hess: Option<&[f32]>,
     index: &[usize],
@@ -285,6 +351,25 @@ pub fn update_histogram(
         }
     };
 
+    let data = match data {
+        BinnedMatrix::Dense(data) => data,
+        BinnedMatrix::Sparse { bins, position, .. } => {
+            update_histogram_sparse(
+                hist,
+                bins,
+                position,
+                start,
+                stop,
+                sorted_grad,
+                sorted_hess,
+                &index[start..stop],
+                col_index,
+                pool,
+            );
+            return;
+        }
+    };
+
     unsafe {
         if pool.current_num_threads() > 1 {
             pool.scope(|s| {
@@ -310,9 +395,57 @@ pub fn update_histogram(
     }
 }
 
+/// Update the histograms of sparse columns from their items outside of the zero bin,
+/// the rows of the node are located by their position in the index.
+#[allow(clippy::too_many_arguments)]
+fn update_histogram_sparse(
+    hist: &NodeHistogram,
+    sparse: &SparseBins,
+    position: &[usize],
+    start: usize,
+    stop: usize,
+    sorted_grad: &[f32],
+    sorted_hess: Option<&[f32]>,
+    index: &[usize],
+    col_index: &[usize],
+    pool: &ThreadPool,
+) {
+    let mut totals = match sorted_hess {
+        Some(_) => Bin::empty(0, f64::NAN),
+        None => Bin::empty_const_hess(0, f64::NAN),
+    };
+    for (p, i) in index.iter().enumerate() {
+        let fold = i % 5;
+        totals.g_folded[fold] += sorted_grad[p];
+        if let (Some(h), Some(hess)) = (totals.h_folded.as_mut(), sorted_hess) {
+            h[fold] += hess[p];
+        }
+        totals.counts[fold] += 1;
+    }
+
+    let update = |i: usize, col: usize| unsafe {
+        hist.data.get_unchecked(i).update_sparse(
+            sparse.items.get_col(col),
+            sparse.zero_bins[col],
+            position,
+            start,
+            stop,
+            sorted_grad,
+            sorted_hess,
+            &totals,
+        )
+    };
+    if pool.current_num_threads() > 1 {
+        pool.install(|| col_index.par_iter().enumerate().for_each(|(i, col)| update(i, *col)));
+    } else {
+        col_index.iter().enumerate().for_each(|(i, col)| update(i, *col));
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::binning::bin_matrix;
+    use crate::binning::{bin_matrix, bin_sparse_matrix, BinnedMatrix};
+    use crate::data::SparseMatrix;
     use crate::histogram::{
         update_histogram, FeatureHistogram, FeatureHistogramOwned, NodeHistogram, NodeHistogramOwned,
     };
@@ -336,7 +469,7 @@ mod tests {
 
         let y_avg = y.iter().sum::<f64>() / y.len() as f64;
         let yhat = vec![y_avg; y.len()];
-        let (g, h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
+        let (g, h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
 
         let col = 0;
         let mut hist_feat_owned = FeatureHistogramOwned::empty_from_cuts(&b.cuts.get_col(col), false);
@@ -389,7 +522,7 @@ mod tests {
         let bdata = Matrix::new(&b.binned_data, data.rows, data.cols);
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap()).collect();
         let yhat = vec![0.5; y.len()];
-        let (g, h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
+        let (g, h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
 
         let col_index: Vec<usize> = (0..data.cols).collect();
         let mut hist_init_owned = NodeHistogramOwned::empty_from_cuts(&b.cuts, &col_index, true, false);
@@ -450,7 +583,7 @@ mod tests {
         let bdata = Matrix::new(&b.binned_data, data.rows, data.cols);
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap_or(f64::NAN)).collect();
         let yhat = vec![0.5; y.len()];
-        let (g, h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
+        let (g, h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
 
         let col_index: Vec<usize> = (0..data.cols).collect();
         let mut hist_init_owned = NodeHistogramOwned::empty_from_cuts(&b.cuts, &col_index, false, false);
@@ -464,7 +597,7 @@ mod tests {
             &mut hist_init,
             0,
             bdata.index.len(),
-            &bdata,
+            &BinnedMatrix::Dense(&bdata),
             &g,
             h.as_deref(),
             &bdata.index,
@@ -505,7 +638,7 @@ mod tests {
         let bdata = Matrix::new(&b.binned_data, data.rows, data.cols);
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap_or(f64::NAN)).collect();
         let yhat = vec![0.5; y.len()];
-        let (g, h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
+        let (g, h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
 
         let col_index: Vec<usize> = (0..data.cols).collect();
 
@@ -524,7 +657,7 @@ mod tests {
             &mut hist_init1,
             0,
             bdata.index.len(),
-            &bdata,
+            &BinnedMatrix::Dense(&bdata),
             &g,
             h.as_deref(),
             &bdata.index,
@@ -536,7 +669,7 @@ mod tests {
             &mut hist_init2,
             0,
             bdata.index.len(),
-            &bdata,
+            &BinnedMatrix::Dense(&bdata),
             &g,
             h.as_deref(),
             &bdata.index,
@@ -564,4 +697,70 @@ mod tests {
                 });
         });
     }
+
+    #[test]
+    fn test_histogram_sparse() {
+        let (rows, cols) = (300, 3);
+        let (mut values, mut indices, mut indptr) = (Vec::new(), Vec::new(), vec![0]);
+        for col in 0..cols {
+            for row in (col..rows).step_by(7) {
+                values.push(((row * 13) % 11) as f64 - 5.0);
+                indices.push(row);
+            }
+            indptr.push(values.len());
+        }
+        let data = SparseMatrix::new_csc(&values, &indices, &indptr, rows, cols);
+        let b = bin_sparse_matrix(&data, None, 16, f64::NAN, None).unwrap();
+        let dense_vec: Vec<f64> = (0..cols)
+            .flat_map(|col| (0..rows).map(move |row| (row, col)))
+            .map(|(row, col)| data.get(row, col))
+            .collect();
+        let dense = Matrix::new(&dense_vec, rows, cols);
+        let b_dense = bin_matrix(&dense, None, 16, f64::NAN, None).unwrap();
+        let bdata = Matrix::new(&b_dense.binned_data, rows, cols);
+        let y: Vec<f64> = (0..rows).map(|i| (i % 2) as f64).collect();
+        let yhat = vec![0.5; y.len()];
+        let (g, h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
+        let col_index: Vec<usize> = (0..cols).collect();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+
+        // A node with all but the first rows of a shuffled index, built from the items outside of the
+        // zero bins, and from every row.
+        let index: Vec<usize> = (0..rows).map(|i| (i * 7) % rows).collect();
+        let mut sparse = BinnedMatrix::sparse(b.sparse.as_ref().unwrap(), rows);
+        sparse.set_positions(&index);
+        let grad: Vec<f32> = index.iter().map(|i| g[*i]).collect();
+        let hess: Vec<f32> = index.iter().map(|i| h.as_ref().unwrap()[*i]).collect();
+        let mut hist_owned_sparse = NodeHistogramOwned::empty_from_cuts(&b.cuts, &col_index, false, false);
+        let hist_sparse = NodeHistogram::from_owned(&mut hist_owned_sparse);
+        let mut hist_owned_dense = NodeHistogramOwned::empty_from_cuts(&b.cuts, &col_index, false, false);
+        let hist_dense = NodeHistogram::from_owned(&mut hist_owned_dense);
+        for (hist, data) in [(&hist_sparse, &sparse), (&hist_dense, &BinnedMatrix::Dense(&bdata))] {
+            update_histogram(
+                hist,
+                20,
+                rows,
+                data,
+                &grad,
+                Some(&hess),
+                &index,
+                &col_index,
+                &pool,
+                true,
+            );
+        }
+
+        for (f1, f2) in hist_owned_sparse.data.iter().zip(hist_owned_dense.data.iter()) {
+            for (b1, b2) in f1.data.iter().zip(f2.data.iter()) {
+                assert_eq!(b1.counts, b2.counts);
+                b1.g_folded.iter().zip(b2.g_folded.iter()).for_each(|(g1, g2)| {
+                    assert_relative_eq!(g1, g2, epsilon = 1e-4);
+                });
+                let (h1, h2) = (b1.h_folded.unwrap(), b2.h_folded.unwrap());
+                h1.iter().zip(h2.iter()).for_each(|(h1, h2)| {
+                    assert_relative_eq!(h1, h2, epsilon = 1e-4);
+                });
+            }
+        }
+    }
 }
@@ -5,6 +5,8 @@ mod partial_dependence;
 mod shapley;
 
 // Modules
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod bin;
 pub mod binning;
 pub mod booster;
@@ -26,4 +28,4 @@ pub mod utils;
 // Individual classes, and functions
 pub use booster::booster::PerpetualBooster;
 pub use booster::multi_output::MultiOutputBooster;
-pub use data::Matrix;
+pub use data::{Matrix, SparseMatrix};
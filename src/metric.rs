@@ -1,6 +1,7 @@
 use crate::data::FloatData;
 use crate::errors::PerpetualError;
-use crate::utils::items_to_strings;
+use crate::objective::{erfc, log1p_exp, CoxLoss, Distribution, DistributionalLoss, ObjectiveFunction};
+use crate::utils::{items_to_strings, query_groups};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
@@ -34,10 +35,42 @@ pub fn is_comparison_better(value: f64, comparison: f64, maximize: bool) -> bool
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
 pub enum Metric {
     AUC,
+    /// Area under the precision recall curve, as the average precision.
+    PRAUC,
+    /// F1 score of the records with a predicted probability at or above a threshold, the threshold
+    /// is passed as `alpha` and defaults to 0.5.
+    F1,
     LogLoss,
+    /// Cross entropy of soft labels between 0 and 1, `yhat` are log odds.
+    CrossEntropy,
     RootMeanSquaredLogError,
     RootMeanSquaredError,
+    MeanAbsoluteError,
     QuantileLoss,
+    PoissonDeviance,
+    GammaDeviance,
+    /// Tweedie deviance, the variance power is passed as `alpha`.
+    TweedieDeviance,
+    /// Normalized discounted cumulative gain, the cutoff `k` is passed as `alpha`.
+    /// Averaged over the queries of the evaluation set `group`, the records are a single query without it.
+    NDCG,
+    /// Mean average precision, the cutoff `k` is passed as `alpha`.
+    /// Averaged over the queries of the evaluation set `group`, the records are a single query without it.
+    MAP,
+    /// Harrell's concordance index, `yhat` is larger for longer survival times, as the log times
+    /// predicted with `AFTLoss`. The censored records of an evaluation set are passed as its `event`,
+    /// all the events are taken as observed without it.
+    ConcordanceIndex,
+    /// Mean negative Cox partial log likelihood. The censored records of an evaluation set are passed
+    /// as its `event`, all the events are taken as observed without it.
+    CoxNegativeLogLikelihood,
+    /// Mean negative log likelihood of a distributional prediction, the default metric of the
+    /// Distributional objective. On its own, `yhat` holds the locations followed by the scales
+    /// of Normal distributions, as returned by `MultiOutputBooster::predict_dist`.
+    NegativeLogLikelihood,
+    /// Mean continuous ranked probability score of a distributional prediction, with `yhat` as for
+    /// `NegativeLogLikelihood`.
+    CRPS,
 }
 
 impl FromStr for Metric {
@@ -46,28 +79,78 @@ impl FromStr for Metric {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "AUC" => Ok(Metric::AUC),
+            "PRAUC" => Ok(Metric::PRAUC),
+            "F1" => Ok(Metric::F1),
             "LogLoss" => Ok(Metric::LogLoss),
+            "CrossEntropy" => Ok(Metric::CrossEntropy),
             "RootMeanSquaredLogError" => Ok(Metric::RootMeanSquaredLogError),
             "RootMeanSquaredError" => Ok(Metric::RootMeanSquaredError),
+            "MeanAbsoluteError" => Ok(Metric::MeanAbsoluteError),
+            "PoissonDeviance" => Ok(Metric::PoissonDeviance),
+            "GammaDeviance" => Ok(Metric::GammaDeviance),
+            "TweedieDeviance" => Ok(Metric::TweedieDeviance),
+            "NDCG" => Ok(Metric::NDCG),
+            "MAP" => Ok(Metric::MAP),
+            "ConcordanceIndex" => Ok(Metric::ConcordanceIndex),
+            "CoxNegativeLogLikelihood" => Ok(Metric::CoxNegativeLogLikelihood),
+            "NegativeLogLikelihood" => Ok(Metric::NegativeLogLikelihood),
+            "CRPS" => Ok(Metric::CRPS),
 
             _ => Err(PerpetualError::ParseString(
                 s.to_string(),
                 "Metric".to_string(),
                 items_to_strings(vec![
                     "AUC",
+                    "PRAUC",
+                    "F1",
                     "LogLoss",
+                    "CrossEntropy",
                     "RootMeanSquaredLogError",
                     "RootMeanSquaredError",
+                    "MeanAbsoluteError",
+                    "PoissonDeviance",
+                    "GammaDeviance",
+                    "TweedieDeviance",
+                    "NDCG",
+                    "MAP",
+                    "ConcordanceIndex",
+                    "CoxNegativeLogLikelihood",
+                    "NegativeLogLikelihood",
+                    "CRPS",
                 ]),
             )),
         }
     }
 }
 
+/// Value of a metric on a set of records. The ranking metrics are averaged over the queries
+/// in `group` when it is given, and the survival metrics account for the censored records
+/// in `event` when it is given.
+pub fn evaluate_metric(
+    metric: &Metric,
+    y: &[f64],
+    yhat: &[f64],
+    sample_weight: &[f64],
+    alpha: Option<f32>,
+    group: Option<&[u64]>,
+    event: Option<&[bool]>,
+) -> f64 {
+    match (metric, group) {
+        (Metric::NDCG, Some(group)) => ndcg_at_k(y, yhat, group, alpha.map(|k| k as usize)),
+        (Metric::MAP, Some(group)) => map_at_k(y, yhat, group, alpha.map(|k| k as usize)),
+        (Metric::ConcordanceIndex, _) => concordance_index(y, yhat, event),
+        (Metric::CoxNegativeLogLikelihood, _) => cox_negative_log_likelihood(y, yhat, sample_weight, event),
+        _ => metric_callables(metric).0(y, yhat, sample_weight, alpha),
+    }
+}
+
 pub fn metric_callables(metric_type: &Metric) -> (MetricFn, bool) {
     match metric_type {
         Metric::AUC => (AUCMetric::calculate_metric, AUCMetric::maximize()),
+        Metric::PRAUC => (PRAUCMetric::calculate_metric, PRAUCMetric::maximize()),
+        Metric::F1 => (F1Metric::calculate_metric, F1Metric::maximize()),
         Metric::LogLoss => (LogLossMetric::calculate_metric, LogLossMetric::maximize()),
+        Metric::CrossEntropy => (CrossEntropyMetric::calculate_metric, CrossEntropyMetric::maximize()),
         Metric::RootMeanSquaredLogError => (
             RootMeanSquaredLogErrorMetric::calculate_metric,
             RootMeanSquaredLogErrorMetric::maximize(),
@@ -76,7 +159,35 @@ pub fn metric_callables(metric_type: &Metric) -> (MetricFn, bool) {
             RootMeanSquaredErrorMetric::calculate_metric,
             RootMeanSquaredErrorMetric::maximize(),
         ),
+        Metric::MeanAbsoluteError => (
+            MeanAbsoluteErrorMetric::calculate_metric,
+            MeanAbsoluteErrorMetric::maximize(),
+        ),
         Metric::QuantileLoss => (QuantileLossMetric::calculate_metric, QuantileLossMetric::maximize()),
+        Metric::PoissonDeviance => (
+            PoissonDevianceMetric::calculate_metric,
+            PoissonDevianceMetric::maximize(),
+        ),
+        Metric::GammaDeviance => (GammaDevianceMetric::calculate_metric, GammaDevianceMetric::maximize()),
+        Metric::TweedieDeviance => (
+            TweedieDevianceMetric::calculate_metric,
+            TweedieDevianceMetric::maximize(),
+        ),
+        Metric::NDCG => (NDCGMetric::calculate_metric, NDCGMetric::maximize()),
+        Metric::MAP => (MAPMetric::calculate_metric, MAPMetric::maximize()),
+        Metric::ConcordanceIndex => (
+            ConcordanceIndexMetric::calculate_metric,
+            ConcordanceIndexMetric::maximize(),
+        ),
+        Metric::CoxNegativeLogLikelihood => (
+            CoxNegativeLogLikelihoodMetric::calculate_metric,
+            CoxNegativeLogLikelihoodMetric::maximize(),
+        ),
+        Metric::NegativeLogLikelihood => (
+            NegativeLogLikelihoodMetric::calculate_metric,
+            NegativeLogLikelihoodMetric::maximize(),
+        ),
+        Metric::CRPS => (CRPSMetric::calculate_metric, CRPSMetric::maximize()),
     }
 }
 
@@ -105,6 +216,16 @@ impl EvaluationMetric for LogLossMetric {
     }
 }
 
+pub struct CrossEntropyMetric {}
+impl EvaluationMetric for CrossEntropyMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        cross_entropy(y, yhat, sample_weight)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
 pub struct AUCMetric {}
 impl EvaluationMetric for AUCMetric {
     fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
@@ -115,6 +236,26 @@ impl EvaluationMetric for AUCMetric {
     }
 }
 
+pub struct PRAUCMetric {}
+impl EvaluationMetric for PRAUCMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        pr_auc_score(y, yhat, sample_weight)
+    }
+    fn maximize() -> bool {
+        true
+    }
+}
+
+pub struct F1Metric {}
+impl EvaluationMetric for F1Metric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], alpha: Option<f32>) -> f64 {
+        f1_score(y, yhat, sample_weight, alpha.map_or(0.5, |a| a as f64))
+    }
+    fn maximize() -> bool {
+        true
+    }
+}
+
 pub struct RootMeanSquaredLogErrorMetric {}
 impl EvaluationMetric for RootMeanSquaredLogErrorMetric {
     fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
@@ -135,6 +276,110 @@ impl EvaluationMetric for RootMeanSquaredErrorMetric {
     }
 }
 
+pub struct MeanAbsoluteErrorMetric {}
+impl EvaluationMetric for MeanAbsoluteErrorMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        mean_absolute_error(y, yhat, sample_weight)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
+pub struct PoissonDevianceMetric {}
+impl EvaluationMetric for PoissonDevianceMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        poisson_deviance(y, yhat, sample_weight)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
+pub struct GammaDevianceMetric {}
+impl EvaluationMetric for GammaDevianceMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        gamma_deviance(y, yhat, sample_weight)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
+pub struct TweedieDevianceMetric {}
+impl EvaluationMetric for TweedieDevianceMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], alpha: Option<f32>) -> f64 {
+        tweedie_deviance(y, yhat, sample_weight, alpha.unwrap_or(1.5) as f64)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
+pub struct NDCGMetric {}
+impl EvaluationMetric for NDCGMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], _sample_weight: &[f64], alpha: Option<f32>) -> f64 {
+        let rows: Vec<usize> = (0..y.len()).collect();
+        query_ndcg(y, yhat, &rows, alpha.map(|k| k as usize))
+    }
+    fn maximize() -> bool {
+        true
+    }
+}
+
+pub struct MAPMetric {}
+impl EvaluationMetric for MAPMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], _sample_weight: &[f64], alpha: Option<f32>) -> f64 {
+        let rows: Vec<usize> = (0..y.len()).collect();
+        query_average_precision(y, yhat, &rows, alpha.map(|k| k as usize))
+    }
+    fn maximize() -> bool {
+        true
+    }
+}
+
+pub struct ConcordanceIndexMetric {}
+impl EvaluationMetric for ConcordanceIndexMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], _sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        concordance_index(y, yhat, None)
+    }
+    fn maximize() -> bool {
+        true
+    }
+}
+
+pub struct CoxNegativeLogLikelihoodMetric {}
+impl EvaluationMetric for CoxNegativeLogLikelihoodMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        cox_negative_log_likelihood(y, yhat, sample_weight, None)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
+pub struct NegativeLogLikelihoodMetric {}
+impl EvaluationMetric for NegativeLogLikelihoodMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        let (loc, scale) = yhat.split_at(y.len());
+        negative_log_likelihood(Distribution::Normal, y, loc, scale, sample_weight)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
+pub struct CRPSMetric {}
+impl EvaluationMetric for CRPSMetric {
+    fn calculate_metric(y: &[f64], yhat: &[f64], sample_weight: &[f64], _alpha: Option<f32>) -> f64 {
+        let (loc, scale) = yhat.split_at(y.len());
+        crps(Distribution::Normal, y, loc, scale, sample_weight)
+    }
+    fn maximize() -> bool {
+        false
+    }
+}
+
 pub fn quantile_loss(y: &[f64], yhat: &[f64], sample_weight: &[f64], alpha: Option<f32>) -> f64 {
     let mut w_sum = 0.;
     let res = y
@@ -167,6 +412,23 @@ pub fn log_loss(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
     res / w_sum
 }
 
+/// Weighted mean cross entropy of soft labels `y`, `yhat` are log odds. Computed from the log
+/// odds directly, so that it stays finite for confident predictions.
+pub fn cross_entropy(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
+    let mut w_sum = 0.;
+    let res = y
+        .iter()
+        .zip(yhat)
+        .zip(sample_weight)
+        .map(|((y_, yhat_), w_)| {
+            w_sum += *w_;
+            // -ln(sigmoid(z)) = ln(1 + e^-z) and -ln(1 - sigmoid(z)) = ln(1 + e^z).
+            (*y_ * log1p_exp(-*yhat_) + (f64::ONE - *y_) * log1p_exp(*yhat_)) * *w_
+        })
+        .sum::<f64>();
+    res / w_sum
+}
+
 pub fn root_mean_squared_log_error(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
     let mut w_sum = 0.;
     let res = y
@@ -195,6 +457,242 @@ pub fn root_mean_squared_error(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -
     (res / w_sum).sqrt()
 }
 
+pub fn mean_absolute_error(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
+    let mut w_sum = 0.;
+    let res = y
+        .iter()
+        .zip(yhat)
+        .zip(sample_weight)
+        .map(|((y_, yhat_), w_)| {
+            w_sum += *w_;
+            (y_ - yhat_).abs() * *w_
+        })
+        .sum::<f64>();
+    res / w_sum
+}
+
+/// Mean Poisson deviance, `yhat` is the log of the expected count.
+pub fn poisson_deviance(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
+    let mut w_sum = 0.;
+    let res = y
+        .iter()
+        .zip(yhat)
+        .zip(sample_weight)
+        .map(|((y_, yhat_), w_)| {
+            w_sum += *w_;
+            let ylogy = if *y_ > 0.0 { *y_ * (y_.ln() - *yhat_) } else { 0.0 };
+            2.0 * (ylogy - (*y_ - yhat_.exp())) * *w_
+        })
+        .sum::<f64>();
+    res / w_sum
+}
+
+/// Mean Gamma deviance, `yhat` is the log of the expected value.
+pub fn gamma_deviance(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
+    let mut w_sum = 0.;
+    let res = y
+        .iter()
+        .zip(yhat)
+        .zip(sample_weight)
+        .map(|((y_, yhat_), w_)| {
+            w_sum += *w_;
+            2.0 * (*yhat_ - y_.ln() + *y_ * (-*yhat_).exp() - 1.0) * *w_
+        })
+        .sum::<f64>();
+    res / w_sum
+}
+
+/// Mean Tweedie deviance with `1 < variance_power < 2`, `yhat` is the log of the expected value.
+pub fn tweedie_deviance(y: &[f64], yhat: &[f64], sample_weight: &[f64], variance_power: f64) -> f64 {
+    let p = variance_power;
+    let mut w_sum = 0.;
+    let res = y
+        .iter()
+        .zip(yhat)
+        .zip(sample_weight)
+        .map(|((y_, yhat_), w_)| {
+            w_sum += *w_;
+            let d = y_.powf(2.0 - p) / ((1.0 - p) * (2.0 - p)) - *y_ * ((1.0 - p) * *yhat_).exp() / (1.0 - p)
+                + ((2.0 - p) * *yhat_).exp() / (2.0 - p);
+            2.0 * d * *w_
+        })
+        .sum::<f64>();
+    res / w_sum
+}
+
+/// Discount of the gain at a zero based rank.
+#[inline]
+pub(crate) fn dcg_discount(rank: usize) -> f64 {
+    1.0 / (rank as f64 + 2.0).log2()
+}
+
+/// Rows of a query, sorted by decreasing prediction.
+fn rank_rows(yhat: &[f64], rows: &[usize]) -> Vec<usize> {
+    let mut ranked = rows.to_vec();
+    ranked.sort_by(|&a, &b| yhat[b].total_cmp(&yhat[a]));
+    ranked
+}
+
+/// Discounted cumulative gain of the first `k` rows, with gains `2^y - 1`.
+fn dcg(y: &[f64], rows: &[usize], k: usize) -> f64 {
+    rows.iter()
+        .take(k)
+        .enumerate()
+        .map(|(rank, i)| (2f64.powf(y[*i]) - 1.0) * dcg_discount(rank))
+        .sum()
+}
+
+/// NDCG@k of the rows of a single query. A query without relevant records scores 1.
+fn query_ndcg(y: &[f64], yhat: &[f64], rows: &[usize], k: Option<usize>) -> f64 {
+    let k = k.unwrap_or(rows.len());
+    let mut ideal = rows.to_vec();
+    ideal.sort_by(|&a, &b| y[b].total_cmp(&y[a]));
+    let idcg = dcg(y, &ideal, k);
+    if idcg <= 0.0 {
+        return 1.0;
+    }
+    dcg(y, &rank_rows(yhat, rows), k) / idcg
+}
+
+/// AP@k of the rows of a single query, records with `y > 0` are relevant.
+/// A query without relevant records scores 1.
+fn query_average_precision(y: &[f64], yhat: &[f64], rows: &[usize], k: Option<usize>) -> f64 {
+    let k = k.unwrap_or(rows.len());
+    let n_relevant = rows.iter().filter(|i| y[**i] > 0.0).count().min(k);
+    if n_relevant == 0 {
+        return 1.0;
+    }
+    let mut hits = 0.0;
+    let mut precision_sum = 0.0;
+    for (rank, i) in rank_rows(yhat, rows).iter().take(k).enumerate() {
+        if y[*i] > 0.0 {
+            hits += 1.0;
+            precision_sum += hits / (rank as f64 + 1.0);
+        }
+    }
+    precision_sum / n_relevant as f64
+}
+
+/// Mean NDCG@k over the queries in `group`, with gains `2^y - 1`.
+/// All the records of a query are used if `k` is `None`.
+pub fn ndcg_at_k(y: &[f64], yhat: &[f64], group: &[u64], k: Option<usize>) -> f64 {
+    let groups = query_groups(group);
+    groups.iter().map(|rows| query_ndcg(y, yhat, rows, k)).sum::<f64>() / groups.len() as f64
+}
+
+/// Mean average precision at k over the queries in `group`, records with `y > 0` are relevant.
+/// All the records of a query are used if `k` is `None`.
+pub fn map_at_k(y: &[f64], yhat: &[f64], group: &[u64], k: Option<usize>) -> f64 {
+    let groups = query_groups(group);
+    groups
+        .iter()
+        .map(|rows| query_average_precision(y, yhat, rows, k))
+        .sum::<f64>()
+        / groups.len() as f64
+}
+
+/// Harrell's concordance index, the fraction of the comparable pairs of records ordered by their
+/// predictions as by their survival times. A pair is comparable when the record with the shorter
+/// time had an observed event. Ties in the predictions count as half concordant.
+///
+/// * `y` - Survival or censoring time of each record.
+/// * `yhat` - Predictions, larger for longer survival times. Negate risk scores, as the log hazard
+///   ratios predicted with `CoxLoss`.
+/// * `event` - Whether the event was observed for each record, every event is observed if `None`.
+pub fn concordance_index(y: &[f64], yhat: &[f64], event: Option<&[bool]>) -> f64 {
+    // Rank of each prediction, for a Fenwick tree of the records with longer times.
+    let mut sorted = yhat.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    sorted.dedup();
+    let rank = |v: f64| sorted.partition_point(|s| *s < v);
+    let mut tree = vec![0.0; sorted.len() + 1];
+    let count_below = |tree: &[f64], r: usize| {
+        let (mut i, mut c) = (r, 0.0);
+        while i > 0 {
+            c += tree[i];
+            i &= i - 1;
+        }
+        c
+    };
+
+    let mut order: Vec<usize> = (0..y.len()).collect();
+    order.sort_by(|&a, &b| y[a].total_cmp(&y[b]));
+    let (mut concordant, mut comparable) = (0.0, 0.0);
+    let mut n_longer = 0.0;
+    let mut end = order.len();
+    while end > 0 {
+        let mut start = end - 1;
+        while start > 0 && y[order[start - 1]] == y[order[end - 1]] {
+            start -= 1;
+        }
+        for i in &order[start..end] {
+            if event.is_none_or(|e| e[*i]) {
+                let r = rank(yhat[*i]);
+                let below = count_below(&tree, r);
+                let tied = count_below(&tree, r + 1) - below;
+                concordant += (n_longer - below - tied) + 0.5 * tied;
+                comparable += n_longer;
+            }
+        }
+        for i in &order[start..end] {
+            let mut j = rank(yhat[*i]) + 1;
+            while j < tree.len() {
+                tree[j] += 1.0;
+                j += j & j.wrapping_neg();
+            }
+            n_longer += 1.0;
+        }
+        end = start;
+    }
+    concordant / comparable
+}
+
+/// Mean negative Cox partial log likelihood, `yhat` are log hazard ratios.
+pub fn cox_negative_log_likelihood(y: &[f64], yhat: &[f64], sample_weight: &[f64], event: Option<&[bool]>) -> f64 {
+    let loss = CoxLoss::new(event).calc_loss(y, yhat, Some(sample_weight), None);
+    loss.iter().map(|l| *l as f64).sum::<f64>() / sample_weight.iter().sum::<f64>()
+}
+
+/// Weighted mean negative log likelihood of a distributional prediction, with the location and the scale
+/// of each record, the two halves of the predictions of `MultiOutputBooster::predict_dist`.
+pub fn negative_log_likelihood(
+    distribution: Distribution,
+    y: &[f64],
+    loc: &[f64],
+    scale: &[f64],
+    sample_weight: &[f64],
+) -> f64 {
+    let log_scale: Vec<f64> = scale.iter().map(|s| s.ln()).collect();
+    let nll = DistributionalLoss::new(distribution).negative_log_likelihood(y, loc, &log_scale, Some(sample_weight));
+    nll.iter().sum::<f64>() / sample_weight.iter().sum::<f64>()
+}
+
+/// Weighted mean continuous ranked probability score of a distributional prediction, with the location
+/// and the scale of each record, the two halves of the predictions of `MultiOutputBooster::predict_dist`. Uses the closed forms
+/// of the Normal and LogNormal distributions.
+pub fn crps(distribution: Distribution, y: &[f64], loc: &[f64], scale: &[f64], sample_weight: &[f64]) -> f64 {
+    let cdf = |z: f64| 0.5 * erfc(-z / std::f64::consts::SQRT_2);
+    let pdf = |z: f64| (-0.5 * z * z).exp() / (2.0 * std::f64::consts::PI).sqrt();
+    let total: f64 = (0..y.len())
+        .map(|i| {
+            let (m, s) = (loc[i], scale[i]);
+            let score = match distribution {
+                Distribution::Normal => {
+                    let z = (y[i] - m) / s;
+                    s * (z * (2.0 * cdf(z) - 1.0) + 2.0 * pdf(z) - 1.0 / std::f64::consts::PI.sqrt())
+                }
+                Distribution::LogNormal => {
+                    let z = (y[i].ln() - m) / s;
+                    y[i] * (2.0 * cdf(z) - 1.0)
+                        - 2.0 * (m + 0.5 * s * s).exp() * (cdf(z - s) + cdf(s / std::f64::consts::SQRT_2) - 1.0)
+                }
+            };
+            score * sample_weight[i]
+        })
+        .sum();
+    total / sample_weight.iter().sum::<f64>()
+}
+
 fn trapezoid_area(x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
     (x0 - x1).abs() * (y0 + y1) * 0.5
 }
@@ -233,6 +731,51 @@ pub fn roc_auc_score(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
     auc / (tp * fp)
 }
 
+/// Area under the precision recall curve, as the average precision. The recall increments of the
+/// records with tied predictions are taken together, at the precision after all of them.
+pub fn pr_auc_score(y: &[f64], yhat: &[f64], sample_weight: &[f64]) -> f64 {
+    let mut indices = (0..y.len()).collect::<Vec<_>>();
+    indices.sort_unstable_by(|&a, &b| yhat[b].total_cmp(&yhat[a]));
+    let total_pos: f64 = y.iter().zip(sample_weight).map(|(y_, w_)| y_ * w_).sum();
+    if total_pos <= 0.0 {
+        return 0.0;
+    }
+
+    let mut ap = 0.0;
+    let mut tp = 0.0;
+    let mut fp = 0.0;
+    let mut tp_prev = 0.0;
+    for (i, idx) in indices.iter().enumerate() {
+        tp += y[*idx] * sample_weight[*idx];
+        fp += (1.0 - y[*idx]) * sample_weight[*idx];
+        let last_of_tie = i + 1 == indices.len() || yhat[indices[i + 1]] != yhat[*idx];
+        if last_of_tie && tp > tp_prev {
+            ap += (tp - tp_prev) / total_pos * tp / (tp + fp);
+            tp_prev = tp;
+        }
+    }
+    ap
+}
+
+/// F1 score, with the records predicted positive when their probability is at or above
+/// `threshold`. `yhat` are log odds.
+pub fn f1_score(y: &[f64], yhat: &[f64], sample_weight: &[f64], threshold: f64) -> f64 {
+    let (mut tp, mut fp, mut fn_) = (0.0, 0.0, 0.0);
+    for ((y_, yhat_), w_) in y.iter().zip(yhat).zip(sample_weight) {
+        let positive = f64::ONE / (f64::ONE + (-*yhat_).exp()) >= threshold;
+        match (positive, *y_ >= 0.5) {
+            (true, true) => tp += w_,
+            (true, false) => fp += w_,
+            (false, true) => fn_ += w_,
+            (false, false) => {}
+        }
+    }
+    if tp <= 0.0 {
+        return 0.0;
+    }
+    2.0 * tp / (2.0 * tp + fp + fn_)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -254,6 +797,60 @@ mod tests {
         assert_eq!(precision_round(res, 6), 1.452966);
     }
 
+    #[test]
+    fn test_mean_absolute_error() {
+        let y = vec![1., 3., 4., 5., 2., 4., 6.];
+        let yhat = vec![3., 2., 3., 4., 4., 4., 4.];
+        let sample_weight = vec![1., 1., 1., 1., 1., 2., 2.];
+        let res = mean_absolute_error(&y, &yhat, &sample_weight);
+        assert_eq!(precision_round(res, 6), 1.222222);
+    }
+
+    #[test]
+    fn test_ranking_metrics() {
+        let y = [3., 0., 2., 0., 1., 1.];
+        let yhat = [0.1, 0.9, 0.4, 0.3, 0.1, 0.2];
+        let group = [1, 2, 1, 1, 2, 1];
+        assert_eq!(precision_round(ndcg_at_k(&y, &yhat, &group, None), 6), 0.662259);
+        assert_eq!(precision_round(ndcg_at_k(&y, &yhat, &group, Some(2)), 6), 0.484141);
+        assert_eq!(precision_round(map_at_k(&y, &yhat, &group, None), 6), 0.652778);
+        assert_eq!(precision_round(map_at_k(&y, &yhat, &group, Some(2)), 6), 0.5);
+        assert_eq!(ndcg_at_k(&[0., 0.], &[0.3, 0.1], &[1, 1], None), 1.0);
+    }
+
+    #[test]
+    fn test_concordance_index() {
+        let y = [1., 2., 3., 4.];
+        let event = [true, false, true, true];
+        assert_eq!(concordance_index(&y, &[2., 3., 1., 4.], Some(&event)), 0.75);
+        assert_eq!(concordance_index(&y, &[2., 3., 2., 4.], Some(&event)), 0.875);
+        assert_eq!(concordance_index(&y, &[4., 3., 2., 1.], None), 0.0);
+        // Records with the same time are not comparable.
+        assert_eq!(concordance_index(&[1., 1., 2.], &[0., 1., 0.5], None), 0.5);
+    }
+
+    #[test]
+    fn test_deviances() {
+        let y = vec![0., 1., 2., 4.];
+        let yhat: Vec<f64> = [0.5_f64, 1.5, 2.0, 3.0].iter().map(|v| v.ln()).collect();
+        let sample_weight = vec![1., 1., 1., 2.];
+        let res = poisson_deviance(&y, &yhat, &sample_weight);
+        assert_eq!(precision_round(res, 6), 0.358397);
+
+        let y = vec![1., 2., 4.];
+        let yhat: Vec<f64> = [1.5_f64, 2.0, 3.0].iter().map(|v| v.ln()).collect();
+        let sample_weight = vec![1., 1., 2.];
+        let res = gamma_deviance(&y, &yhat, &sample_weight);
+        assert_eq!(precision_round(res, 6), 0.081717);
+        let res = tweedie_deviance(&y, &yhat, &sample_weight, 1.5);
+        assert_eq!(precision_round(res, 6), 0.124145);
+
+        // The deviance is zero for perfect predictions.
+        let yhat: Vec<f64> = y.iter().map(|v| v.ln()).collect();
+        let res = tweedie_deviance(&y, &yhat, &sample_weight, 1.5);
+        assert_eq!(precision_round(res, 6), 0.0);
+    }
+
     #[test]
     fn test_log_loss() {
         let y = vec![1., 0., 1., 0., 0., 0., 0.];
@@ -311,4 +908,75 @@ mod tests {
         let auc_score = roc_auc_score(&y, &yhat, &sample_weight);
         assert!(auc_score.is_nan());
     }
+
+    #[test]
+    fn test_classification_metrics() {
+        let y = [1., 0., 1., 0., 0., 1.];
+        let yhat = [2.0, 1.0, 0.5, -0.5, -1.0, -2.0];
+        let w = [1.0; 6];
+        // Precision 1, 2/3 and 1/2 at the three positives.
+        assert_eq!(precision_round(pr_auc_score(&y, &yhat, &w), 6), 0.722222);
+        // Ties are ranked together.
+        assert_eq!(pr_auc_score(&y, &[0.0; 6], &w), 0.5);
+        assert_eq!(pr_auc_score(&[0.0; 6], &yhat, &w), 0.0);
+        assert_eq!(precision_round(f1_score(&y, &yhat, &w, 0.5), 6), 0.666667);
+        assert_eq!(precision_round(f1_score(&y, &yhat, &w, 0.8), 6), 0.5);
+        assert_eq!(f1_score(&y, &yhat, &w, 0.99), 0.0);
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let yhat = [2.0, 1.0, 0.5, -0.5, -1.0, -2.0];
+        let w = [1., 1., 1., 1., 2., 2.];
+        // Same as the log loss for binary labels.
+        let y = [1., 0., 1., 0., 0., 1.];
+        assert_eq!(
+            precision_round(cross_entropy(&y, &yhat, &w), 10),
+            precision_round(log_loss(&y, &yhat, &w), 10)
+        );
+        let y = [0.9, 0.2, 0.5, 0.5, 0.0, 0.7];
+        assert_eq!(precision_round(cross_entropy(&y, &yhat, &w), 6), 0.82109);
+        assert!(cross_entropy(&[1.0], &[1000.0], &[1.0]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_distribution_metrics() {
+        let y = [1.0, 2.0, 3.0];
+        let loc = [0.5, 0.5, 1.0];
+        let scale = [1.0, 0.5, 2.0];
+        let w = [1.0, 2.0, 1.0];
+        assert_eq!(
+            precision_round(negative_log_likelihood(Distribution::Normal, &y, &loc, &scale, &w), 6),
+            3.151902
+        );
+        assert_eq!(
+            precision_round(
+                negative_log_likelihood(Distribution::LogNormal, &y, &loc, &scale, &w),
+                6
+            ),
+            1.435738
+        );
+        assert_eq!(
+            precision_round(crps(Distribution::Normal, &y, &loc, &scale, &w), 5),
+            0.99322
+        );
+        assert_eq!(
+            precision_round(crps(Distribution::LogNormal, &y, &loc, &scale, &w), 5),
+            0.82928
+        );
+        let dist = [loc, scale].concat();
+        assert_eq!(
+            metric_callables(&Metric::NegativeLogLikelihood).0(&y, &dist, &w, None),
+            negative_log_likelihood(Distribution::Normal, &y, &loc, &scale, &w)
+        );
+        assert_eq!(
+            metric_callables(&Metric::CRPS).0(&y, &dist, &w, None),
+            crps(Distribution::Normal, &y, &loc, &scale, &w)
+        );
+        // The score of a point at the mean of a standard Normal.
+        assert_eq!(
+            precision_round(crps(Distribution::Normal, &[0.0], &[0.0], &[1.0], &[1.0]), 6),
+            0.233695
+        );
+    }
 }
@@ -32,6 +32,8 @@ pub struct SplittableNode {
     pub parent_node: usize,
     pub left_cats: HashSet<usize>,
     pub right_cats: HashSet<usize>,
+    /// Features split on along the path from the root to the node.
+    pub path_features: HashSet<usize>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -159,6 +161,7 @@ impl SplittableNode {
             parent_node,
             left_cats: HashSet::new(),
             right_cats: HashSet::new(),
+            path_features: HashSet::new(),
         }
     }
 
@@ -206,6 +209,7 @@ impl SplittableNode {
             parent_node: 0,
             left_cats,
             right_cats,
+            path_features: HashSet::new(),
         }
     }
 
@@ -262,17 +266,23 @@ impl SplittableNode {
     }
 }
 
-impl fmt::Display for Node {
-    // This trait requires `fmt` with this exact signature.
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl Node {
+    /// Format the node as in its `Display` output, with the name of the split feature
+    /// instead of its index, if the feature names are given.
+    ///
+    /// * `feature_names` - Names of the features, indexed by the split feature.
+    pub fn to_string_with_names(&self, feature_names: Option<&[String]>) -> String {
         if self.is_leaf {
-            write!(f, "{}:leaf={},cover={}", self.num, self.weight_value, self.hessian_sum)
+            format!("{}:leaf={},cover={}", self.num, self.weight_value, self.hessian_sum)
         } else {
-            write!(
-                f,
+            let split_feature = match feature_names.and_then(|names| names.get(self.split_feature)) {
+                Some(name) => name.to_string(),
+                None => self.split_feature.to_string(),
+            };
+            format!(
                 "{}:[{} < {}] yes={},no={},missing={},gain={},cover={}",
                 self.num,
-                self.split_feature,
+                split_feature,
                 self.split_value,
                 self.left_child,
                 self.right_child,
@@ -283,3 +293,10 @@ impl fmt::Display for Node {
         }
     }
 }
+
+impl fmt::Display for Node {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_with_names(None))
+    }
+}
@@ -1,50 +1,487 @@
-use crate::{data::FloatData, metric::Metric, utils::fast_sum};
+use crate::{
+    data::{FloatData, Matrix},
+    errors::PerpetualError,
+    metric::{dcg_discount, Metric},
+    utils::{fast_sum, percentiles, query_groups},
+};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
 
-type ObjFn = fn(&[f64], &[f64], Option<&[f64]>, Option<f64>) -> (Vec<f32>, Option<Vec<f32>>);
-type LossFn = fn(&[f64], &[f64], Option<&[f64]>, Option<f64>) -> Vec<f32>;
+pub type ObjFn = Box<dyn Fn(&[f64], &[f64], Option<&[f64]>, Option<f64>) -> (Vec<f32>, Option<Vec<f32>>) + Send + Sync>;
+pub type LossFn = Box<dyn Fn(&[f64], &[f64], Option<&[f64]>, Option<f64>) -> Vec<f32> + Send + Sync>;
+pub type InitFn = Box<dyn Fn(&[f64], Option<&[f64]>, Option<f64>) -> f64 + Send + Sync>;
+pub type LeafFn = Box<dyn Fn(&[f64], &[f64], Option<&[f64]>) -> f64 + Send + Sync>;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum Objective {
     LogLoss,
+    /// Focal loss for imbalanced binary classification. The log loss of each record is scaled
+    /// by `(1 - p_t)^gamma`, which down-weights the well classified records, and by `alpha` for
+    /// the positive class and `1 - alpha` for the negative class.
+    FocalLoss {
+        gamma: f64,
+        alpha: f64,
+    },
+    /// Cross entropy for soft labels between 0 and 1, such as averaged annotator labels or the
+    /// probabilities of a teacher model. The predictions are log odds, as with LogLoss.
+    CrossEntropy,
     SquaredLoss,
     QuantileLoss,
+    /// Cross entropy over multiple classes, trained jointly with `MultiOutputBooster`,
+    /// with one booster per class.
+    Softmax,
+    /// Poisson deviance with a log link, for count data.
+    PoissonLoss,
+    /// Gamma deviance with a log link, for positive continuous data.
+    GammaLoss,
+    /// Tweedie deviance with a log link, for non-negative data with exact zeros.
+    /// `variance_power` must be between 1 (Poisson) and 2 (Gamma).
+    TweedieLoss {
+        variance_power: f64,
+    },
+    /// Absolute error, for median regression.
+    AbsoluteLoss,
+    /// Huber loss, squared error for residuals smaller than `delta` and absolute error otherwise.
+    HuberLoss {
+        delta: f64,
+    },
+    /// Pseudo-Huber loss, a smooth approximation of the Huber loss with transition point `delta`.
+    PseudoHuberLoss {
+        delta: f64,
+    },
+    /// Pairwise logistic loss between the records of each query (RankNet).
+    /// The query of each record is passed as `group` to `fit`.
+    RankNetLoss,
+    /// Pairwise logistic loss weighted by the change in NDCG from swapping the two records
+    /// (LambdaRank). The query of each record is passed as `group` to `fit`.
+    LambdaRankLoss,
+    /// Negative Cox partial log likelihood, for right-censored survival times. The predictions
+    /// are log hazard ratios. Censored records are passed with `fit_survival`.
+    CoxLoss,
+    /// Accelerated failure time model, `log(T) = yhat + sigma * Z` with the error `Z` following
+    /// `distribution`. The predictions are log survival times. Censored records are passed as
+    /// bounds with `fit_survival`.
+    AFTLoss {
+        distribution: AFTDistribution,
+        sigma: f64,
+    },
+    /// Distributional regression, the location and the log scale of `distribution` are boosted
+    /// jointly, with the two boosters of a `MultiOutputBooster`. See `MultiOutputBooster::predict_dist`.
+    Distributional {
+        distribution: Distribution,
+    },
+    /// A user defined objective, created with `Objective::custom`.
+    Custom(CustomObjective),
 }
 
-pub fn loss_callables(objective: &Objective) -> LossFn {
-    match objective {
-        Objective::LogLoss => LogLoss::calc_loss,
-        Objective::SquaredLoss => SquaredLoss::calc_loss,
-        Objective::QuantileLoss => QuantileLoss::calc_loss,
+/// Distribution of the target in distributional regression.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    Normal,
+    /// The log of the target follows a Normal distribution, the target must be positive.
+    LogNormal,
+}
+
+/// Distribution of the error of the accelerated failure time model.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum AFTDistribution {
+    Normal,
+    Logistic,
+    /// Minimum extreme value (Gumbel) distribution, the survival times follow a Weibull distribution.
+    Extreme,
+}
+
+/// Censoring of the survival times passed as `y` to `fit_survival`.
+pub enum Censoring<'a> {
+    /// Whether the event was observed for each record, `y` is the time of censoring otherwise.
+    Event(&'a [bool]),
+    /// Upper bound of the survival time of each record, with `y` as the lower bound. The upper bound
+    /// equals `y` when the event was observed, and is infinite for right-censored records.
+    UpperBound(&'a [f64]),
+}
+
+impl Censoring<'_> {
+    /// Upper bound of the survival time of each record.
+    pub fn upper_bounds(&self, y: &[f64]) -> Vec<f64> {
+        match self {
+            Censoring::Event(event) => y
+                .iter()
+                .zip(event.iter())
+                .map(|(y_, e_)| if *e_ { *y_ } else { f64::INFINITY })
+                .collect(),
+            Censoring::UpperBound(upper) => upper.to_vec(),
+        }
+    }
+}
+
+impl Objective {
+    /// Whether the loss of a record depends on the other records of its query.
+    pub fn is_ranking(&self) -> bool {
+        matches!(self, Objective::RankNetLoss | Objective::LambdaRankLoss)
+    }
+
+    /// Whether the objective models survival times, trained with `fit_survival`.
+    pub fn is_survival(&self) -> bool {
+        matches!(self, Objective::CoxLoss | Objective::AFTLoss { .. })
+    }
+
+    /// Whether the loss of a record only depends on its own target and prediction.
+    pub fn has_record_loss(&self) -> bool {
+        !(self.is_ranking() || matches!(self, Objective::CoxLoss | Objective::Distributional { .. }))
+    }
+
+    /// Create an objective from a user implementation of `ObjectiveFunction`.
+    ///
+    /// * `objective` - The objective function to optimize.
+    pub fn custom<T: ObjectiveFunction + 'static>(objective: T) -> Self {
+        Objective::Custom(CustomObjective::new(objective))
+    }
+
+    /// Check that the objective can be used for training. Custom objectives
+    /// are not serialized with the model, so they need to be set again after loading.
+    pub fn validate(&self) -> Result<(), PerpetualError> {
+        match self {
+            Objective::Custom(c) if c.function.is_none() => Err(PerpetualError::UnregisteredObjective(c.name.clone())),
+            Objective::TweedieLoss { variance_power } if !(*variance_power > 1.0 && *variance_power < 2.0) => {
+                Err(PerpetualError::InvalidParameter(
+                    "variance_power".to_string(),
+                    "a value between 1 and 2".to_string(),
+                    variance_power.to_string(),
+                ))
+            }
+            Objective::HuberLoss { delta } | Objective::PseudoHuberLoss { delta }
+                if delta.is_nan() || *delta <= 0.0 =>
+            {
+                Err(PerpetualError::InvalidParameter(
+                    "delta".to_string(),
+                    "a positive value".to_string(),
+                    delta.to_string(),
+                ))
+            }
+            Objective::FocalLoss { gamma, .. } if !(gamma.is_finite() && *gamma >= 0.0) => {
+                Err(PerpetualError::InvalidParameter(
+                    "gamma".to_string(),
+                    "a non-negative value".to_string(),
+                    gamma.to_string(),
+                ))
+            }
+            Objective::FocalLoss { alpha, .. } if !(*alpha > 0.0 && *alpha < 1.0) => {
+                Err(PerpetualError::InvalidParameter(
+                    "alpha".to_string(),
+                    "a value between 0 and 1".to_string(),
+                    alpha.to_string(),
+                ))
+            }
+            Objective::AFTLoss { sigma, .. } if sigma.is_nan() || *sigma <= 0.0 => {
+                Err(PerpetualError::InvalidParameter(
+                    "sigma".to_string(),
+                    "a positive value".to_string(),
+                    sigma.to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// User defined objective. Only the name of the objective is serialized with the model,
+/// the objective function itself needs to be registered again after the model is loaded.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CustomObjective {
+    /// Name of the objective, as returned by `ObjectiveFunction::name`.
+    pub name: String,
+    #[serde(skip)]
+    function: Option<Arc<dyn ObjectiveFunction>>,
+}
+
+impl CustomObjective {
+    pub fn new<T: ObjectiveFunction + 'static>(objective: T) -> Self {
+        CustomObjective {
+            name: objective.name(),
+            function: Some(Arc::new(objective)),
+        }
+    }
+
+    /// Get the registered objective function, if any.
+    pub fn function(&self) -> Option<&Arc<dyn ObjectiveFunction>> {
+        self.function.as_ref()
+    }
+
+    fn registered(&self) -> Arc<dyn ObjectiveFunction> {
+        match &self.function {
+            Some(f) => Arc::clone(f),
+            None => panic!("{}", PerpetualError::UnregisteredObjective(self.name.clone())),
+        }
+    }
+}
+
+impl fmt::Debug for CustomObjective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomObjective")
+            .field("name", &self.name)
+            .field("registered", &self.function.is_some())
+            .finish()
     }
 }
 
-pub fn gradient_hessian_callables(objective: &Objective) -> ObjFn {
+/// Error for the objectives without a single output loss. Distributional is only fit by
+/// `MultiOutputBooster`, with a loss for each of its parameters.
+fn single_output_error(objective: &Objective) -> PerpetualError {
+    PerpetualError::InvalidParameter(
+        "objective".to_string(),
+        "an objective with a single output loss".to_string(),
+        format!("{:?}", objective),
+    )
+}
+
+/// The loss of an objective, or an error for the objectives without a single output loss.
+pub fn loss_callables(objective: &Objective) -> Result<LossFn, PerpetualError> {
+    let f: LossFn = match objective {
+        Objective::LogLoss => Box::new(|y, yhat, w, q| LogLoss::default().calc_loss(y, yhat, w, q)),
+        Objective::CrossEntropy => Box::new(|y, yhat, w, q| CrossEntropy::default().calc_loss(y, yhat, w, q)),
+        Objective::FocalLoss { gamma, alpha } => {
+            let f = FocalLoss::new(*gamma, *alpha);
+            Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q))
+        }
+        Objective::SquaredLoss => Box::new(|y, yhat, w, q| SquaredLoss::default().calc_loss(y, yhat, w, q)),
+        Objective::QuantileLoss => Box::new(|y, yhat, w, q| QuantileLoss::default().calc_loss(y, yhat, w, q)),
+        Objective::Softmax => Box::new(|y, yhat, w, q| Softmax::default().calc_loss(y, yhat, w, q)),
+        Objective::PoissonLoss => Box::new(|y, yhat, w, q| PoissonLoss::default().calc_loss(y, yhat, w, q)),
+        Objective::GammaLoss => Box::new(|y, yhat, w, q| GammaLoss::default().calc_loss(y, yhat, w, q)),
+        Objective::TweedieLoss { variance_power } => {
+            let f = TweedieLoss::new(*variance_power);
+            Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q))
+        }
+        Objective::AbsoluteLoss => Box::new(|y, yhat, w, q| AbsoluteLoss::default().calc_loss(y, yhat, w, q)),
+        Objective::HuberLoss { delta } => {
+            let f = HuberLoss::new(*delta);
+            Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q))
+        }
+        Objective::PseudoHuberLoss { delta } => {
+            let f = PseudoHuberLoss::new(*delta);
+            Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q))
+        }
+        Objective::RankNetLoss => Box::new(|y, yhat, w, q| RankLoss::new(false, None).calc_loss(y, yhat, w, q)),
+        Objective::LambdaRankLoss => Box::new(|y, yhat, w, q| RankLoss::new(true, None).calc_loss(y, yhat, w, q)),
+        Objective::CoxLoss => Box::new(|y, yhat, w, q| CoxLoss::new(None).calc_loss(y, yhat, w, q)),
+        Objective::AFTLoss { distribution, sigma } => {
+            let f = AFTLoss::new(*distribution, *sigma, None);
+            Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q))
+        }
+        Objective::Distributional { .. } => return Err(single_output_error(objective)),
+        Objective::Custom(c) => {
+            let f = c.registered();
+            Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q))
+        }
+    };
+    Ok(f)
+}
+
+/// The gradient and hessian of an objective, or an error for the objectives without a single output loss.
+pub fn gradient_hessian_callables(objective: &Objective) -> Result<ObjFn, PerpetualError> {
+    let f: ObjFn = match objective {
+        Objective::LogLoss => Box::new(|y, yhat, w, q| LogLoss::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::CrossEntropy => Box::new(|y, yhat, w, q| CrossEntropy::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::FocalLoss { gamma, alpha } => {
+            let f = FocalLoss::new(*gamma, *alpha);
+            Box::new(move |y, yhat, w, q| f.calc_grad_hess(y, yhat, w, q))
+        }
+        Objective::SquaredLoss => Box::new(|y, yhat, w, q| SquaredLoss::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::QuantileLoss => Box::new(|y, yhat, w, q| QuantileLoss::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::Softmax => Box::new(|y, yhat, w, q| Softmax::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::PoissonLoss => Box::new(|y, yhat, w, q| PoissonLoss::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::GammaLoss => Box::new(|y, yhat, w, q| GammaLoss::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::TweedieLoss { variance_power } => {
+            let f = TweedieLoss::new(*variance_power);
+            Box::new(move |y, yhat, w, q| f.calc_grad_hess(y, yhat, w, q))
+        }
+        Objective::AbsoluteLoss => Box::new(|y, yhat, w, q| AbsoluteLoss::default().calc_grad_hess(y, yhat, w, q)),
+        Objective::HuberLoss { delta } => {
+            let f = HuberLoss::new(*delta);
+            Box::new(move |y, yhat, w, q| f.calc_grad_hess(y, yhat, w, q))
+        }
+        Objective::PseudoHuberLoss { delta } => {
+            let f = PseudoHuberLoss::new(*delta);
+            Box::new(move |y, yhat, w, q| f.calc_grad_hess(y, yhat, w, q))
+        }
+        Objective::RankNetLoss => Box::new(|y, yhat, w, q| RankLoss::new(false, None).calc_grad_hess(y, yhat, w, q)),
+        Objective::LambdaRankLoss => Box::new(|y, yhat, w, q| RankLoss::new(true, None).calc_grad_hess(y, yhat, w, q)),
+        Objective::CoxLoss => Box::new(|y, yhat, w, q| CoxLoss::new(None).calc_grad_hess(y, yhat, w, q)),
+        Objective::AFTLoss { distribution, sigma } => {
+            let f = AFTLoss::new(*distribution, *sigma, None);
+            Box::new(move |y, yhat, w, q| f.calc_grad_hess(y, yhat, w, q))
+        }
+        Objective::Distributional { .. } => return Err(single_output_error(objective)),
+        Objective::Custom(c) => {
+            let f = c.registered();
+            Box::new(move |y, yhat, w, q| f.calc_grad_hess(y, yhat, w, q))
+        }
+    };
+    Ok(f)
+}
+
+/// The initial value of an objective, or an error for the objectives without a single output loss.
+pub fn calc_init_callables(objective: &Objective) -> Result<InitFn, PerpetualError> {
+    let f: InitFn = match objective {
+        Objective::LogLoss => Box::new(|y, w, q| LogLoss::default().calc_init(y, w, q)),
+        Objective::CrossEntropy => Box::new(|y, w, q| CrossEntropy::default().calc_init(y, w, q)),
+        Objective::FocalLoss { gamma, alpha } => {
+            let f = FocalLoss::new(*gamma, *alpha);
+            Box::new(move |y, w, q| f.calc_init(y, w, q))
+        }
+        Objective::SquaredLoss => Box::new(|y, w, q| SquaredLoss::default().calc_init(y, w, q)),
+        Objective::QuantileLoss => Box::new(|y, w, q| QuantileLoss::default().calc_init(y, w, q)),
+        Objective::Softmax => Box::new(|y, w, q| Softmax::default().calc_init(y, w, q)),
+        Objective::PoissonLoss => Box::new(|y, w, q| PoissonLoss::default().calc_init(y, w, q)),
+        Objective::GammaLoss => Box::new(|y, w, q| GammaLoss::default().calc_init(y, w, q)),
+        Objective::TweedieLoss { variance_power } => {
+            let f = TweedieLoss::new(*variance_power);
+            Box::new(move |y, w, q| f.calc_init(y, w, q))
+        }
+        Objective::AbsoluteLoss => Box::new(|y, w, q| AbsoluteLoss::default().calc_init(y, w, q)),
+        Objective::HuberLoss { delta } => {
+            let f = HuberLoss::new(*delta);
+            Box::new(move |y, w, q| f.calc_init(y, w, q))
+        }
+        Objective::PseudoHuberLoss { delta } => {
+            let f = PseudoHuberLoss::new(*delta);
+            Box::new(move |y, w, q| f.calc_init(y, w, q))
+        }
+        Objective::RankNetLoss => Box::new(|y, w, q| RankLoss::new(false, None).calc_init(y, w, q)),
+        Objective::LambdaRankLoss => Box::new(|y, w, q| RankLoss::new(true, None).calc_init(y, w, q)),
+        Objective::CoxLoss => Box::new(|y, w, q| CoxLoss::new(None).calc_init(y, w, q)),
+        Objective::AFTLoss { distribution, sigma } => {
+            let f = AFTLoss::new(*distribution, *sigma, None);
+            Box::new(move |y, w, q| f.calc_init(y, w, q))
+        }
+        Objective::Distributional { .. } => return Err(single_output_error(objective)),
+        Objective::Custom(c) => {
+            let f = c.registered();
+            Box::new(move |y, w, q| f.calc_init(y, w, q))
+        }
+    };
+    Ok(f)
+}
+
+pub fn default_metric(objective: &Objective) -> Metric {
     match objective {
-        Objective::LogLoss => LogLoss::calc_grad_hess,
-        Objective::SquaredLoss => SquaredLoss::calc_grad_hess,
-        Objective::QuantileLoss => QuantileLoss::calc_grad_hess,
+        Objective::LogLoss => LogLoss::default().default_metric(),
+        Objective::CrossEntropy => CrossEntropy::default().default_metric(),
+        Objective::FocalLoss { gamma, alpha } => FocalLoss::new(*gamma, *alpha).default_metric(),
+        Objective::SquaredLoss => SquaredLoss::default().default_metric(),
+        Objective::QuantileLoss => QuantileLoss::default().default_metric(),
+        Objective::Softmax => Softmax::default().default_metric(),
+        Objective::PoissonLoss => PoissonLoss::default().default_metric(),
+        Objective::GammaLoss => GammaLoss::default().default_metric(),
+        Objective::TweedieLoss { variance_power } => TweedieLoss::new(*variance_power).default_metric(),
+        Objective::AbsoluteLoss => AbsoluteLoss::default().default_metric(),
+        Objective::HuberLoss { delta } => HuberLoss::new(*delta).default_metric(),
+        Objective::PseudoHuberLoss { delta } => PseudoHuberLoss::new(*delta).default_metric(),
+        Objective::RankNetLoss => RankLoss::new(false, None).default_metric(),
+        Objective::LambdaRankLoss => RankLoss::new(true, None).default_metric(),
+        Objective::CoxLoss => CoxLoss::new(None).default_metric(),
+        Objective::AFTLoss { distribution, sigma } => AFTLoss::new(*distribution, *sigma, None).default_metric(),
+        Objective::Distributional { .. } => Metric::NegativeLogLikelihood,
+        Objective::Custom(c) => c.registered().default_metric(),
     }
 }
 
-pub fn calc_init_callables(objective: &Objective) -> fn(&[f64], Option<&[f64]>, Option<f64>) -> f64 {
+/// Objectives for which the leaf values are not a good Newton step, because the hessian is
+/// zero or meaningless for part of the records, get their leaf values set again after a tree
+/// is grown, from the targets and predictions of the records in each leaf.
+pub fn leaf_value_callables(objective: &Objective) -> Option<LeafFn> {
     match objective {
-        Objective::LogLoss => LogLoss::calc_init,
-        Objective::SquaredLoss => SquaredLoss::calc_init,
-        Objective::QuantileLoss => QuantileLoss::calc_init,
+        Objective::AbsoluteLoss => Some(Box::new(|y, yhat, w| {
+            AbsoluteLoss::default().calc_leaf_value(y, yhat, w)
+        })),
+        Objective::HuberLoss { delta } => {
+            let f = HuberLoss::new(*delta);
+            Some(Box::new(move |y, yhat, w| f.calc_leaf_value(y, yhat, w)))
+        }
+        Objective::PseudoHuberLoss { delta } => {
+            let f = PseudoHuberLoss::new(*delta);
+            Some(Box::new(move |y, yhat, w| f.calc_leaf_value(y, yhat, w)))
+        }
+        _ => None,
     }
 }
 
-pub trait ObjectiveFunction {
-    fn calc_loss(y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> Vec<f32>;
+/// Leaf values of a quantile regression tree, the quantile of the residuals of the records
+/// in each leaf. Used to give every quantile its own leaf values on a shared tree structure.
+pub fn quantile_leaf_callable(quantile: f64) -> LeafFn {
+    Box::new(move |y, yhat, w| quantile_residual(y, yhat, w, quantile))
+}
+
+/// Loss and gradient callables of a ranking objective, for the records of the queries in `group`.
+/// Returns `None` for objectives that are not ranking objectives.
+pub fn rank_callables(objective: &Objective, group: &[u64]) -> Option<(LossFn, ObjFn)> {
+    let f = Arc::new(match objective {
+        Objective::RankNetLoss => RankLoss::new(false, Some(group)),
+        Objective::LambdaRankLoss => RankLoss::new(true, Some(group)),
+        _ => return None,
+    });
+    let g = Arc::clone(&f);
+    Some((
+        Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q)),
+        Box::new(move |y, yhat, w, q| g.calc_grad_hess(y, yhat, w, q)),
+    ))
+}
+
+/// Loss and gradient callables of a survival objective, for right or interval censored records
+/// with survival times between `y` and `y_upper`. Returns `None` for other objectives.
+pub fn survival_callables(objective: &Objective, y_upper: &[f64]) -> Option<(LossFn, ObjFn)> {
+    let f: Arc<dyn ObjectiveFunction> = match objective {
+        Objective::CoxLoss => {
+            let event: Vec<bool> = y_upper.iter().map(|u| u.is_finite()).collect();
+            Arc::new(CoxLoss::new(Some(&event)))
+        }
+        Objective::AFTLoss { distribution, sigma } => Arc::new(AFTLoss::new(*distribution, *sigma, Some(y_upper))),
+        _ => return None,
+    };
+    let g = Arc::clone(&f);
+    Some((
+        Box::new(move |y, yhat, w, q| f.calc_loss(y, yhat, w, q)),
+        Box::new(move |y, yhat, w, q| g.calc_grad_hess(y, yhat, w, q)),
+    ))
+}
+
+/// Change in loss of records whose predictions move by `yhat`, from the second order expansion
+/// of the loss around the current predictions, with the gradients passed as `y` and the hessians
+/// as `sample_weight`. Trees use it to measure the loss decrement of their leaves when the loss
+/// of a record depends on the other records, as with the ranking objectives.
+pub fn second_order_loss_callable() -> LossFn {
+    Box::new(|y, yhat, w, _q| {
+        let h = w.expect("the hessian is passed as sample_weight");
+        y.iter()
+            .zip(yhat)
+            .zip(h)
+            .map(|((g_, d_), h_)| (*g_ * *d_ + 0.5 * *h_ * *d_ * *d_) as f32)
+            .collect()
+    })
+}
+
+/// Objective function to optimize. Implement this trait, and pass it to
+/// `Objective::custom` to train a booster on a user defined loss.
+pub trait ObjectiveFunction: Send + Sync {
+    /// Calculate the loss for each record.
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> Vec<f32>;
+    /// Calculate the gradient and hessian for each record. Return `None` for the hessian
+    /// if it is constant and equal to one for every record.
     fn calc_grad_hess(
+        &self,
         y: &[f64],
         yhat: &[f64],
         sample_weight: Option<&[f64]>,
         quantile: Option<f64>,
     ) -> (Vec<f32>, Option<Vec<f32>>);
-    fn calc_init(y: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> f64;
-    fn default_metric() -> Metric;
+    /// Calculate the initial prediction value of the model.
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> f64;
+    fn default_metric(&self) -> Metric;
+    /// Name used to identify the objective when the model is serialized.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
 }
 
 #[derive(Default)]
@@ -52,7 +489,7 @@ pub struct LogLoss {}
 
 impl ObjectiveFunction for LogLoss {
     #[inline]
-    fn calc_loss(y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
         match sample_weight {
             Some(sample_weight) => y
                 .iter()
@@ -74,7 +511,7 @@ impl ObjectiveFunction for LogLoss {
         }
     }
 
-    fn calc_init(y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
         match sample_weight {
             Some(sample_weight) => {
                 let mut ytot: f64 = 0.;
@@ -95,6 +532,7 @@ impl ObjectiveFunction for LogLoss {
 
     #[inline]
     fn calc_grad_hess(
+        &self,
         y: &[f64],
         yhat: &[f64],
         sample_weight: Option<&[f64]>,
@@ -127,17 +565,214 @@ impl ObjectiveFunction for LogLoss {
         }
     }
 
-    fn default_metric() -> Metric {
+    fn default_metric(&self) -> Metric {
         Metric::LogLoss
     }
 }
 
+#[derive(Default)]
+pub struct CrossEntropy {}
+
+impl CrossEntropy {
+    /// Check that the labels are probabilities.
+    pub fn validate_labels(y: &[f64]) -> Result<(), PerpetualError> {
+        match y.iter().find(|y_| !(0.0..=1.0).contains(*y_)) {
+            Some(y_) => Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                "labels between 0 and 1 for CrossEntropy".to_string(),
+                y_.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ObjectiveFunction for CrossEntropy {
+    /// Cross entropy in excess of the entropy of the label, so that the loss of a record is zero
+    /// when the predicted probability equals its label, as for the binary labels of LogLoss.
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let record_loss = |y_: f64, yhat_: f64| {
+            let entropy = -(xlogy(y_, y_) + xlogy(1.0 - y_, 1.0 - y_));
+            y_ * log1p_exp(-yhat_) + (1.0 - y_) * log1p_exp(yhat_) - entropy
+        };
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| (record_loss(*y_, *yhat_) * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| record_loss(*y_, *yhat_) as f32)
+                .collect(),
+        }
+    }
+
+    /// Log odds of the weighted mean label, kept finite when all the labels are 0 or 1.
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        let (ytot, ntot) = match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(sample_weight)
+                .fold((0.0, 0.0), |(yt, nt), (y_, w_)| (yt + y_ * w_, nt + w_)),
+            None => (fast_sum(y), y.len() as f64),
+        };
+        let mean = (ytot / ntot).clamp(1e-15, 1.0 - 1e-15);
+        f64::ln(mean / (1.0 - mean))
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        LogLoss::default().calc_grad_hess(y, yhat, sample_weight, quantile)
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::CrossEntropy
+    }
+}
+
+/// `x * ln(y)`, zero when `x` is zero.
+#[inline]
+fn xlogy(x: f64, y: f64) -> f64 {
+    if x == 0.0 {
+        0.0
+    } else {
+        x * y.ln()
+    }
+}
+
+const FOCAL_INIT_ITERATIONS: usize = 60;
+const FOCAL_INIT_BOUND: f64 = 30.0;
+
+pub struct FocalLoss {
+    pub gamma: f64,
+    pub alpha: f64,
+}
+
+impl FocalLoss {
+    pub fn new(gamma: f64, alpha: f64) -> Self {
+        FocalLoss { gamma, alpha }
+    }
+
+    // Probability of the observed class, and the class weight and the sign of the margin for it.
+    #[inline]
+    fn observed(&self, y: f64, yhat: f64) -> (f64, f64, f64) {
+        let p = f64::ONE / (f64::ONE + (-yhat).exp());
+        let (p_t, alpha_t, sign) = if y >= 0.5 {
+            (p, self.alpha, 1.0)
+        } else {
+            (1.0 - p, 1.0 - self.alpha, -1.0)
+        };
+        (p_t.clamp(1e-15, 1.0 - 1e-15), alpha_t, sign)
+    }
+
+    fn record_loss(&self, y: f64, yhat: f64) -> f64 {
+        let (p_t, alpha_t, _) = self.observed(y, yhat);
+        -alpha_t * (1.0 - p_t).powf(self.gamma) * p_t.ln()
+    }
+
+    // Derivatives of the loss of a record with respect to the margin. The hessian is not
+    // convex everywhere for gamma > 1, so it is floored to keep the leaf values bounded.
+    fn record_grad_hess(&self, y: f64, yhat: f64) -> (f64, f64) {
+        let (p_t, alpha_t, sign) = self.observed(y, yhat);
+        let g = self.gamma;
+        let q_t = 1.0 - p_t;
+        let inner = g * p_t * p_t.ln() - q_t;
+        let grad = sign * alpha_t * q_t.powf(g) * inner;
+        let hess = alpha_t * p_t * (-g * q_t.powf(g) * inner + q_t.powf(g + 1.0) * (g * p_t.ln() + g + 1.0));
+        (grad, hess.max(f64::EPSILON))
+    }
+}
+
+impl ObjectiveFunction for FocalLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| (self.record_loss(*y_, *yhat_) * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| self.record_loss(*y_, *yhat_) as f32)
+                .collect(),
+        }
+    }
+
+    /// Constant margin where the gradients sum to zero, found by bisection. The sum of the gradients
+    /// goes from minus the weight of the positive class to the weight of the negative class.
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        let grad_sum = |margin: f64| {
+            y.iter()
+                .enumerate()
+                .map(|(i, y_)| self.record_grad_hess(*y_, margin).0 * sample_weight.map_or(1.0, |w| w[i]))
+                .sum::<f64>()
+        };
+        let (mut lower, mut upper) = (-FOCAL_INIT_BOUND, FOCAL_INIT_BOUND);
+        for _ in 0..FOCAL_INIT_ITERATIONS {
+            let mid = 0.5 * (lower + upper);
+            if grad_sum(mid) < 0.0 {
+                lower = mid;
+            } else {
+                upper = mid;
+            }
+        }
+        0.5 * (lower + upper)
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let (g, h) = match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| {
+                    let (g, h) = self.record_grad_hess(*y_, *yhat_);
+                    ((g * *w_) as f32, (h * *w_) as f32)
+                })
+                .unzip(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| {
+                    let (g, h) = self.record_grad_hess(*y_, *yhat_);
+                    (g as f32, h as f32)
+                })
+                .unzip(),
+        };
+        (g, Some(h))
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::PRAUC
+    }
+}
+
 #[derive(Default)]
 pub struct SquaredLoss {}
 
 impl ObjectiveFunction for SquaredLoss {
     #[inline]
-    fn calc_loss(y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
         match sample_weight {
             Some(sample_weight) => y
                 .iter()
@@ -159,7 +794,7 @@ impl ObjectiveFunction for SquaredLoss {
         }
     }
 
-    fn calc_init(y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
         match sample_weight {
             Some(sample_weight) => {
                 let mut ytot: f64 = 0.;
@@ -176,6 +811,7 @@ impl ObjectiveFunction for SquaredLoss {
 
     #[inline]
     fn calc_grad_hess(
+        &self,
         y: &[f64],
         yhat: &[f64],
         sample_weight: Option<&[f64]>,
@@ -198,8 +834,8 @@ impl ObjectiveFunction for SquaredLoss {
         }
     }
 
-    fn default_metric() -> Metric {
-        Metric::RootMeanSquaredLogError
+    fn default_metric(&self) -> Metric {
+        Metric::RootMeanSquaredError
     }
 }
 
@@ -208,7 +844,7 @@ pub struct QuantileLoss {}
 
 impl ObjectiveFunction for QuantileLoss {
     #[inline]
-    fn calc_loss(y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> Vec<f32> {
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> Vec<f32> {
         match sample_weight {
             Some(sample_weight) => y
                 .iter()
@@ -234,7 +870,7 @@ impl ObjectiveFunction for QuantileLoss {
         }
     }
 
-    fn calc_init(y: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> f64 {
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> f64 {
         match sample_weight {
             Some(sample_weight) => {
                 let mut indices = (0..y.len()).collect::<Vec<_>>();
@@ -273,6 +909,7 @@ impl ObjectiveFunction for QuantileLoss {
 
     #[inline]
     fn calc_grad_hess(
+        &self,
         y: &[f64],
         yhat: &[f64],
         sample_weight: Option<&[f64]>,
@@ -317,84 +954,1775 @@ impl ObjectiveFunction for QuantileLoss {
         }
     }
 
-    fn default_metric() -> Metric {
+    fn default_metric(&self) -> Metric {
         Metric::QuantileLoss
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn test_logloss_loss() {
-        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
-        let yhat1 = vec![-1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
-        let l1 = LogLoss::calc_loss(&y, &yhat1, None, None);
-        let yhat2 = vec![0.0, 0.0, -1.0, 1.0, 0.0, 1.0];
-        let l2 = LogLoss::calc_loss(&y, &yhat2, None, None);
-        assert!(l1.iter().sum::<f32>() < l2.iter().sum::<f32>());
+/// Multiclass cross entropy. The loss of a single class, with the margins of all the
+/// other classes held fixed, is the logistic loss of the class margin offset by the
+/// log-sum-exp of the other margins, up to a constant. So each class tree of a boosting
+/// round is fit on `yhat - offset` (see `Softmax::offsets`), using the logistic loss.
+#[derive(Default)]
+pub struct Softmax {}
+
+impl Softmax {
+    /// Calculate the offsets of the class margins, `ln(sum(exp(yhat_j)))` over every class `j != k`,
+    /// for each class `k` and record.
+    ///
+    /// * `yhat` - The margins of each class.
+    pub fn offsets(yhat: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let n_classes = yhat.len();
+        let n_rows = yhat.first().map_or(0, |v| v.len());
+        (0..n_classes)
+            .map(|k| {
+                (0..n_rows)
+                    .map(|i| {
+                        let max = (0..n_classes)
+                            .filter(|j| *j != k)
+                            .map(|j| yhat[j][i])
+                            .fold(f64::NEG_INFINITY, f64::max);
+                        let sum: f64 = (0..n_classes)
+                            .filter(|j| *j != k)
+                            .map(|j| (yhat[j][i] - max).exp())
+                            .sum();
+                        max + sum.ln()
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
-    #[test]
-    fn test_logloss_grad() {
-        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
-        let yhat1 = vec![-1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
-        let (g1, _) = LogLoss::calc_grad_hess(&y, &yhat1, None, None);
-        let yhat2 = vec![0.0, 0.0, -1.0, 1.0, 0.0, 1.0];
-        let (g2, _) = LogLoss::calc_grad_hess(&y, &yhat2, None, None);
-        assert!(g1.iter().sum::<f32>() < g2.iter().sum::<f32>());
+    /// Calculate the cross entropy over all classes for each record.
+    ///
+    /// * `y` - One hot encoded labels, one column per class.
+    /// * `yhat` - The margins of each class.
+    /// * `sample_weight` - Instance weights for each record.
+    pub fn joint_loss(y: &Matrix<f64>, yhat: &[Vec<f64>], sample_weight: Option<&[f64]>) -> Vec<f32> {
+        (0..y.rows)
+            .map(|i| {
+                let max = yhat.iter().map(|v| v[i]).fold(f64::NEG_INFINITY, f64::max);
+                let lse = max + yhat.iter().map(|v| (v[i] - max).exp()).sum::<f64>().ln();
+                let l: f64 = yhat.iter().enumerate().map(|(k, v)| *y.get(i, k) * (lse - v[i])).sum();
+                match sample_weight {
+                    Some(sample_weight) => (l * sample_weight[i]) as f32,
+                    None => l as f32,
+                }
+            })
+            .collect()
     }
+}
 
-    #[test]
-    fn test_logloss_init() {
-        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
-        let l1 = LogLoss::calc_init(&y, None, None);
-        assert!(l1 == 0.);
+impl ObjectiveFunction for Softmax {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, quantile: Option<f64>) -> Vec<f32> {
+        LogLoss::default().calc_loss(y, yhat, sample_weight, quantile)
+    }
 
-        let y = vec![1.0; 6];
-        let l2 = LogLoss::calc_init(&y, None, None);
-        assert!(l2 == f64::INFINITY);
+    // The log of the class prior, the softmax of these over all classes gives back the priors.
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        log_mean_init(y, sample_weight)
+    }
 
-        let y = vec![0.0; 6];
-        let l3 = LogLoss::calc_init(&y, None, None);
-        assert!(l3 == f64::NEG_INFINITY);
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        LogLoss::default().calc_grad_hess(y, yhat, sample_weight, quantile)
+    }
 
-        let y = vec![0., 0., 0., 0., 1., 1.];
-        let l4 = LogLoss::calc_init(&y, None, None);
-        assert!(l4 == f64::ln(2. / 4.));
+    fn default_metric(&self) -> Metric {
+        Metric::LogLoss
     }
+}
 
-    #[test]
-    fn test_mse_init() {
-        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
-        let l1 = SquaredLoss::calc_init(&y, None, None);
-        assert!(l1 == 0.5);
+/// Initial value of the log link objectives, the log of the weighted mean of y.
+fn log_mean_init(y: &[f64], sample_weight: Option<&[f64]>) -> f64 {
+    let (ytot, ntot) = match sample_weight {
+        Some(sample_weight) => y
+            .iter()
+            .zip(sample_weight)
+            .fold((0.0, 0.0), |(yt, nt), (y_, w_)| (yt + y_ * w_, nt + w_)),
+        None => (fast_sum(y), y.len() as f64),
+    };
+    f64::ln(f64::max(ytot / ntot, f64::EPSILON))
+}
 
-        let y = vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
-        let l2 = SquaredLoss::calc_init(&y, None, None);
-        assert!(l2 == 1.);
+/// Poisson regression with a log link, `yhat` is the log of the expected count.
+/// The loss is the unit deviance, `2 * (y * ln(y / mu) - (y - mu))`.
+#[derive(Default)]
+pub struct PoissonLoss {}
 
-        let y = vec![-1.0, -1.0, -1.0, -1.0, -1.0, -1.0];
-        let l3 = SquaredLoss::calc_init(&y, None, None);
-        assert!(l3 == -1.);
+impl PoissonLoss {
+    /// Check that the labels are non-negative.
+    pub fn validate_labels(y: &[f64]) -> Result<(), PerpetualError> {
+        match y.iter().find(|y_| !(y_.is_finite() && **y_ >= 0.0)) {
+            Some(y_) => Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                "non-negative labels for PoissonLoss".to_string(),
+                y_.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
 
-        let y = vec![-1.0, -1.0, -1.0, 1., 1., 1.];
-        let l4 = SquaredLoss::calc_init(&y, None, None);
-        assert!(l4 == 0.);
+impl ObjectiveFunction for PoissonLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let deviance = |y_: f64, yhat_: f64| {
+            let mu = yhat_.exp();
+            let ylogy = if y_ > 0.0 { y_ * (y_.ln() - yhat_) } else { 0.0 };
+            2.0 * (ylogy - (y_ - mu))
+        };
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| (deviance(*y_, *yhat_) * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| deviance(*y_, *yhat_) as f32)
+                .collect(),
+        }
     }
 
-    #[test]
-    fn test_quantile_init() {
-        let y = vec![1.0, 2.0, 9.0, 3.2, 4.0];
-        let w = vec![0.0, 0.5, 1.0, 0.3, 0.5];
-        let l1 = QuantileLoss::calc_init(&y, Some(&w), Some(0.1));
-        println!("{}", l1);
-        assert!(l1 == 2.0);
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        log_mean_init(y, sample_weight)
+    }
 
-        let y = vec![1.0, 2.0, 9.0, 3.2, 4.0];
-        let w = vec![0.0, 0.5, 1.0, 0.3, 0.5];
-        let l2 = QuantileLoss::calc_init(&y, Some(&w), Some(0.9));
-        println!("{}", l2);
-        assert!(l2 == 9.0);
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        match sample_weight {
+            Some(sample_weight) => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .zip(sample_weight)
+                    .map(|((y_, yhat_), w_)| {
+                        let mu = yhat_.exp();
+                        (((mu - *y_) * *w_) as f32, (mu * *w_) as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+            None => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .map(|(y_, yhat_)| {
+                        let mu = yhat_.exp();
+                        ((mu - *y_) as f32, mu as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+        }
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::PoissonDeviance
+    }
+}
+
+/// Gamma regression with a log link, `yhat` is the log of the expected value.
+/// The loss is the unit deviance, `2 * (ln(mu / y) + y / mu - 1)`.
+#[derive(Default)]
+pub struct GammaLoss {}
+
+impl GammaLoss {
+    /// Check that the labels are positive.
+    pub fn validate_labels(y: &[f64]) -> Result<(), PerpetualError> {
+        match y.iter().find(|y_| !(y_.is_finite() && **y_ > 0.0)) {
+            Some(y_) => Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                "positive labels for GammaLoss".to_string(),
+                y_.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ObjectiveFunction for GammaLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let deviance = |y_: f64, yhat_: f64| 2.0 * (yhat_ - y_.ln() + y_ * (-yhat_).exp() - 1.0);
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| (deviance(*y_, *yhat_) * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| deviance(*y_, *yhat_) as f32)
+                .collect(),
+        }
+    }
+
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        log_mean_init(y, sample_weight)
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        match sample_weight {
+            Some(sample_weight) => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .zip(sample_weight)
+                    .map(|((y_, yhat_), w_)| {
+                        let r = *y_ * (-*yhat_).exp();
+                        (((1.0 - r) * *w_) as f32, (r * *w_) as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+            None => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .map(|(y_, yhat_)| {
+                        let r = *y_ * (-*yhat_).exp();
+                        ((1.0 - r) as f32, r as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+        }
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::GammaDeviance
+    }
+}
+
+/// Tweedie regression with a log link, `yhat` is the log of the expected value.
+/// The loss is the unit deviance for the given variance power `p`, with `1 < p < 2`,
+/// `2 * (y^(2-p) / ((1-p) * (2-p)) - y * mu^(1-p) / (1-p) + mu^(2-p) / (2-p))`.
+pub struct TweedieLoss {
+    pub variance_power: f64,
+}
+
+impl TweedieLoss {
+    pub fn new(variance_power: f64) -> Self {
+        TweedieLoss { variance_power }
+    }
+}
+
+impl Default for TweedieLoss {
+    fn default() -> Self {
+        TweedieLoss::new(1.5)
+    }
+}
+
+impl TweedieLoss {
+    /// Check that the labels are non-negative.
+    pub fn validate_labels(y: &[f64]) -> Result<(), PerpetualError> {
+        match y.iter().find(|y_| !(y_.is_finite() && **y_ >= 0.0)) {
+            Some(y_) => Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                "non-negative labels for TweedieLoss".to_string(),
+                y_.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ObjectiveFunction for TweedieLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let p = self.variance_power;
+        let deviance = |y_: f64, yhat_: f64| {
+            2.0 * (y_.powf(2.0 - p) / ((1.0 - p) * (2.0 - p)) - y_ * ((1.0 - p) * yhat_).exp() / (1.0 - p)
+                + ((2.0 - p) * yhat_).exp() / (2.0 - p))
+        };
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| (deviance(*y_, *yhat_) * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| deviance(*y_, *yhat_) as f32)
+                .collect(),
+        }
+    }
+
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        log_mean_init(y, sample_weight)
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let p = self.variance_power;
+        let grad_hess = |y_: f64, yhat_: f64| {
+            let a = y_ * ((1.0 - p) * yhat_).exp();
+            let b = ((2.0 - p) * yhat_).exp();
+            (b - a, (2.0 - p) * b - (1.0 - p) * a)
+        };
+        match sample_weight {
+            Some(sample_weight) => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .zip(sample_weight)
+                    .map(|((y_, yhat_), w_)| {
+                        let (g_, h_) = grad_hess(*y_, *yhat_);
+                        ((g_ * *w_) as f32, (h_ * *w_) as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+            None => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .map(|(y_, yhat_)| {
+                        let (g_, h_) = grad_hess(*y_, *yhat_);
+                        (g_ as f32, h_ as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+        }
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::TweedieDeviance
+    }
+}
+
+/// Weighted median of the residuals `y - yhat`.
+fn median_residual(y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>) -> f64 {
+    quantile_residual(y, yhat, sample_weight, 0.5)
+}
+
+/// Weighted quantile of the residuals of a set of records.
+fn quantile_residual(y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, quantile: f64) -> f64 {
+    let residuals: Vec<f64> = y.iter().zip(yhat).map(|(y_, yhat_)| y_ - yhat_).collect();
+    match sample_weight {
+        Some(sample_weight) => percentiles(&residuals, sample_weight, &[quantile])[0],
+        None => percentiles(&residuals, &vec![1.0; residuals.len()], &[quantile])[0],
+    }
+}
+
+const PSEUDO_HUBER_ITERATIONS: usize = 20;
+
+/// Sign of a value, zero for zero.
+fn sign(v: f64) -> f64 {
+    if v > 0.0 {
+        1.0
+    } else if v < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+/// Absolute error, `|y - yhat|`. The hessian is zero, so the trees are grown on the sign of
+/// the residuals with a constant hessian, and the leaf values are set to the median residual.
+#[derive(Default)]
+pub struct AbsoluteLoss {}
+
+impl AbsoluteLoss {
+    /// Median of the residuals of the records in a leaf.
+    pub fn calc_leaf_value(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>) -> f64 {
+        median_residual(y, yhat, sample_weight)
+    }
+}
+
+impl ObjectiveFunction for AbsoluteLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| ((*y_ - *yhat_).abs() * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| (*y_ - *yhat_).abs() as f32)
+                .collect(),
+        }
+    }
+
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        median_residual(y, &vec![0.0; y.len()], sample_weight)
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        match sample_weight {
+            Some(sample_weight) => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .zip(sample_weight)
+                    .map(|((y_, yhat_), w_)| ((sign(yhat_ - *y_) * *w_) as f32, *w_ as f32))
+                    .unzip();
+                (g, Some(h))
+            }
+            None => (
+                y.iter().zip(yhat).map(|(y_, yhat_)| sign(yhat_ - *y_) as f32).collect(),
+                None,
+            ),
+        }
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::MeanAbsoluteError
+    }
+}
+
+/// Huber loss, `0.5 * r^2` for residuals with `|r| <= delta`, and `delta * (|r| - 0.5 * delta)` otherwise.
+/// The hessian is zero outside of `delta`, so the trees are grown on the clipped residuals with a
+/// constant hessian, and the leaf values are set with the robust estimate of Friedman (2001),
+/// the median residual plus the mean of the clipped deviations from it.
+pub struct HuberLoss {
+    pub delta: f64,
+}
+
+impl HuberLoss {
+    pub fn new(delta: f64) -> Self {
+        HuberLoss { delta }
+    }
+
+    /// Robust estimate of the location of the residuals of the records in a leaf.
+    pub fn calc_leaf_value(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>) -> f64 {
+        let median = median_residual(y, yhat, sample_weight);
+        let clipped = |y_: &f64, yhat_: &f64| (*y_ - *yhat_ - median).clamp(-self.delta, self.delta);
+        let (total, w_total) = match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .fold((0.0, 0.0), |(t, wt), ((y_, yhat_), w_)| {
+                    (t + clipped(y_, yhat_) * w_, wt + w_)
+                }),
+            None => (
+                y.iter().zip(yhat).map(|(y_, yhat_)| clipped(y_, yhat_)).sum::<f64>(),
+                y.len() as f64,
+            ),
+        };
+        median + total / w_total
+    }
+}
+
+impl Default for HuberLoss {
+    fn default() -> Self {
+        HuberLoss::new(1.0)
+    }
+}
+
+impl ObjectiveFunction for HuberLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let delta = self.delta;
+        let huber = |y_: f64, yhat_: f64| {
+            let r = (y_ - yhat_).abs();
+            if r <= delta {
+                0.5 * r * r
+            } else {
+                delta * (r - 0.5 * delta)
+            }
+        };
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| (huber(*y_, *yhat_) * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| huber(*y_, *yhat_) as f32)
+                .collect(),
+        }
+    }
+
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        self.calc_leaf_value(y, &vec![0.0; y.len()], sample_weight)
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let delta = self.delta;
+        match sample_weight {
+            Some(sample_weight) => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .zip(sample_weight)
+                    .map(|((y_, yhat_), w_)| (((yhat_ - *y_).clamp(-delta, delta) * *w_) as f32, *w_ as f32))
+                    .unzip();
+                (g, Some(h))
+            }
+            None => (
+                y.iter()
+                    .zip(yhat)
+                    .map(|(y_, yhat_)| (yhat_ - *y_).clamp(-delta, delta) as f32)
+                    .collect(),
+                None,
+            ),
+        }
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::MeanAbsoluteError
+    }
+}
+
+/// Pseudo-Huber loss, `delta^2 * (sqrt(1 + (r / delta)^2) - 1)`. The hessian is positive but
+/// vanishes for large residuals, so the Newton step of a leaf with mostly large residuals overshoots.
+/// The trees are grown on the exact gradient and hessian, and the leaf values are set to the
+/// minimizer of the loss of the records in the leaf.
+pub struct PseudoHuberLoss {
+    pub delta: f64,
+}
+
+impl PseudoHuberLoss {
+    pub fn new(delta: f64) -> Self {
+        PseudoHuberLoss { delta }
+    }
+
+    /// Location of the residuals of the records in a leaf, that minimizes the loss. Found
+    /// with iteratively reweighted least squares, starting from the median residual.
+    pub fn calc_leaf_value(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>) -> f64 {
+        let residuals: Vec<f64> = y.iter().zip(yhat).map(|(y_, yhat_)| y_ - yhat_).collect();
+        let mut value = median_residual(y, yhat, sample_weight);
+        for _ in 0..PSEUDO_HUBER_ITERATIONS {
+            let (total, w_total) = residuals.iter().enumerate().fold((0.0, 0.0), |(t, wt), (i, r)| {
+                let d = (r - value) / self.delta;
+                let w = sample_weight.map_or(1.0, |w| w[i]) / (1.0 + d * d).sqrt();
+                (t + w * r, wt + w)
+            });
+            value = total / w_total;
+        }
+        value
+    }
+}
+
+impl Default for PseudoHuberLoss {
+    fn default() -> Self {
+        PseudoHuberLoss::new(1.0)
+    }
+}
+
+impl ObjectiveFunction for PseudoHuberLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let delta = self.delta;
+        let pseudo_huber = |y_: f64, yhat_: f64| {
+            let r = (y_ - yhat_) / delta;
+            delta * delta * ((1.0 + r * r).sqrt() - 1.0)
+        };
+        match sample_weight {
+            Some(sample_weight) => y
+                .iter()
+                .zip(yhat)
+                .zip(sample_weight)
+                .map(|((y_, yhat_), w_)| (pseudo_huber(*y_, *yhat_) * *w_) as f32)
+                .collect(),
+            None => y
+                .iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| pseudo_huber(*y_, *yhat_) as f32)
+                .collect(),
+        }
+    }
+
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        median_residual(y, &vec![0.0; y.len()], sample_weight)
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let delta = self.delta;
+        let grad_hess = |y_: f64, yhat_: f64| {
+            let r = yhat_ - y_;
+            let s = 1.0 + (r / delta) * (r / delta);
+            (r / s.sqrt(), 1.0 / (s * s.sqrt()))
+        };
+        match sample_weight {
+            Some(sample_weight) => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .zip(sample_weight)
+                    .map(|((y_, yhat_), w_)| {
+                        let (g_, h_) = grad_hess(*y_, *yhat_);
+                        ((g_ * *w_) as f32, (h_ * *w_) as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+            None => {
+                let (g, h) = y
+                    .iter()
+                    .zip(yhat)
+                    .map(|(y_, yhat_)| {
+                        let (g_, h_) = grad_hess(*y_, *yhat_);
+                        (g_ as f32, h_ as f32)
+                    })
+                    .unzip();
+                (g, Some(h))
+            }
+        }
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::MeanAbsoluteError
+    }
+}
+
+/// `ln(1 + e^x)`, without overflow for large `x`.
+#[inline]
+pub(crate) fn log1p_exp(x: f64) -> f64 {
+    x.max(0.0) + (-x.abs()).exp().ln_1p()
+}
+
+/// Pairwise logistic loss between the records of each query, `log(1 + exp(-(s_i - s_j)))` for each pair
+/// where record i is more relevant than record j. With `ndcg` set, the pairs are weighted by the change in
+/// NDCG from swapping the two records in the current ranking (LambdaRank), otherwise all pairs count
+/// the same (RankNet). The loss of a pair is split evenly between its records, so that the loss of a
+/// record is its share of the loss of its query.
+pub struct RankLoss {
+    pub ndcg: bool,
+    /// Rows of each query, all the records are a single query when empty.
+    groups: Vec<Vec<usize>>,
+}
+
+impl RankLoss {
+    /// * `ndcg` - Weight the pairs by the change in NDCG.
+    /// * `group` - Query id of each record, all the records are a single query if `None`.
+    pub fn new(ndcg: bool, group: Option<&[u64]>) -> Self {
+        RankLoss {
+            ndcg,
+            groups: group.map(query_groups).unwrap_or_default(),
+        }
+    }
+
+    /// Call `f` for each pair of records of a query with different relevance, with the more relevant
+    /// record first, and the weight of the pair.
+    fn for_each_pair<F: FnMut(usize, usize, f64)>(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        mut f: F,
+    ) {
+        let single_query = [(0..y.len()).collect::<Vec<usize>>()];
+        let groups: &[Vec<usize>] = if self.groups.is_empty() {
+            &single_query
+        } else {
+            &self.groups
+        };
+        for rows in groups {
+            let mut rank = vec![0; rows.len()];
+            let mut idcg = 1.0;
+            if self.ndcg {
+                let mut order: Vec<usize> = (0..rows.len()).collect();
+                order.sort_by(|&a, &b| yhat[rows[b]].total_cmp(&yhat[rows[a]]));
+                order.iter().enumerate().for_each(|(r, p)| rank[*p] = r);
+                let mut ideal: Vec<f64> = rows.iter().map(|i| y[*i]).collect();
+                ideal.sort_by(|a, b| b.total_cmp(a));
+                idcg = ideal
+                    .iter()
+                    .enumerate()
+                    .map(|(r, y_)| (2f64.powf(*y_) - 1.0) * dcg_discount(r))
+                    .sum();
+                if idcg <= 0.0 {
+                    continue;
+                }
+            }
+            for a in 0..rows.len() {
+                for b in (a + 1)..rows.len() {
+                    let (i, j) = (rows[a], rows[b]);
+                    if y[i] == y[j] {
+                        continue;
+                    }
+                    let (hi, lo, r_hi, r_lo) = if y[i] > y[j] {
+                        (i, j, rank[a], rank[b])
+                    } else {
+                        (j, i, rank[b], rank[a])
+                    };
+                    let mut pair_weight = match sample_weight {
+                        Some(w) => 0.5 * (w[hi] + w[lo]),
+                        None => 1.0,
+                    };
+                    if self.ndcg {
+                        pair_weight *=
+                            ((2f64.powf(y[hi]) - 2f64.powf(y[lo])) * (dcg_discount(r_hi) - dcg_discount(r_lo))).abs()
+                                / idcg;
+                    }
+                    f(hi, lo, pair_weight);
+                }
+            }
+        }
+    }
+}
+
+impl ObjectiveFunction for RankLoss {
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let mut loss = vec![0.0_f64; y.len()];
+        self.for_each_pair(y, yhat, sample_weight, |hi, lo, pair_weight| {
+            let l = 0.5 * pair_weight * log1p_exp(yhat[lo] - yhat[hi]);
+            loss[hi] += l;
+            loss[lo] += l;
+        });
+        loss.into_iter().map(|l| l as f32).collect()
+    }
+
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let mut grad = vec![0.0_f64; y.len()];
+        let mut hess = vec![0.0_f64; y.len()];
+        self.for_each_pair(y, yhat, sample_weight, |hi, lo, pair_weight| {
+            let rho = 1.0 / (1.0 + (yhat[hi] - yhat[lo]).exp());
+            grad[hi] -= pair_weight * rho;
+            grad[lo] += pair_weight * rho;
+            let h = pair_weight * rho * (1.0 - rho);
+            hess[hi] += h;
+            hess[lo] += h;
+        });
+        (
+            grad.into_iter().map(|g| g as f32).collect(),
+            Some(hess.into_iter().map(|h| h as f32).collect()),
+        )
+    }
+
+    /// Only the order of the predictions within a query matters.
+    fn calc_init(&self, _y: &[f64], _sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        0.0
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::NDCG
+    }
+}
+
+/// Negative Cox partial log likelihood with the Breslow approximation for tied times. The loss of
+/// an observed event is `log(sum_j w_j exp(yhat_j)) - yhat_i` over the records `j` still at risk at
+/// its time, censored records only contribute through the risk sets.
+pub struct CoxLoss {
+    /// Whether the event was observed for each record, every event is observed if `None`.
+    event: Option<Vec<bool>>,
+}
+
+impl CoxLoss {
+    pub fn new(event: Option<&[bool]>) -> Self {
+        CoxLoss {
+            event: event.map(|e| e.to_vec()),
+        }
+    }
+
+    #[inline]
+    fn is_event(&self, i: usize) -> bool {
+        self.event.as_ref().is_none_or(|e| e[i])
+    }
+
+    /// Indices of the records sorted by time, the largest prediction, and the risk set sum of
+    /// `w * exp(yhat - max)` for each record.
+    fn risk_sets(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>) -> (Vec<usize>, f64, Vec<f64>) {
+        let mut order: Vec<usize> = (0..y.len()).collect();
+        order.sort_by(|&a, &b| y[a].total_cmp(&y[b]));
+        let max = yhat.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mut risk = vec![0.0; y.len()];
+        let mut total = 0.0;
+        let mut end = order.len();
+        while end > 0 {
+            // Records with the same time share the same risk set.
+            let mut start = end - 1;
+            while start > 0 && y[order[start - 1]] == y[order[end - 1]] {
+                start -= 1;
+            }
+            for i in &order[start..end] {
+                total += sample_weight.map_or(1.0, |w| w[*i]) * (yhat[*i] - max).exp();
+            }
+            order[start..end].iter().for_each(|i| risk[*i] = total);
+            end = start;
+        }
+        (order, max, risk)
+    }
+}
+
+impl ObjectiveFunction for CoxLoss {
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        let (_, max, risk) = self.risk_sets(y, yhat, sample_weight);
+        (0..y.len())
+            .map(|i| {
+                if self.is_event(i) {
+                    let w_ = sample_weight.map_or(1.0, |w| w[i]);
+                    (w_ * (risk[i].ln() + max - yhat[i])) as f32
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let (order, max, risk) = self.risk_sets(y, yhat, sample_weight);
+        let mut grad = vec![0.0; y.len()];
+        let mut hess = vec![0.0; y.len()];
+        // Sums over the events at or before the time of each record, of which it is in the risk set.
+        let (mut a, mut b) = (0.0, 0.0);
+        let mut start = 0;
+        while start < order.len() {
+            let mut end = start + 1;
+            while end < order.len() && y[order[end]] == y[order[start]] {
+                end += 1;
+            }
+            for i in &order[start..end] {
+                if self.is_event(*i) {
+                    let w_ = sample_weight.map_or(1.0, |w| w[*i]);
+                    a += w_ / risk[*i];
+                    b += w_ / (risk[*i] * risk[*i]);
+                }
+            }
+            for i in &order[start..end] {
+                let w_ = sample_weight.map_or(1.0, |w| w[*i]);
+                let p = w_ * (yhat[*i] - max).exp();
+                let event = if self.is_event(*i) { w_ } else { 0.0 };
+                grad[*i] = (p * a - event) as f32;
+                hess[*i] = (p * a - p * p * b) as f32;
+            }
+            start = end;
+        }
+        (grad, Some(hess))
+    }
+
+    /// Only the differences between the predictions matter.
+    fn calc_init(&self, _y: &[f64], _sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        0.0
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::CoxNegativeLogLikelihood
+    }
+}
+
+/// Complementary error function, with a fractional error below 1.2e-7 (Numerical Recipes).
+pub(crate) fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x.abs());
+    let poly = -1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let v = t * (-x * x + poly).exp();
+    if x >= 0.0 {
+        v
+    } else {
+        2.0 - v
+    }
+}
+
+impl AFTDistribution {
+    fn pdf(&self, z: f64) -> f64 {
+        if z.is_infinite() {
+            return 0.0;
+        }
+        match self {
+            AFTDistribution::Normal => (-0.5 * z * z).exp() / (2.0 * std::f64::consts::PI).sqrt(),
+            AFTDistribution::Logistic => {
+                let e = (-z.abs()).exp();
+                e / ((1.0 + e) * (1.0 + e))
+            }
+            AFTDistribution::Extreme => (z - z.exp()).exp(),
+        }
+    }
+
+    /// Derivative of the density.
+    fn pdf_grad(&self, z: f64) -> f64 {
+        if z.is_infinite() {
+            return 0.0;
+        }
+        match self {
+            AFTDistribution::Normal => -z * self.pdf(z),
+            AFTDistribution::Logistic => -self.pdf(z) * (0.5 * z).tanh(),
+            AFTDistribution::Extreme => self.pdf(z) * (1.0 - z.exp()),
+        }
+    }
+
+    fn cdf(&self, z: f64) -> f64 {
+        match self {
+            AFTDistribution::Normal => 0.5 * erfc(-z / std::f64::consts::SQRT_2),
+            AFTDistribution::Logistic => 1.0 / (1.0 + (-z).exp()),
+            AFTDistribution::Extreme => -(-z.exp()).exp_m1(),
+        }
+    }
+
+    /// Survival function, `1 - cdf(z)` without the cancellation in the upper tail.
+    fn sf(&self, z: f64) -> f64 {
+        match self {
+            AFTDistribution::Normal => 0.5 * erfc(z / std::f64::consts::SQRT_2),
+            AFTDistribution::Logistic => 1.0 / (1.0 + z.exp()),
+            AFTDistribution::Extreme => (-z.exp()).exp(),
+        }
+    }
+
+    /// Negative log density relative to its minimum, and its first and second derivatives.
+    fn nll(&self, z: f64) -> (f64, f64, f64) {
+        match self {
+            AFTDistribution::Normal => (0.5 * z * z, z, 1.0),
+            AFTDistribution::Logistic => {
+                let s = 1.0 / (1.0 + (-z).exp());
+                (
+                    -z + 2.0 * log1p_exp(z) - 2.0 * std::f64::consts::LN_2,
+                    2.0 * s - 1.0,
+                    2.0 * s * (1.0 - s),
+                )
+            }
+            AFTDistribution::Extreme => (z.exp() - z - 1.0, z.exp() - 1.0, z.exp()),
+        }
+    }
+}
+
+/// Negative log likelihood of the accelerated failure time model `log(T) = yhat + sigma * Z`.
+/// Records with an observed event contribute the negative log density of their time, relative to
+/// its minimum, and censored records the negative log probability of their interval.
+pub struct AFTLoss {
+    pub distribution: AFTDistribution,
+    pub sigma: f64,
+    /// Upper bound of the survival time of each record, every event is observed if `None`.
+    y_upper: Option<Vec<f64>>,
+}
+
+impl AFTLoss {
+    pub fn new(distribution: AFTDistribution, sigma: f64, y_upper: Option<&[f64]>) -> Self {
+        AFTLoss {
+            distribution,
+            sigma,
+            y_upper: y_upper.map(|u| u.to_vec()),
+        }
+    }
+
+    /// Check that the survival times are positive, as their logarithm is modeled.
+    pub fn validate_labels(y: &[f64]) -> Result<(), PerpetualError> {
+        match y.iter().find(|y_| !(y_.is_finite() && **y_ > 0.0)) {
+            Some(y_) => Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                "positive survival times for AFTLoss".to_string(),
+                y_.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Loss, gradient and hessian of a single record, with survival time between `lower` and `upper`.
+    fn record_loss(&self, lower: f64, upper: f64, yhat: f64) -> (f64, f64, f64) {
+        let d = self.distribution;
+        let sigma = self.sigma;
+        if lower == upper {
+            let (l, dl, d2l) = d.nll((lower.ln() - yhat) / sigma);
+            return (l, -dl / sigma, d2l / (sigma * sigma));
+        }
+        let z_l = if lower > 0.0 {
+            (lower.ln() - yhat) / sigma
+        } else {
+            f64::NEG_INFINITY
+        };
+        let z_u = if upper.is_finite() {
+            (upper.ln() - yhat) / sigma
+        } else {
+            f64::INFINITY
+        };
+        let p = if upper.is_infinite() {
+            d.sf(z_l)
+        } else if lower <= 0.0 {
+            d.cdf(z_u)
+        } else {
+            d.cdf(z_u) - d.cdf(z_l)
+        }
+        .max(f64::MIN_POSITIVE);
+        let df = d.pdf(z_u) - d.pdf(z_l);
+        let dfp = d.pdf_grad(z_u) - d.pdf_grad(z_l);
+        let g = df / (sigma * p);
+        let h = (df * df - dfp * p) / (sigma * sigma * p * p);
+        (-p.ln(), g, h.max(f64::EPSILON))
+    }
+}
+
+impl ObjectiveFunction for AFTLoss {
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        (0..y.len())
+            .map(|i| {
+                let upper = self.y_upper.as_ref().map_or(y[i], |u| u[i]);
+                let (l, _, _) = self.record_loss(y[i], upper, yhat[i]);
+                (l * sample_weight.map_or(1.0, |w| w[i])) as f32
+            })
+            .collect()
+    }
+
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let (g, h) = (0..y.len())
+            .map(|i| {
+                let upper = self.y_upper.as_ref().map_or(y[i], |u| u[i]);
+                let (_, g_, h_) = self.record_loss(y[i], upper, yhat[i]);
+                let w_ = sample_weight.map_or(1.0, |w| w[i]);
+                ((g_ * w_) as f32, (h_ * w_) as f32)
+            })
+            .unzip();
+        (g, Some(h))
+    }
+
+    /// Mean of the log of the positive lower bounds.
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        let (total, w_total) =
+            y.iter()
+                .enumerate()
+                .filter(|(_, y_)| **y_ > 0.0)
+                .fold((0.0, 0.0), |(t, wt), (i, y_)| {
+                    let w_ = sample_weight.map_or(1.0, |w| w[i]);
+                    (t + y_.ln() * w_, wt + w_)
+                });
+        if w_total > 0.0 {
+            total / w_total
+        } else {
+            0.0
+        }
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::ConcordanceIndex
+    }
+}
+
+/// Smallest absolute residual used for the targets of the log scale trees, so that their log stays finite.
+const MIN_ABS_RESIDUAL: f64 = 1e-150;
+
+/// Distributional regression of a Normal or LogNormal target. The negative log likelihood of a record,
+/// as a function of the location with the log scale `s` held fixed, is the squared error weighted by
+/// `exp(-2 s)`, up to a constant. As a function of the log scale with the location held fixed, it is
+/// `LogScaleLoss` of the log of the absolute residual. So every boosting round fits one tree per
+/// parameter, at the parameters from the start of the round.
+pub struct DistributionalLoss {
+    pub distribution: Distribution,
+}
+
+impl DistributionalLoss {
+    pub fn new(distribution: Distribution) -> Self {
+        DistributionalLoss { distribution }
+    }
+
+    /// The target on the scale of the Normal distribution, its log for LogNormal.
+    pub fn transform(&self, y: &[f64]) -> Vec<f64> {
+        match self.distribution {
+            Distribution::Normal => y.to_vec(),
+            Distribution::LogNormal => y.iter().map(|y_| y_.ln()).collect(),
+        }
+    }
+
+    /// Check that the target is in the support of the distribution.
+    pub fn validate_labels(&self, y: &[f64]) -> Result<(), PerpetualError> {
+        let invalid = match self.distribution {
+            Distribution::Normal => y.iter().find(|y_| !y_.is_finite()),
+            Distribution::LogNormal => y.iter().find(|y_| !(y_.is_finite() && **y_ > 0.0)),
+        };
+        match invalid {
+            Some(y_) => Err(PerpetualError::InvalidParameter(
+                "y".to_string(),
+                format!("values in the support of the {:?} distribution", self.distribution),
+                y_.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Initial location and log scale, the weighted mean and the log of the weighted standard deviation
+    /// of the transformed target `z`.
+    pub fn calc_init_params(&self, z: &[f64], sample_weight: Option<&[f64]>) -> (f64, f64) {
+        let loc = SquaredLoss::default().calc_init(z, sample_weight, None);
+        let residuals: Vec<f64> = z.iter().map(|z_| (z_ - loc).abs().max(MIN_ABS_RESIDUAL).ln()).collect();
+        (loc, LogScaleLoss::default().calc_init(&residuals, sample_weight, None))
+    }
+
+    /// Sample weights of the location trees, `w * exp(-2 s)`.
+    pub fn location_weights(log_scale: &[f64], sample_weight: Option<&[f64]>) -> Vec<f64> {
+        log_scale
+            .iter()
+            .enumerate()
+            .map(|(i, s)| sample_weight.map_or(1.0, |w| w[i]) * (-2.0 * s).exp())
+            .collect()
+    }
+
+    /// Targets of the log scale trees, the log of the absolute residuals of the location.
+    pub fn scale_targets(z: &[f64], loc: &[f64]) -> Vec<f64> {
+        z.iter()
+            .zip(loc)
+            .map(|(z_, loc_)| (z_ - loc_).abs().max(MIN_ABS_RESIDUAL).ln())
+            .collect()
+    }
+
+    /// Negative log likelihood of each record, with the target `y` on its original scale.
+    pub fn negative_log_likelihood(
+        &self,
+        y: &[f64],
+        loc: &[f64],
+        log_scale: &[f64],
+        sample_weight: Option<&[f64]>,
+    ) -> Vec<f64> {
+        let z = self.transform(y);
+        (0..y.len())
+            .map(|i| {
+                let r = (z[i] - loc[i]) * (-log_scale[i]).exp();
+                let mut l = log_scale[i] + 0.5 * r * r + 0.5 * (2.0 * std::f64::consts::PI).ln();
+                if let Distribution::LogNormal = self.distribution {
+                    l += z[i];
+                }
+                l * sample_weight.map_or(1.0, |w| w[i])
+            })
+            .collect()
+    }
+}
+
+/// Twice the negative log likelihood of a Normal log scale `yhat`, in excess of its minimum, for a record
+/// whose log absolute residual is `y`: `2 u + exp(-2 u) - 1` with `u = yhat - y`. As with SquaredLoss, the
+/// gradient and the hessian are those of half the loss, and the hessian is its expected value (the Fisher
+/// information), so that the leaf values are natural gradient steps.
+#[derive(Default)]
+pub struct LogScaleLoss {}
+
+impl ObjectiveFunction for LogScaleLoss {
+    #[inline]
+    fn calc_loss(&self, y: &[f64], yhat: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> Vec<f32> {
+        y.iter()
+            .zip(yhat)
+            .enumerate()
+            .map(|(i, (y_, yhat_))| {
+                let u = yhat_ - y_;
+                ((2.0 * u + (-2.0 * u).exp() - 1.0) * sample_weight.map_or(1.0, |w| w[i])) as f32
+            })
+            .collect()
+    }
+
+    /// The log of the weighted root mean square of the residuals.
+    fn calc_init(&self, y: &[f64], sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+        let (total, w_total) = y.iter().enumerate().fold((0.0, 0.0), |(t, wt), (i, y_)| {
+            let w = sample_weight.map_or(1.0, |w| w[i]);
+            (t + (2.0 * y_).exp() * w, wt + w)
+        });
+        0.5 * (total / w_total).ln()
+    }
+
+    #[inline]
+    fn calc_grad_hess(
+        &self,
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        _quantile: Option<f64>,
+    ) -> (Vec<f32>, Option<Vec<f32>>) {
+        let (g, h) = y
+            .iter()
+            .zip(yhat)
+            .enumerate()
+            .map(|(i, (y_, yhat_))| {
+                let w = sample_weight.map_or(1.0, |w| w[i]);
+                (((1.0 - (-2.0 * (yhat_ - y_)).exp()) * w) as f32, (2.0 * w) as f32)
+            })
+            .unzip();
+        (g, Some(h))
+    }
+
+    fn default_metric(&self) -> Metric {
+        Metric::RootMeanSquaredError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_logloss_loss() {
+        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let yhat1 = vec![-1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+        let l1 = LogLoss::default().calc_loss(&y, &yhat1, None, None);
+        let yhat2 = vec![0.0, 0.0, -1.0, 1.0, 0.0, 1.0];
+        let l2 = LogLoss::default().calc_loss(&y, &yhat2, None, None);
+        assert!(l1.iter().sum::<f32>() < l2.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_logloss_grad() {
+        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let yhat1 = vec![-1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+        let (g1, _) = LogLoss::default().calc_grad_hess(&y, &yhat1, None, None);
+        let yhat2 = vec![0.0, 0.0, -1.0, 1.0, 0.0, 1.0];
+        let (g2, _) = LogLoss::default().calc_grad_hess(&y, &yhat2, None, None);
+        assert!(g1.iter().sum::<f32>() < g2.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_logloss_init() {
+        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let l1 = LogLoss::default().calc_init(&y, None, None);
+        assert!(l1 == 0.);
+
+        let y = vec![1.0; 6];
+        let l2 = LogLoss::default().calc_init(&y, None, None);
+        assert!(l2 == f64::INFINITY);
+
+        let y = vec![0.0; 6];
+        let l3 = LogLoss::default().calc_init(&y, None, None);
+        assert!(l3 == f64::NEG_INFINITY);
+
+        let y = vec![0., 0., 0., 0., 1., 1.];
+        let l4 = LogLoss::default().calc_init(&y, None, None);
+        assert!(l4 == f64::ln(2. / 4.));
+    }
+
+    #[test]
+    fn test_mse_init() {
+        let y = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let l1 = SquaredLoss::default().calc_init(&y, None, None);
+        assert!(l1 == 0.5);
+
+        let y = vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let l2 = SquaredLoss::default().calc_init(&y, None, None);
+        assert!(l2 == 1.);
+
+        let y = vec![-1.0, -1.0, -1.0, -1.0, -1.0, -1.0];
+        let l3 = SquaredLoss::default().calc_init(&y, None, None);
+        assert!(l3 == -1.);
+
+        let y = vec![-1.0, -1.0, -1.0, 1., 1., 1.];
+        let l4 = SquaredLoss::default().calc_init(&y, None, None);
+        assert!(l4 == 0.);
+    }
+
+    #[test]
+    fn test_quantile_init() {
+        let y = vec![1.0, 2.0, 9.0, 3.2, 4.0];
+        let w = vec![0.0, 0.5, 1.0, 0.3, 0.5];
+        let l1 = QuantileLoss::default().calc_init(&y, Some(&w), Some(0.1));
+        println!("{}", l1);
+        assert!(l1 == 2.0);
+
+        let y = vec![1.0, 2.0, 9.0, 3.2, 4.0];
+        let w = vec![0.0, 0.5, 1.0, 0.3, 0.5];
+        let l2 = QuantileLoss::default().calc_init(&y, Some(&w), Some(0.9));
+        println!("{}", l2);
+        assert!(l2 == 9.0);
+    }
+
+    struct HalfSquaredLoss {}
+
+    impl ObjectiveFunction for HalfSquaredLoss {
+        fn calc_loss(
+            &self,
+            y: &[f64],
+            yhat: &[f64],
+            _sample_weight: Option<&[f64]>,
+            _quantile: Option<f64>,
+        ) -> Vec<f32> {
+            y.iter()
+                .zip(yhat)
+                .map(|(y_, yhat_)| (0.5 * (y_ - yhat_).powi(2)) as f32)
+                .collect()
+        }
+        fn calc_grad_hess(
+            &self,
+            y: &[f64],
+            yhat: &[f64],
+            _sample_weight: Option<&[f64]>,
+            _quantile: Option<f64>,
+        ) -> (Vec<f32>, Option<Vec<f32>>) {
+            (
+                y.iter().zip(yhat).map(|(y_, yhat_)| (yhat_ - y_) as f32).collect(),
+                None,
+            )
+        }
+        fn calc_init(&self, y: &[f64], _sample_weight: Option<&[f64]>, _quantile: Option<f64>) -> f64 {
+            fast_sum(y) / y.len() as f64
+        }
+        fn default_metric(&self) -> Metric {
+            Metric::RootMeanSquaredError
+        }
+        fn name(&self) -> String {
+            "HalfSquaredLoss".to_string()
+        }
+    }
+
+    #[test]
+    fn test_custom_callables() {
+        let objective = Objective::custom(HalfSquaredLoss {});
+        assert!(objective.validate().is_ok());
+
+        let y = vec![0.0, 1.0, 2.0, 3.0];
+        let yhat = vec![1.0, 1.0, 1.0, 1.0];
+        let l = loss_callables(&objective).unwrap()(&y, &yhat, None, None);
+        assert_eq!(l, vec![0.5, 0.0, 0.5, 2.0]);
+        let (g, h) = gradient_hessian_callables(&objective).unwrap()(&y, &yhat, None, None);
+        assert_eq!(g, vec![1.0, 0.0, -1.0, -2.0]);
+        assert!(h.is_none());
+        assert_eq!(calc_init_callables(&objective).unwrap()(&y, None, None), 1.5);
+    }
+
+    #[test]
+    fn test_distributional_callables() {
+        // Distributional has a loss for each of its parameters, and none for a single output.
+        let objective = Objective::Distributional {
+            distribution: Distribution::Normal,
+        };
+        assert!(loss_callables(&objective).is_err());
+        assert!(gradient_hessian_callables(&objective).is_err());
+        assert!(calc_init_callables(&objective).is_err());
+    }
+
+    #[test]
+    fn test_custom_serialization() {
+        let objective = Objective::custom(HalfSquaredLoss {});
+        let s = serde_json::to_string(&objective).unwrap();
+        assert_eq!(s, r#"{"Custom":{"name":"HalfSquaredLoss"}}"#);
+
+        let loaded: Objective = serde_json::from_str(&s).unwrap();
+        match loaded.validate() {
+            Err(PerpetualError::UnregisteredObjective(name)) => assert_eq!(name, "HalfSquaredLoss"),
+            _ => panic!("Loaded custom objective should not be registered."),
+        }
+    }
+
+    #[test]
+    fn test_softmax_offsets() {
+        let yhat = vec![vec![0.0, 1.0], vec![1.0, -2.0], vec![-1.0, 3.0]];
+        let offsets = Softmax::offsets(&yhat);
+        for i in 0..2 {
+            let total: f64 = yhat.iter().map(|v| v[i].exp()).sum();
+            for k in 0..3 {
+                // The logistic function of the offset margin is the softmax probability of the class.
+                let p = 1.0 / (1.0 + (-(yhat[k][i] - offsets[k][i])).exp());
+                assert!((p - yhat[k][i].exp() / total).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_softmax_loss() {
+        let y_data = vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let y = Matrix::new(&y_data, 2, 3);
+        let yhat = vec![vec![0.0, 1.0], vec![1.0, -2.0], vec![-1.0, 3.0]];
+        let offsets = Softmax::offsets(&yhat);
+        let joint = Softmax::joint_loss(&y, &yhat, None);
+
+        // The class loss on the offset margins differs from the joint loss only by a constant,
+        // so a change of one class margin changes both in the same way.
+        let k = 1;
+        let margin: Vec<f64> = yhat[k].iter().zip(&offsets[k]).map(|(z, o)| z - o).collect();
+        let shifted: Vec<f64> = margin.iter().map(|m| m + 0.5).collect();
+        let class_decr: Vec<f32> = Softmax::default()
+            .calc_loss(y.get_col(k), &margin, None, None)
+            .iter()
+            .zip(Softmax::default().calc_loss(y.get_col(k), &shifted, None, None))
+            .map(|(a, b)| a - b)
+            .collect();
+        let mut yhat_shifted = yhat.clone();
+        yhat_shifted[k].iter_mut().for_each(|z| *z += 0.5);
+        let joint_shifted = Softmax::joint_loss(&y, &yhat_shifted, None);
+        for i in 0..2 {
+            assert!((class_decr[i] - (joint[i] - joint_shifted[i])).abs() < 1e-5);
+        }
+
+        let init = Softmax::default().calc_init(&[0.0, 0.0, 1.0, 1.0], None, None);
+        assert_eq!(init, f64::ln(0.5));
+    }
+
+    #[test]
+    fn test_log_link_grad_hess() {
+        let y = vec![0.0, 1.0, 3.0, 0.5];
+        let yhat = vec![-0.5, 0.2, 1.0, 0.0];
+        let y_pos = vec![0.2, 1.0, 3.0, 0.5];
+        let eps = 1e-2;
+        let objectives: Vec<(Box<dyn ObjectiveFunction>, &Vec<f64>)> = vec![
+            (Box::new(PoissonLoss::default()), &y),
+            (Box::new(GammaLoss::default()), &y_pos),
+            (Box::new(TweedieLoss::new(1.3)), &y),
+        ];
+        for (objective, y) in objectives {
+            let (g, h) = objective.calc_grad_hess(y, &yhat, None, None);
+            let h = h.unwrap();
+            let loss = |d: f64| {
+                let yhat_d: Vec<f64> = yhat.iter().map(|v| v + d).collect();
+                objective.calc_loss(y, &yhat_d, None, None)
+            };
+            let (l_lo, l_0, l_hi) = (loss(-eps), loss(0.0), loss(eps));
+            for i in 0..y.len() {
+                // The loss is the deviance, twice the negative log likelihood up to a constant.
+                let g_num = (l_hi[i] as f64 - l_lo[i] as f64) / (4.0 * eps);
+                let h_num = (l_hi[i] as f64 - 2.0 * l_0[i] as f64 + l_lo[i] as f64) / (2.0 * eps * eps);
+                assert!((g[i] as f64 - g_num).abs() < 1e-2);
+                assert!((h[i] as f64 - h_num).abs() < 0.1 * f64::max(1.0, h[i] as f64));
+                assert!(l_0[i] >= 0.0);
+            }
+        }
+
+        assert_eq!(PoissonLoss::default().calc_init(&[0.0, 1.0, 2.0, 1.0], None, None), 0.0);
+        assert!(Objective::TweedieLoss { variance_power: 1.0 }.validate().is_err());
+        assert!(Objective::TweedieLoss { variance_power: 1.5 }.validate().is_ok());
+
+        assert!(PoissonLoss::validate_labels(&[0.0, 1.0, 2.0]).is_ok());
+        assert!(PoissonLoss::validate_labels(&[1.0, -1.0]).is_err());
+        assert!(GammaLoss::validate_labels(&[0.5, 2.0]).is_ok());
+        assert!(GammaLoss::validate_labels(&[0.5, 0.0]).is_err());
+        assert!(TweedieLoss::validate_labels(&[0.0, 2.0]).is_ok());
+        assert!(TweedieLoss::validate_labels(&[0.0, f64::NAN]).is_err());
+    }
+
+    #[test]
+    fn test_robust_objectives() {
+        let y = vec![0.0, 1.0, 2.0, 3.0, 100.0];
+        let yhat = vec![1.0; 5];
+
+        let (g, h) = AbsoluteLoss::default().calc_grad_hess(&y, &yhat, None, None);
+        assert_eq!(g, vec![1.0, 0.0, -1.0, -1.0, -1.0]);
+        assert!(h.is_none());
+        assert_eq!(AbsoluteLoss::default().calc_init(&y, None, None), 2.0);
+        assert_eq!(AbsoluteLoss::default().calc_leaf_value(&y, &yhat, None), 1.0);
+
+        let huber = HuberLoss::new(1.5);
+        let (g, h) = huber.calc_grad_hess(&y, &yhat, None, None);
+        assert_eq!(g, vec![1.0, 0.0, -1.0, -1.5, -1.5]);
+        assert!(h.is_none());
+        assert_eq!(
+            huber.calc_loss(&y, &yhat, None, None),
+            vec![0.5, 0.0, 0.5, 1.875, 147.375]
+        );
+        // Median residual of 1.0, plus the mean of the deviations clipped at 1.5.
+        assert_eq!(
+            huber.calc_leaf_value(&y, &yhat, None),
+            1.0 + (-1.5 - 1.0 + 0.0 + 1.0 + 1.5) / 5.0
+        );
+
+        let pseudo_huber = PseudoHuberLoss::new(1.0);
+        let (g, h) = pseudo_huber.calc_grad_hess(&y, &yhat, None, None);
+        let h = h.unwrap();
+        assert!(h.iter().all(|v| *v > 0.0 && *v <= 1.0));
+        assert!(g[4] > -1.0 && g[4] < -0.99);
+        assert!(h[4] < h[3] && h[3] < h[1]);
+
+        assert!(leaf_value_callables(&Objective::HuberLoss { delta: 1.0 }).is_some());
+        // The leaf value is between the median residual and the mean residual.
+        let leaf_value = pseudo_huber.calc_leaf_value(&y, &yhat, None);
+        assert!(leaf_value > 1.0 && leaf_value < 20.2);
+        let (g, _) = pseudo_huber.calc_grad_hess(&y, &[1.0 + leaf_value; 5], None, None);
+        assert!(g.iter().sum::<f32>().abs() < 1e-4);
+        assert!(leaf_value_callables(&Objective::SquaredLoss).is_none());
+        assert!(Objective::HuberLoss { delta: 0.0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_rank_loss() {
+        let y = vec![2.0, 0.0, 1.0, 0.0, 1.0, 1.0];
+        let yhat = vec![0.1, 0.5, -0.3, 0.2, 0.0, 0.7];
+        let group = [1, 1, 1, 2, 2, 2];
+        let eps = 1e-3;
+        for ndcg in [false, true] {
+            let objective = RankLoss::new(ndcg, Some(&group));
+            let (g, h) = objective.calc_grad_hess(&y, &yhat, None, None);
+            let h = h.unwrap();
+            let total_loss = |i: usize, d: f64| {
+                let mut yhat_d = yhat.clone();
+                yhat_d[i] += d;
+                objective
+                    .calc_loss(&y, &yhat_d, None, None)
+                    .iter()
+                    .map(|l| *l as f64)
+                    .sum::<f64>()
+            };
+            for i in 0..y.len() {
+                let g_num = (total_loss(i, eps) - total_loss(i, -eps)) / (2.0 * eps);
+                assert!((g[i] as f64 - g_num).abs() < 1e-3);
+                assert!(h[i] > 0.0);
+            }
+            // The gradients of the records of a query sum to zero.
+            assert!(g[..3].iter().sum::<f32>().abs() < 1e-6);
+            assert!(g[3..].iter().sum::<f32>().abs() < 1e-6);
+        }
+
+        // Records with the same relevance as every other record of their query don't contribute.
+        let (g, _) =
+            RankLoss::new(false, Some(&[1, 1, 2])).calc_grad_hess(&[1.0, 1.0, 0.0], &[0.3, 0.1, 0.0], None, None);
+        assert_eq!(g, vec![0.0, 0.0, 0.0]);
+        assert!(Objective::LambdaRankLoss.is_ranking());
+    }
+
+    #[test]
+    fn test_survival_objectives() {
+        let y = vec![2.0, 1.0, 3.0, 2.0, 5.0, 0.5];
+        let yhat = vec![0.3, -0.2, 0.1, 0.5, -0.4, 0.2];
+        let w = vec![1.0, 2.0, 1.0, 0.5, 1.0, 1.0];
+        let eps = 1e-4;
+
+        let event = [true, true, false, true, false, true];
+        let cox = CoxLoss::new(Some(&event));
+        let (g, h) = cox.calc_grad_hess(&y, &yhat, Some(&w), None);
+        let h = h.unwrap();
+        let total_loss = |i: usize, d: f64| {
+            let mut yhat_d = yhat.clone();
+            yhat_d[i] += d;
+            cox.calc_loss(&y, &yhat_d, Some(&w), None)
+                .iter()
+                .map(|l| *l as f64)
+                .sum::<f64>()
+        };
+        for i in 0..y.len() {
+            let g_num = (total_loss(i, eps) - total_loss(i, -eps)) / (2.0 * eps);
+            assert!((g[i] as f64 - g_num).abs() < 1e-3);
+            assert!(h[i] > 0.0);
+        }
+        assert!(g.iter().sum::<f32>().abs() < 1e-5);
+
+        // Observed, right censored, interval censored and left censored records.
+        let y_upper = vec![2.0, 1.0, f64::INFINITY, 4.0, f64::INFINITY, 0.5];
+        let y_lower = vec![2.0, 1.0, 3.0, 2.0, 5.0, 0.0];
+        for distribution in [
+            AFTDistribution::Normal,
+            AFTDistribution::Logistic,
+            AFTDistribution::Extreme,
+        ] {
+            let aft = AFTLoss::new(distribution, 0.8, Some(&y_upper));
+            let (g, h) = aft.calc_grad_hess(&y_lower, &yhat, Some(&w), None);
+            let h = h.unwrap();
+            let loss = |d: f64| {
+                let yhat_d: Vec<f64> = yhat.iter().map(|v| v + d).collect();
+                aft.calc_loss(&y_lower, &yhat_d, Some(&w), None)
+            };
+            let (l_lo, l_0, l_hi) = (loss(-eps), loss(0.0), loss(eps));
+            for i in 0..y.len() {
+                let g_num = (l_hi[i] as f64 - l_lo[i] as f64) / (2.0 * eps);
+                assert!((g[i] as f64 - g_num).abs() < 1e-2);
+                assert!(h[i] > 0.0);
+                assert!(l_0[i] >= 0.0);
+            }
+        }
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6 && (erfc(1.0) - 0.157299207).abs() < 1e-7);
+        assert!(Objective::AFTLoss {
+            distribution: AFTDistribution::Normal,
+            sigma: 0.0
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_focal_loss() {
+        let y = vec![1.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+        let yhat = vec![0.3, -0.2, 2.1, 0.5, -3.4, -1.2];
+        let w = vec![1.0, 2.0, 1.0, 0.5, 1.0, 1.0];
+        let eps = 1e-4;
+
+        // Without focusing, focal loss is the class weighted log loss.
+        let focal = FocalLoss::new(0.0, 0.5);
+        let l_focal = focal.calc_loss(&y, &yhat, Some(&w), None);
+        let l_log = LogLoss::default().calc_loss(&y, &yhat, Some(&w), None);
+        for (a, b) in l_focal.iter().zip(&l_log) {
+            assert!((2.0 * a - b).abs() < 1e-6);
+        }
+
+        let focal = FocalLoss::new(2.0, 0.25);
+        let (g, h) = focal.calc_grad_hess(&y, &yhat, Some(&w), None);
+        let h = h.unwrap();
+        let loss = |d: f64| {
+            let yhat_d: Vec<f64> = yhat.iter().map(|v| v + d).collect();
+            focal.calc_loss(&y, &yhat_d, Some(&w), None)
+        };
+        let (l_lo, l_hi) = (loss(-eps), loss(eps));
+        for i in 0..y.len() {
+            let g_num = (l_hi[i] as f64 - l_lo[i] as f64) / (2.0 * eps);
+            assert!((g[i] as f64 - g_num).abs() < 1e-3);
+            assert!(h[i] > 0.0);
+        }
+
+        // The base score minimizes the loss of a constant prediction.
+        let init = focal.calc_init(&y, Some(&w), None);
+        let (g, _) = focal.calc_grad_hess(&y, &vec![init; y.len()], Some(&w), None);
+        assert!(g.iter().sum::<f32>().abs() < 1e-5);
+
+        assert!(Objective::FocalLoss {
+            gamma: -1.0,
+            alpha: 0.25
+        }
+        .validate()
+        .is_err());
+        assert!(Objective::FocalLoss { gamma: 2.0, alpha: 1.0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let y = vec![0.2, 0.0, 1.0, 0.7, 0.5];
+        let w = vec![1.0, 2.0, 1.0, 0.5, 1.0];
+        let ce = CrossEntropy::default();
+
+        // The loss is zero when the predicted probability is the label.
+        let yhat: Vec<f64> = [0.2_f64, 1e-6, 1.0 - 1e-6, 0.7, 0.5]
+            .iter()
+            .map(|p| (p / (1.0 - p)).ln())
+            .collect();
+        assert!(ce.calc_loss(&y, &yhat, Some(&w), None).iter().all(|l| l.abs() < 1e-5));
+        let l = ce.calc_loss(&y, &[0.0; 5], None, None);
+        assert!(l.iter().all(|l| *l >= 0.0) && l[4] == 0.0);
+
+        let yhat = vec![0.3, -0.2, 2.1, 0.5, -3.4];
+        let (g, h) = ce.calc_grad_hess(&y, &yhat, Some(&w), None);
+        let (g_log, h_log) = LogLoss::default().calc_grad_hess(&y, &yhat, Some(&w), None);
+        assert_eq!(g, g_log);
+        assert_eq!(h, h_log);
+
+        let init = ce.calc_init(&y, Some(&w), None);
+        let mean: f64 = (0.2 + 1.0 + 0.35 + 0.5) / 5.5;
+        assert!((init - (mean / (1.0 - mean)).ln()).abs() < 1e-12);
+        assert!(ce.calc_init(&[1.0, 1.0], None, None).is_finite());
+
+        assert!(CrossEntropy::validate_labels(&y).is_ok());
+        assert!(CrossEntropy::validate_labels(&[0.5, 1.5]).is_err());
+        assert!(CrossEntropy::validate_labels(&[0.5, f64::NAN]).is_err());
+    }
+
+    #[test]
+    fn test_distributional_loss() {
+        let y = vec![1.2, 0.4, 2.5, 0.9];
+        let w = vec![1.0, 2.0, 1.0, 0.5];
+        let eps = 1e-4;
+
+        // Half the log scale loss has the gradient of the negative log likelihood.
+        let targets = vec![0.1, -0.5, 0.3, -2.0];
+        let yhat = vec![0.2, 0.1, -0.4, -1.0];
+        let f = LogScaleLoss::default();
+        let (g, h) = f.calc_grad_hess(&targets, &yhat, Some(&w), None);
+        let loss = |d: f64| {
+            let yhat_d: Vec<f64> = yhat.iter().map(|v| v + d).collect();
+            f.calc_loss(&targets, &yhat_d, Some(&w), None)
+        };
+        let (l_lo, l_0, l_hi) = (loss(-eps), loss(0.0), loss(eps));
+        for i in 0..y.len() {
+            let g_num = 0.5 * (l_hi[i] as f64 - l_lo[i] as f64) / (2.0 * eps);
+            assert!((g[i] as f64 - g_num).abs() < 1e-3);
+            assert_eq!(h.as_ref().unwrap()[i] as f64, 2.0 * w[i]);
+            assert!(l_0[i] >= 0.0);
+        }
+        assert_eq!(f.calc_loss(&[0.3], &[0.3], None, None), vec![0.0]);
+
+        // With the other parameter fixed, each parameter tree sees the negative log likelihood.
+        let d = DistributionalLoss::new(Distribution::LogNormal);
+        let z = d.transform(&y);
+        let loc = vec![0.1, -0.2, 0.5, 0.0];
+        let log_scale = vec![-0.3, 0.2, 0.0, -1.0];
+        let nll = |loc: &[f64], log_scale: &[f64]| d.negative_log_likelihood(&y, loc, log_scale, Some(&w));
+        let weights = DistributionalLoss::location_weights(&log_scale, Some(&w));
+        let squared = SquaredLoss::default();
+        let loc_d: Vec<f64> = loc.iter().map(|v| v + 0.1).collect();
+        let nll_change: Vec<f64> = nll(&loc_d, &log_scale)
+            .iter()
+            .zip(nll(&loc, &log_scale))
+            .map(|(a, b)| a - b)
+            .collect();
+        let loss_change: Vec<f32> = squared
+            .calc_loss(&z, &loc_d, Some(&weights), None)
+            .iter()
+            .zip(squared.calc_loss(&z, &loc, Some(&weights), None))
+            .map(|(a, b)| a - b)
+            .collect();
+        for (n, l) in nll_change.iter().zip(&loss_change) {
+            assert!((2.0 * n - *l as f64).abs() < 1e-5);
+        }
+        let targets = DistributionalLoss::scale_targets(&z, &loc);
+        let log_scale_d: Vec<f64> = log_scale.iter().map(|v| v + 0.1).collect();
+        let scale_change: Vec<f32> = f
+            .calc_loss(&targets, &log_scale_d, Some(&w), None)
+            .iter()
+            .zip(f.calc_loss(&targets, &log_scale, Some(&w), None))
+            .map(|(a, b)| a - b)
+            .collect();
+        for ((a, b), l) in nll(&loc, &log_scale_d)
+            .iter()
+            .zip(nll(&loc, &log_scale))
+            .zip(&scale_change)
+        {
+            assert!((2.0 * (a - b) - *l as f64).abs() < 1e-5);
+        }
+
+        let (loc_init, log_scale_init) = DistributionalLoss::new(Distribution::Normal).calc_init_params(&y, None);
+        assert!((loc_init - 1.25).abs() < 1e-12);
+        assert!((log_scale_init - 0.5 * (2.41 / 4.0_f64).ln()).abs() < 1e-12);
+        assert!(d.validate_labels(&[1.0, 0.0]).is_err());
+        assert!(DistributionalLoss::new(Distribution::Normal)
+            .validate_labels(&[1.0, -2.0])
+            .is_ok());
     }
 }
@@ -75,11 +75,11 @@ pub fn tree_partial_dependence(
 mod tests {
 
     use super::*;
-    use crate::binning::bin_matrix;
+    use crate::binning::{bin_matrix, BinnedMatrix};
     use crate::constraints::ConstraintMap;
     use crate::data::Matrix;
     use crate::histogram::{NodeHistogram, NodeHistogramOwned};
-    use crate::objective::{LogLoss, ObjectiveFunction};
+    use crate::objective::{loss_callables, LogLoss, Objective, ObjectiveFunction};
     use crate::splitter::{MissingImputerSplitter, SplitInfo, SplitInfoSlice};
     use crate::tree::Tree;
     use std::fs;
@@ -94,8 +94,8 @@ mod tests {
         let file = fs::read_to_string("resources/performance.csv").expect("Something went wrong reading the file");
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap()).collect();
         let yhat = vec![0.5; y.len()];
-        let (mut g, mut h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
-        let loss = LogLoss::calc_loss(&y, &yhat, None, None);
+        let (mut g, mut h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
+        let loss = LogLoss::default().calc_loss(&y, &yhat, None, None);
 
         let data = Matrix::new(&data_vec, 891, 5);
         let splitter = MissingImputerSplitter::new(0.3, true, ConstraintMap::new());
@@ -122,9 +122,10 @@ mod tests {
         let split_info_slice = SplitInfoSlice::new(&mut split_info_vec);
 
         tree.fit(
-            &bdata,
+            &mut BinnedMatrix::Dense(&bdata),
             data.index.to_owned(),
             &col_index,
+            None,
             &mut g,
             h.as_deref_mut(),
             &splitter,
@@ -132,7 +133,7 @@ mod tests {
             Some(f32::MAX),
             &loss,
             &y,
-            LogLoss::calc_loss,
+            &loss_callables(&Objective::LogLoss).unwrap(),
             &yhat,
             None,
             None,
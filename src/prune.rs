@@ -1,9 +1,10 @@
 use crate::{
+    data::DataMatrix,
     errors::PerpetualError,
     node::{Node, NodeType},
-    objective::{calc_init_callables, loss_callables},
+    objective::{calc_init_callables, loss_callables, LossFn},
     tree::Tree,
-    Matrix, PerpetualBooster,
+    PerpetualBooster,
 };
 use std::collections::HashMap;
 
@@ -13,27 +14,39 @@ impl PerpetualBooster {
     /// * `data` -  Either a pandas DataFrame, or a 2 dimensional numpy array.
     /// * `y` - Either a pandas Series, or a 1 dimensional numpy array.
     /// * `sample_weight` - Instance weights to use when training the model.
-    pub fn prune(
+    pub fn prune<D: DataMatrix>(
         &mut self,
-        data: &Matrix<f64>,
+        data: &D,
         y: &[f64],
         sample_weight: Option<&[f64]>,
     ) -> Result<(), PerpetualError> {
-        let calc_loss = loss_callables(&self.objective);
+        self.objective.validate()?;
+        // Pruning compares the loss of the records of each node on their own,
+        // which is not defined for the ranking and Cox objectives.
+        if !self.objective.has_record_loss() {
+            return Err(PerpetualError::InvalidParameter(
+                "objective".to_string(),
+                "an objective with a loss for each record".to_string(),
+                format!("{:?}", self.objective),
+            ));
+        }
+
+        let calc_loss = loss_callables(&self.objective)?;
 
         let old_length = self.trees.len();
         let old_n_nodes: usize = self.trees.iter().map(|t| t.nodes.len()).sum();
 
-        let base_score = calc_init_callables(&self.objective)(y, sample_weight, self.quantile);
+        let base_score = calc_init_callables(&self.objective)?(y, sample_weight, self.quantile);
         let yhat = vec![base_score; y.len()];
         let init_losses = calc_loss(y, &yhat, sample_weight, self.quantile);
         let init_loss = init_losses.iter().sum::<f32>() / init_losses.len() as f32;
 
+        let missing = data.missing_as(&self.missing);
         self.trees.iter_mut().for_each(|t| {
             t.prune_bottom_up(
                 data,
-                &self.missing,
-                calc_loss,
+                &missing,
+                &calc_loss,
                 init_loss,
                 y,
                 sample_weight,
@@ -56,14 +69,12 @@ impl PerpetualBooster {
     }
 }
 
-type LossFn = fn(&[f64], &[f64], Option<&[f64]>, Option<f64>) -> Vec<f32>;
-
 impl Tree {
-    pub fn prune(
+    pub fn prune<D: DataMatrix>(
         &mut self,
-        data: &Matrix<f64>,
+        data: &D,
         missing: &f64,
-        calc_loss: LossFn,
+        calc_loss: &LossFn,
         init_loss: f32,
         y: &[f64],
         sample_weight: Option<&[f64]>,
@@ -77,7 +88,7 @@ impl Tree {
 
         match sample_weight {
             None => {
-                data.index.iter().for_each(|i| {
+                data.index().iter().for_each(|i| {
                     self.predict_loss(
                         data,
                         *i,
@@ -92,7 +103,7 @@ impl Tree {
                 });
             }
             Some(sw) => {
-                data.index.iter().for_each(|i| {
+                data.index().iter().for_each(|i| {
                     self.predict_loss(
                         data,
                         *i,
@@ -135,12 +146,12 @@ impl Tree {
         println!("Pruned nodes: {} -> {}", old_length, new_length);
     }
 
-    pub fn predict_loss(
+    pub fn predict_loss<D: DataMatrix>(
         &self,
-        data: &Matrix<f64>,
+        data: &D,
         row: usize,
         missing: &f64,
-        calc_loss: LossFn,
+        calc_loss: &LossFn,
         base_score: f64,
         quantile: Option<f64>,
         node_losses: &mut HashMap<usize, Vec<f32>>,
@@ -159,18 +170,18 @@ impl Tree {
             let nl = node_losses.get_mut(&node_idx).unwrap();
             nl.push(loss);
             if !node.is_leaf {
-                node_idx = node.get_child_idx(data.get(row, node.split_feature), missing);
+                node_idx = node.get_child_idx(&data.get_f64(row, node.split_feature), missing);
             } else {
                 break;
             }
         }
     }
 
-    pub fn prune_bottom_up(
+    pub fn prune_bottom_up<D: DataMatrix>(
         &mut self,
-        data: &Matrix<f64>,
+        data: &D,
         missing: &f64,
-        calc_loss: LossFn,
+        calc_loss: &LossFn,
         init_loss: f32,
         y: &[f64],
         sample_weight: Option<&[f64]>,
@@ -195,7 +206,7 @@ impl Tree {
 
         match sample_weight {
             None => {
-                data.index.iter().for_each(|i| {
+                data.index().iter().for_each(|i| {
                     let i_ = *i;
                     let (pred, node_idx) = self.predict_row_and_node_idx(data, *i, missing);
                     let loss = calc_loss(&[y[i_]], &[pred + base_score], None, quantile)[0];
@@ -204,7 +215,7 @@ impl Tree {
                 });
             }
             Some(sw) => {
-                data.index.iter().for_each(|i| {
+                data.index().iter().for_each(|i| {
                     let i_ = *i;
                     let (pred, node_idx) = self.predict_row_and_node_idx(data, *i, missing);
                     let loss = calc_loss(&[y[i_]], &[pred + base_score], Some(&[sw[i_]]), quantile)[0];
@@ -259,14 +270,14 @@ impl Tree {
         println!("Pruned nodes: {} -> {}", old_length, new_length);
     }
 
-    pub fn predict_row_and_node_idx(&self, data: &Matrix<f64>, row: usize, missing: &f64) -> (f64, usize) {
+    pub fn predict_row_and_node_idx<D: DataMatrix>(&self, data: &D, row: usize, missing: &f64) -> (f64, usize) {
         let mut node_idx = 0;
         loop {
             let node = &self.nodes.get(&node_idx).unwrap();
             if node.is_leaf {
                 return (node.weight_value as f64, node_idx);
             } else {
-                node_idx = node.get_child_idx(data.get(row, node.split_feature), missing);
+                node_idx = node.get_child_idx(&data.get_f64(row, node.split_feature), missing);
             }
         }
     }
@@ -275,6 +286,7 @@ impl Tree {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::Matrix;
     use crate::objective::Objective;
     use polars::io::SerReader;
     use polars::prelude::{CsvReadOptions, DataType};
@@ -1,18 +1,32 @@
 use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 pub enum SampleMethod {
     None,
+    /// Sample a `subsample` fraction of the rows uniformly at random.
     Random,
+    /// Gradient-based one-side sampling, keep the `top_rate` fraction of the rows with the largest
+    /// gradients, and sample an `other_rate` fraction of all the rows from the rest, with their
+    /// gradients and hessians scaled up to stand for the rows left out.
+    Goss,
 }
 
 // A sampler can be used to subset the data prior to fitting a new tree.
 pub trait Sampler {
     /// Sample the data, returning a tuple, where the first item is the samples
     /// chosen for training, and the second are the samples excluded.
-    fn sample(&mut self, rng: &mut StdRng, index: &[usize]) -> (Vec<usize>, Vec<usize>);
+    /// The gradient and hessian of the chosen samples can be reweighted.
+    fn sample(
+        &mut self,
+        rng: &mut StdRng,
+        index: &[usize],
+        grad: &mut [f32],
+        hess: Option<&mut [f32]>,
+    ) -> (Vec<usize>, Vec<usize>);
 }
 
 pub struct RandomSampler {
@@ -20,14 +34,19 @@ pub struct RandomSampler {
 }
 
 impl RandomSampler {
-    #[allow(dead_code)]
     pub fn new(subsample: f32) -> Self {
         RandomSampler { subsample }
     }
 }
 
 impl Sampler for RandomSampler {
-    fn sample(&mut self, rng: &mut StdRng, index: &[usize]) -> (Vec<usize>, Vec<usize>) {
+    fn sample(
+        &mut self,
+        rng: &mut StdRng,
+        index: &[usize],
+        _grad: &mut [f32],
+        _hess: Option<&mut [f32]>,
+    ) -> (Vec<usize>, Vec<usize>) {
         let subsample = self.subsample;
         let mut chosen = Vec::new();
         let mut excluded = Vec::new();
@@ -41,3 +60,196 @@ impl Sampler for RandomSampler {
         (chosen, excluded)
     }
 }
+
+pub struct GossSampler {
+    top_rate: f32,
+    other_rate: f32,
+}
+
+impl GossSampler {
+    pub fn new(top_rate: f32, other_rate: f32) -> Self {
+        GossSampler { top_rate, other_rate }
+    }
+}
+
+impl Sampler for GossSampler {
+    fn sample(
+        &mut self,
+        rng: &mut StdRng,
+        index: &[usize],
+        grad: &mut [f32],
+        mut hess: Option<&mut [f32]>,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let n_top = (self.top_rate * index.len() as f32).round() as usize;
+        let mut sorted = index.to_vec();
+        sorted.sort_unstable_by(|a, b| grad[*b].abs().total_cmp(&grad[*a].abs()));
+        let (top, rest) = sorted.split_at(n_top);
+
+        // The rest is sampled with a rate giving `other_rate` of all the rows, and amplified
+        // by its inverse, so that the gradient sums are unbiased.
+        let rest_rate = if self.top_rate < 1.0 {
+            (self.other_rate / (1.0 - self.top_rate)).min(1.0)
+        } else {
+            0.0
+        };
+        let amplification = 1.0 / rest_rate;
+        let mut chosen = top.to_vec();
+        let mut excluded = Vec::new();
+        for i in rest {
+            if rng.random_range(0.0..1.0) < rest_rate {
+                chosen.push(*i);
+                grad[*i] *= amplification;
+                if let Some(h) = hess.as_deref_mut() {
+                    h[*i] *= amplification;
+                }
+            } else {
+                excluded.push(*i);
+            }
+        }
+        chosen.sort_unstable();
+        excluded.sort_unstable();
+        (chosen, excluded)
+    }
+}
+
+/// Samples the columns the trees, and the nodes of a tree, are split on.
+/// The columns are drawn with probability proportional to their weight, and
+/// the always included columns are part of every sample.
+pub struct ColumnSampler {
+    colsample_bynode: Option<f32>,
+    feature_weights: Option<Vec<f64>>,
+    always_include: HashSet<usize>,
+}
+
+impl ColumnSampler {
+    /// * `colsample_bynode` - Fraction of the columns of the tree each node is split on, all of them if `None`.
+    /// * `feature_weights` - Sampling weight of every column, uniform if `None`.
+    /// * `always_include` - Columns part of every sample.
+    pub fn new(
+        colsample_bynode: Option<f32>,
+        feature_weights: Option<Vec<f64>>,
+        always_include: Option<&HashSet<usize>>,
+    ) -> Self {
+        ColumnSampler {
+            colsample_bynode,
+            feature_weights,
+            always_include: always_include.cloned().unwrap_or_default(),
+        }
+    }
+
+    /// The columns of the data that can be split on, all but those with a weight of zero
+    /// that are not always included.
+    pub fn columns(&self, cols: usize) -> Vec<usize> {
+        (0..cols)
+            .filter(|c| self.always_include.contains(c) || self.feature_weights.as_ref().is_none_or(|w| w[*c] > 0.0))
+            .collect()
+    }
+
+    /// Whether the nodes are split on a sample of the columns of the tree.
+    pub fn samples_nodes(&self) -> bool {
+        self.colsample_bynode.is_some()
+    }
+
+    /// Choose `amount` of the columns in `col_index`, sorted by index. The always included
+    /// columns are chosen first, and columns with a weight of zero are never drawn.
+    pub fn sample(&self, rng: &mut StdRng, col_index: &[usize], amount: usize) -> Vec<usize> {
+        let (mut chosen, rest): (Vec<usize>, Vec<usize>) =
+            col_index.iter().partition(|c| self.always_include.contains(c));
+        let n_draws = amount.saturating_sub(chosen.len()).min(rest.len());
+        match &self.feature_weights {
+            None => chosen.extend(rest.iter().choose_multiple(rng, n_draws)),
+            Some(weights) => {
+                // Weighted sampling without replacement, keeping the columns with the largest
+                // keys u^(1 / w), compared on the log scale.
+                let mut keys: Vec<(f64, usize)> = rest
+                    .iter()
+                    .filter(|c| weights[**c] > 0.0)
+                    .map(|c| (rng.random_range(0.0_f64..1.0).ln() / weights[*c], *c))
+                    .collect();
+                keys.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+                chosen.extend(keys.iter().take(n_draws).map(|(_, c)| *c));
+            }
+        }
+        chosen.sort_unstable();
+        chosen
+    }
+
+    /// Positions in `col_index` of the columns a node is split on, `None` when it is split on all of them.
+    pub fn sample_node(&self, rng: &mut StdRng, col_index: &[usize]) -> Option<Vec<usize>> {
+        let rate = self.colsample_bynode?;
+        let amount = ((rate * col_index.len() as f32).ceil() as usize).clamp(1, col_index.len());
+        let columns = self.sample(rng, col_index, amount);
+        Some(
+            col_index
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| columns.binary_search(c).is_ok())
+                .map(|(i, _)| i)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_goss_sampler() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let index: Vec<usize> = (0..1000).collect();
+        let mut grad: Vec<f32> = index.iter().map(|i| (*i as f32 - 500.0) / 100.0).collect();
+        let mut hess = vec![1.0_f32; 1000];
+        let grad_sum: f32 = grad.iter().map(|g| g.abs()).sum();
+
+        let mut sampler = GossSampler::new(0.2, 0.1);
+        let (chosen, excluded) = sampler.sample(&mut rng, &index, &mut grad, Some(&mut hess));
+        assert_eq!(chosen.len() + excluded.len(), index.len());
+        assert!(chosen.windows(2).all(|w| w[0] < w[1]));
+        // The rows with the largest gradients are always kept, with their weight unchanged.
+        assert!(chosen.contains(&0) && chosen.contains(&999));
+        assert_eq!(hess[0], 1.0);
+        assert!(chosen.len() > 250 && chosen.len() < 350);
+        assert!(chosen.iter().filter(|i| hess[**i] > 1.0).all(|i| hess[*i] == 8.0));
+        // The scaled gradients of the sample add up to about the total.
+        let sample_sum: f32 = chosen.iter().map(|i| grad[*i].abs()).sum();
+        assert!((sample_sum / grad_sum - 1.0).abs() < 0.1);
+
+        let mut sampler = RandomSampler::new(0.5);
+        let (chosen, excluded) = sampler.sample(&mut rng, &index, &mut grad, None);
+        assert_eq!(chosen.len() + excluded.len(), index.len());
+        assert!(chosen.len() > 400 && chosen.len() < 600);
+    }
+
+    #[test]
+    fn test_column_sampler() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let col_index: Vec<usize> = (0..10).collect();
+        let mut weights = vec![1.0; 10];
+        weights[3] = 0.0;
+        weights[7] = 100.0;
+        let always_include = HashSet::from([5]);
+        let sampler = ColumnSampler::new(Some(0.5), Some(weights), Some(&always_include));
+
+        let mut counts = [0; 10];
+        for _ in 0..200 {
+            let chosen = sampler.sample(&mut rng, &col_index, 4);
+            assert_eq!(chosen.len(), 4);
+            assert!(chosen.windows(2).all(|w| w[0] < w[1]));
+            chosen.iter().for_each(|c| counts[*c] += 1);
+        }
+        assert_eq!(counts[5], 200);
+        assert_eq!(counts[3], 0);
+        assert!(counts[7] > 190);
+
+        // The nodes sample the positions of the columns of the tree.
+        let tree_cols = [1, 5, 7, 8];
+        let positions = sampler.sample_node(&mut rng, &tree_cols).unwrap();
+        assert_eq!(positions.len(), 2);
+        assert!(positions.contains(&1));
+        assert!(ColumnSampler::new(None, None, None)
+            .sample_node(&mut rng, &tree_cols)
+            .is_none());
+    }
+}
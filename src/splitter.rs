@@ -1,8 +1,9 @@
 use crate::bin::sort_cat_bins_by_stat;
+use crate::binning::BinnedMatrix;
 use crate::booster::booster::MissingNodeTreatment;
 use crate::constants::GENERALIZATION_THRESHOLD;
-use crate::constraints::{Constraint, ConstraintMap};
-use crate::data::{FloatData, Matrix};
+use crate::constraints::{interaction_allowed, Constraint, ConstraintMap, InteractionConstraints};
+use crate::data::FloatData;
 use crate::histogram::{update_histogram, FeatureHistogram, NodeHistogram};
 use crate::node::{NodeType, SplittableNode};
 use crate::tree::Tree;
@@ -146,6 +147,7 @@ pub trait Splitter {
     }
     fn get_constraint(&self, feature: &usize) -> Option<&Constraint>;
     fn get_constraint_map(&self) -> &ConstraintMap;
+    fn get_interaction_constraints(&self) -> Option<&InteractionConstraints>;
     fn get_allow_missing_splits(&self) -> bool;
     fn get_create_missing_branch(&self) -> bool;
     fn get_eta(&self) -> f32;
@@ -158,11 +160,15 @@ pub trait Splitter {
     /// not used.
     fn clean_up_splits(&self, _tree: &mut Tree) {}
 
-    /// Find the best possible split, considering all feature histograms.
+    /// Find the best possible split, considering all feature histograms, or the histograms
+    /// of the features at the positions in `node_features`, when the node samples the features.
+    /// Only the features allowed to interact with the ones on the path of the node are considered.
+    #[allow(clippy::too_many_arguments)]
     fn best_split(
         &self,
         node: &SplittableNode,
         col_index: &[usize],
+        node_features: Option<&[usize]>,
         is_const_hess: bool,
         hist_tree: &[NodeHistogram],
         pool: &ThreadPool,
@@ -179,11 +185,24 @@ pub trait Splitter {
 
         let best_feature_split = best_feature_split_callables(is_const_hess);
 
-        let feature_index = (0..col_index.len()).collect::<Vec<_>>();
+        let interaction_constraints = self.get_interaction_constraints();
+        let feature_index = (0..col_index.len())
+            .filter(|i| node_features.is_none_or(|f| f.binary_search(i).is_ok()))
+            .filter(|i| {
+                interaction_constraints.is_none_or(|c| interaction_allowed(c, &node.path_features, col_index[*i]))
+            })
+            .collect::<Vec<_>>();
+        if feature_index.len() < col_index.len() {
+            // The split info of the features left out must not win.
+            (0..col_index.len())
+                .filter(|i| feature_index.binary_search(i).is_err())
+                .for_each(|i| unsafe { split_info_slice.get_mut(i).split_gain = -1.0 });
+        }
 
         if pool.current_num_threads() > 1 {
             pool.scope(|s| {
-                for (feature, feat_idx) in col_index.iter().zip(feature_index.iter()) {
+                for feat_idx in feature_index.iter() {
+                    let feature = &col_index[*feat_idx];
                     s.spawn(|_| {
                         best_feature_split(
                             node,
@@ -202,8 +221,9 @@ pub trait Splitter {
                 }
             });
         } else {
-            for (feature, feat_idx) in col_index.iter().zip(feature_index.iter()) {
-                let constraint = self.get_constraint(&feature);
+            for feat_idx in feature_index.iter() {
+                let feature = &col_index[*feat_idx];
+                let constraint = self.get_constraint(feature);
                 best_feature_split(
                     node,
                     *feat_idx,
@@ -232,7 +252,7 @@ pub trait Splitter {
         node: &mut SplittableNode,
         index: &mut [usize],
         col_index: &[usize],
-        data: &Matrix<u16>,
+        data: &mut BinnedMatrix,
         grad: &mut [f32],
         hess: Option<&mut [f32]>,
         pool: &ThreadPool,
@@ -248,7 +268,8 @@ pub trait Splitter {
         node: &mut SplittableNode,
         index: &mut [usize],
         col_index: &[usize],
-        data: &Matrix<u16>,
+        node_features: Option<&[usize]>,
+        data: &mut BinnedMatrix,
         grad: &mut [f32],
         hess: Option<&mut [f32]>,
         pool: &ThreadPool,
@@ -260,6 +281,7 @@ pub trait Splitter {
         self.best_split(
             node,
             col_index,
+            node_features,
             is_const_hess,
             hist_tree,
             pool,
@@ -270,9 +292,14 @@ pub trait Splitter {
         let split_info = unsafe { split_info_slice.best_split_info() };
 
         if split_info.split_gain > 0.0 {
-            self.handle_split_info(
+            let mut new_nodes = self.handle_split_info(
                 split_info, n_nodes, node, index, col_index, data, grad, hess, pool, hist_tree,
-            )
+            );
+            for n in new_nodes.iter_mut() {
+                n.path_features.clone_from(&node.path_features);
+                n.path_features.insert(node.split_feature);
+            }
+            new_nodes
         } else {
             Vec::new()
         }
@@ -292,6 +319,7 @@ pub struct MissingBranchSplitter {
     pub terminate_missing_features: HashSet<usize>,
     pub missing_node_treatment: MissingNodeTreatment,
     pub force_children_to_bound_parent: bool,
+    pub interaction_constraints: Option<InteractionConstraints>,
 }
 
 impl MissingBranchSplitter {
@@ -313,9 +341,16 @@ impl MissingBranchSplitter {
             terminate_missing_features,
             missing_node_treatment,
             force_children_to_bound_parent,
+            interaction_constraints: None,
         }
     }
 
+    /// Set the groups of features allowed to interact, any features may interact if `None`.
+    pub fn set_interaction_constraints(mut self, interaction_constraints: Option<InteractionConstraints>) -> Self {
+        self.interaction_constraints = interaction_constraints;
+        self
+    }
+
     pub fn new_leaves_added(&self) -> usize {
         2
     }
@@ -381,6 +416,9 @@ impl Splitter for MissingBranchSplitter {
     fn get_constraint_map(&self) -> &HashMap<usize, Constraint> {
         &self.constraints_map
     }
+    fn get_interaction_constraints(&self) -> Option<&InteractionConstraints> {
+        self.interaction_constraints.as_ref()
+    }
     fn get_allow_missing_splits(&self) -> bool {
         self.allow_missing_splits
     }
@@ -404,7 +442,7 @@ impl Splitter for MissingBranchSplitter {
         node: &mut SplittableNode,
         mut index: &mut [usize],
         col_index: &[usize],
-        data: &Matrix<u16>,
+        data: &mut BinnedMatrix,
         grad: &mut [f32],
         mut hess: Option<&mut [f32]>,
         pool: &ThreadPool,
@@ -420,6 +458,7 @@ impl Splitter for MissingBranchSplitter {
         // Missing all falls to the bottom.
         let mut missing_split_idx: usize;
         let mut split_idx: usize;
+        let feature = data.split_col(split_info.split_feature, index, node.start_idx, node.stop_idx);
         if hess.is_some() {
             (missing_split_idx, split_idx) = pivot_on_split_exclude_missing(
                 node.start_idx,
@@ -427,7 +466,7 @@ impl Splitter for MissingBranchSplitter {
                 &mut index,
                 grad,
                 &mut hess.as_mut().unwrap(),
-                data.get_col(split_info.split_feature),
+                feature,
                 split_info.split_bin,
                 &split_info.left_cats,
             );
@@ -437,11 +476,12 @@ impl Splitter for MissingBranchSplitter {
                 node.stop_idx,
                 &mut index,
                 grad,
-                data.get_col(split_info.split_feature),
+                feature,
                 split_info.split_bin,
                 &split_info.left_cats,
             );
         }
+        data.update_positions(index, node.start_idx, node.stop_idx);
 
         node.update_children(missing_child, left_child, right_child, &split_info);
 
@@ -658,6 +698,7 @@ pub struct MissingImputerSplitter {
     pub constraints_map: ConstraintMap,
     pub missing_node_treatment: MissingNodeTreatment,
     pub force_children_to_bound_parent: bool,
+    pub interaction_constraints: Option<InteractionConstraints>,
 }
 
 impl MissingImputerSplitter {
@@ -671,8 +712,15 @@ impl MissingImputerSplitter {
             constraints_map,
             missing_node_treatment: MissingNodeTreatment::None,
             force_children_to_bound_parent: false,
+            interaction_constraints: None,
         }
     }
+
+    /// Set the groups of features allowed to interact, any features may interact if `None`.
+    pub fn set_interaction_constraints(mut self, interaction_constraints: Option<InteractionConstraints>) -> Self {
+        self.interaction_constraints = interaction_constraints;
+        self
+    }
 }
 
 impl Splitter for MissingImputerSplitter {
@@ -682,6 +730,9 @@ impl Splitter for MissingImputerSplitter {
     fn get_constraint_map(&self) -> &HashMap<usize, Constraint> {
         &self.constraints_map
     }
+    fn get_interaction_constraints(&self) -> Option<&InteractionConstraints> {
+        self.interaction_constraints.as_ref()
+    }
     fn get_allow_missing_splits(&self) -> bool {
         self.allow_missing_splits
     }
@@ -705,7 +756,7 @@ impl Splitter for MissingImputerSplitter {
         node: &mut SplittableNode,
         index: &mut [usize],
         col_index: &[usize],
-        data: &Matrix<u16>,
+        data: &mut BinnedMatrix,
         grad: &mut [f32],
         mut hess: Option<&mut [f32]>,
         pool: &ThreadPool,
@@ -729,13 +780,14 @@ impl Splitter for MissingImputerSplitter {
         //
         // This function mutates index by swapping indices based on split bin
         let mut split_idx: usize;
+        let feature = data.split_col(split_info.split_feature, index, node.start_idx, node.stop_idx);
         if hess.is_none() {
             split_idx = pivot_on_split_const_hess(
                 node.start_idx,
                 node.stop_idx,
                 index,
                 grad,
-                data.get_col(split_info.split_feature),
+                feature,
                 split_info.split_bin,
                 missing_right,
                 &split_info.left_cats,
@@ -747,12 +799,13 @@ impl Splitter for MissingImputerSplitter {
                 index,
                 grad,
                 &mut hess.as_mut().unwrap(),
-                data.get_col(split_info.split_feature),
+                feature,
                 split_info.split_bin,
                 missing_right,
                 &split_info.left_cats,
             );
         }
+        data.update_positions(index, node.start_idx, node.stop_idx);
 
         // Calculate histograms
         let total_recs = node.stop_idx - node.start_idx;
@@ -1841,7 +1894,7 @@ mod tests {
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap()).collect();
         let yhat = vec![0.5; y.len()];
 
-        let (grad, hess) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
+        let (grad, hess) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
 
         let splitter = MissingImputerSplitter::new(0.3, true, ConstraintMap::new());
         let gradient_sum = grad.iter().sum();
@@ -1879,7 +1932,7 @@ mod tests {
                 unsafe { &mut hist_tree.get_unchecked(i) },
                 0,
                 index.len(),
-                &bdata,
+                &mut BinnedMatrix::Dense(&bdata),
                 &grad,
                 hess.as_deref(),
                 &index,
@@ -1911,6 +1964,7 @@ mod tests {
         splitter.best_split(
             &mut n,
             &col_index,
+            None,
             false,
             &mut hist_tree,
             &pool,
@@ -1992,7 +2046,7 @@ mod tests {
 
         let y_test_avg = y_test.iter().sum::<f64>() / y_test.len() as f64;
         let yhat = vec![y_test_avg; y_test.len()];
-        let (grad, hess) = SquaredLoss::calc_grad_hess(&y_test, &yhat, None, None);
+        let (grad, hess) = SquaredLoss::default().calc_grad_hess(&y_test, &yhat, None, None);
 
         let splitter = MissingImputerSplitter::new(0.3, false, ConstraintMap::new());
 
@@ -2021,7 +2075,7 @@ mod tests {
                 unsafe { &mut hist_tree.get_unchecked(i) },
                 0,
                 index.len(),
-                &bdata,
+                &mut BinnedMatrix::Dense(&bdata),
                 &grad,
                 hess.as_deref(),
                 &index,
@@ -2038,6 +2092,7 @@ mod tests {
         splitter.best_split(
             &mut n,
             &col_index,
+            None,
             is_const_hess,
             &mut hist_tree,
             &pool,
@@ -2073,7 +2128,7 @@ mod tests {
 
         let y_avg = y.iter().sum::<f64>() / y.len() as f64;
         let yhat = vec![y_avg; y.len()];
-        let (grad, hess) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
+        let (grad, hess) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
 
         let splitter = MissingImputerSplitter::new(eta, false, ConstraintMap::new());
 
@@ -2108,7 +2163,7 @@ mod tests {
                 unsafe { &mut hist_tree.get_unchecked(i) },
                 0,
                 index.len(),
-                &bdata,
+                &mut BinnedMatrix::Dense(&bdata),
                 &grad,
                 hess.as_deref(),
                 &index,
@@ -2140,6 +2195,7 @@ mod tests {
         splitter.best_split(
             &mut n,
             &col_index,
+            None,
             is_const_hess,
             &mut hist_tree,
             &pool,
@@ -2182,7 +2238,7 @@ mod tests {
 
         let y_avg = y.iter().sum::<f64>() / y.len() as f64;
         let yhat = vec![y_avg; y.len()];
-        let (grad, hess) = SquaredLoss::calc_grad_hess(&y, &yhat, None, None);
+        let (grad, hess) = SquaredLoss::default().calc_grad_hess(&y, &yhat, None, None);
 
         let splitter = MissingImputerSplitter::new(eta, false, ConstraintMap::new());
 
@@ -2207,7 +2263,7 @@ mod tests {
                 unsafe { &mut hist_tree.get_unchecked(i) },
                 0,
                 index.len(),
-                &bdata,
+                &mut BinnedMatrix::Dense(&bdata),
                 &grad,
                 None,
                 &index,
@@ -2224,6 +2280,7 @@ mod tests {
         splitter.best_split(
             &mut n,
             &col_index,
+            None,
             is_const_hess,
             &mut hist_tree,
             &pool,
@@ -1,10 +1,14 @@
-use crate::data::Matrix;
+use crate::binning::BinnedMatrix;
+use crate::data::DataMatrix;
 use crate::grower::Grower;
 use crate::histogram::{update_histogram, NodeHistogram};
 use crate::node::{Node, NodeType, SplittableNode};
+use crate::objective::{LeafFn, LossFn};
 use crate::partial_dependence::tree_partial_dependence;
+use crate::sampler::ColumnSampler;
 use crate::splitter::{SplitInfoSlice, Splitter};
 use crate::utils::{fast_f64_sum, gain, gain_const_hess, odds, weight, weight_const_hess};
+use rand::rngs::StdRng;
 use rayon::{prelude::*, ThreadPool};
 use serde::{Deserialize, Serialize};
 use std::cmp::max;
@@ -24,6 +28,9 @@ pub struct Tree {
     pub stopper: TreeStopper,
     pub depth: usize,
     pub n_leaves: usize,
+    /// Index of the data batch the tree was fit on, counting the fits that continued training.
+    #[serde(default)]
+    pub batch: usize,
 }
 
 impl Default for Tree {
@@ -39,15 +46,17 @@ impl Tree {
             stopper: TreeStopper::Generalization,
             depth: 0,
             n_leaves: 0,
+            batch: 0,
         }
     }
 
     #[allow(clippy::too_many_arguments)]
     pub fn fit<T: Splitter>(
         &mut self,
-        data: &Matrix<u16>,
+        data: &mut BinnedMatrix,
         mut index: Vec<usize>,
         col_index: &[usize],
+        mut col_sampler: Option<(&ColumnSampler, &mut StdRng)>,
         grad: &mut [f32],
         mut hess: Option<&mut [f32]>,
         splitter: &T,
@@ -55,7 +64,7 @@ impl Tree {
         target_loss_decrement: Option<f32>,
         loss: &[f32],
         y: &[f64],
-        calc_loss: fn(&[f64], &[f64], Option<&[f64]>, Option<f64>) -> Vec<f32>,
+        calc_loss: &LossFn,
         yhat: &[f64],
         sample_weight: Option<&[f64]>,
         quantile: Option<f64>,
@@ -68,6 +77,7 @@ impl Tree {
         let mut n_nodes = 1;
         self.n_leaves = 1;
 
+        data.set_positions(&index);
         let root_hist = unsafe { hist_tree.get_unchecked_mut(0) };
         update_histogram(
             root_hist,
@@ -87,7 +97,8 @@ impl Tree {
 
         let mut growable = BinaryHeap::<SplittableNode>::default();
 
-        let mut loss_decr = vec![0.0_f32; index.len()];
+        // Indexed by record, the index holds a sample of the records when the rows are sampled.
+        let mut loss_decr = vec![0.0_f32; data.rows()];
         let mut loss_decr_avg = 0.0_f32;
         let index_length = index.len() as f32;
 
@@ -120,11 +131,15 @@ impl Tree {
             // more, then just add 1 back to n_leaves
             self.n_leaves -= 1;
 
+            let node_features = col_sampler
+                .as_mut()
+                .and_then(|(sampler, rng)| sampler.sample_node(rng, col_index));
             let new_nodes = splitter.split_node(
                 &n_nodes,
                 &mut node,
                 &mut index,
                 col_index,
+                node_features.as_deref(),
                 data,
                 grad,
                 hess.as_deref_mut(),
@@ -338,12 +353,15 @@ impl Tree {
         }
     }
 
-    pub fn predict_contributions_weight(&self, data: &Matrix<f64>, contribs: &mut [f64], missing: &f64) {
+    pub fn predict_contributions_weight<D: DataMatrix>(&self, data: &D, contribs: &mut [f64], missing: &f64) {
+        let missing = &data.missing_as(missing);
         // There needs to always be at least 2 trees
-        data.index
+        data.index()
             .par_iter()
-            .zip(contribs.par_chunks_mut(data.cols + 1))
-            .for_each(|(row, contribs)| self.predict_contributions_row_weight(&data.get_row(*row), contribs, missing))
+            .zip(contribs.par_chunks_mut(data.cols() + 1))
+            .for_each(|(row, contribs)| {
+                self.predict_contributions_row_weight(&data.get_row_f64(*row), contribs, missing)
+            })
     }
 
     pub fn predict_contributions_row_average(
@@ -371,30 +389,32 @@ impl Tree {
         }
     }
 
-    pub fn predict_contributions_average(
+    pub fn predict_contributions_average<D: DataMatrix>(
         &self,
-        data: &Matrix<f64>,
+        data: &D,
         contribs: &mut [f64],
         weights: &HashMap<usize, f64>,
         missing: &f64,
     ) {
+        let missing = &data.missing_as(missing);
         // There needs to always be at least 2 trees
-        data.index
+        data.index()
             .par_iter()
-            .zip(contribs.par_chunks_mut(data.cols + 1))
+            .zip(contribs.par_chunks_mut(data.cols() + 1))
             .for_each(|(row, contribs)| {
-                self.predict_contributions_row_average(&data.get_row(*row), contribs, weights, missing)
+                self.predict_contributions_row_average(&data.get_row_f64(*row), contribs, weights, missing)
             })
     }
 
-    fn predict_row(&self, data: &Matrix<f64>, row: usize, missing: &f64) -> f64 {
+    /// Predict a row of the data, `missing` is rounded to the type of the data.
+    fn predict_row<D: DataMatrix>(&self, data: &D, row: usize, missing: &f64) -> f64 {
         let mut node_idx = 0;
         loop {
             let node = &self.nodes.get(&node_idx).unwrap();
             if node.is_leaf {
                 return node.weight_value as f64;
             } else {
-                node_idx = node.get_child_idx(data.get(row, node.split_feature), missing);
+                node_idx = node.get_child_idx(&data.get_f64(row, node.split_feature), missing);
             }
         }
     }
@@ -411,18 +431,78 @@ impl Tree {
         }
     }
 
-    fn predict_single_threaded(&self, data: &Matrix<f64>, missing: &f64) -> Vec<f64> {
-        data.index.iter().map(|i| self.predict_row(data, *i, missing)).collect()
+    /// Find the leaf of a row of the data, `missing` is rounded to the type of the data.
+    fn predict_leaf_row<D: DataMatrix>(&self, data: &D, row: usize, missing: &f64) -> usize {
+        let mut node_idx = 0;
+        loop {
+            let node = &self.nodes.get(&node_idx).unwrap();
+            if node.is_leaf {
+                return node_idx;
+            } else {
+                node_idx = node.get_child_idx(&data.get_f64(row, node.split_feature), missing);
+            }
+        }
+    }
+
+    /// Set the leaf values again, from the targets and predictions of the records
+    /// that fall in each leaf, scaled by the step size.
+    ///
+    /// * `data` - The data the tree was fit on.
+    /// * `index` - The records the tree was fit on.
+    /// * `y` - The target of each record.
+    /// * `yhat` - The predictions before this tree.
+    /// * `sample_weight` - Instance weights for each record.
+    /// * `eta` - The step size the leaf values are multiplied with.
+    /// * `missing` - Value to consider missing.
+    /// * `calc_leaf_value` - Calculates the value of a leaf, from the records in it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn refit_leaf_values<D: DataMatrix>(
+        &mut self,
+        data: &D,
+        index: &[usize],
+        y: &[f64],
+        yhat: &[f64],
+        sample_weight: Option<&[f64]>,
+        eta: f32,
+        missing: &f64,
+        calc_leaf_value: &LeafFn,
+    ) {
+        let missing = &data.missing_as(missing);
+        let mut leaf_rows: HashMap<usize, Vec<usize>> = HashMap::new();
+        for row in index.iter() {
+            leaf_rows
+                .entry(self.predict_leaf_row(data, *row, missing))
+                .or_default()
+                .push(*row);
+        }
+        for (leaf, rows) in leaf_rows {
+            let y_leaf: Vec<f64> = rows.iter().map(|i| y[*i]).collect();
+            let yhat_leaf: Vec<f64> = rows.iter().map(|i| yhat[*i]).collect();
+            let w_leaf: Option<Vec<f64>> = sample_weight.map(|w| rows.iter().map(|i| w[*i]).collect());
+            let value = calc_leaf_value(&y_leaf, &yhat_leaf, w_leaf.as_deref());
+            if let Some(node) = self.nodes.get_mut(&leaf) {
+                node.weight_value = (value as f32) * eta;
+            }
+        }
+    }
+
+    fn predict_single_threaded<D: DataMatrix>(&self, data: &D, missing: &f64) -> Vec<f64> {
+        data.index()
+            .iter()
+            .map(|i| self.predict_row(data, *i, missing))
+            .collect()
     }
 
-    fn predict_parallel(&self, data: &Matrix<f64>, missing: &f64) -> Vec<f64> {
-        data.index
+    fn predict_parallel<D: DataMatrix>(&self, data: &D, missing: &f64) -> Vec<f64> {
+        data.index()
             .par_iter()
             .map(|i| self.predict_row(data, *i, missing))
             .collect()
     }
 
-    pub fn predict(&self, data: &Matrix<f64>, parallel: bool, missing: &f64) -> Vec<f64> {
+    /// Predict the data, of f64 or f32 values.
+    pub fn predict<D: DataMatrix>(&self, data: &D, parallel: bool, missing: &f64) -> Vec<f64> {
+        let missing = &data.missing_as(missing);
         if parallel {
             self.predict_parallel(data, missing)
         } else {
@@ -526,19 +606,23 @@ impl Tree {
     pub fn calculate_importance_cover(&self, stats: &mut HashMap<usize, (f32, usize)>) {
         self.get_node_stats(&|n: &Node| n.hessian_sum, stats);
     }
-}
 
-impl Display for Tree {
-    // This trait requires `fmt` with this exact signature.
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// Format the tree as in its `Display` output, with the names of the split features
+    /// instead of their indices, if the feature names are given.
+    ///
+    /// * `feature_names` - Names of the features, indexed by the split feature.
+    pub fn to_string_with_names(&self, feature_names: Option<&[String]>) -> String {
         let mut print_buffer: Vec<usize> = vec![0];
         let mut r = String::new();
         while let Some(idx) = print_buffer.pop() {
             let node = &self.nodes[&idx];
-            if node.is_leaf {
-                r += format!("{}{}\n", "      ".repeat(node.depth).as_str(), node).as_str();
-            } else {
-                r += format!("{}{}\n", "      ".repeat(node.depth).as_str(), node).as_str();
+            r += format!(
+                "{}{}\n",
+                "      ".repeat(node.depth).as_str(),
+                node.to_string_with_names(feature_names)
+            )
+            .as_str();
+            if !node.is_leaf {
                 print_buffer.push(node.right_child);
                 print_buffer.push(node.left_child);
                 if node.has_missing_branch() {
@@ -546,7 +630,14 @@ impl Display for Tree {
                 }
             }
         }
-        write!(f, "{}", r)
+        r
+    }
+}
+
+impl Display for Tree {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_with_names(None))
     }
 }
 
@@ -587,8 +678,9 @@ mod tests {
     use super::*;
     use crate::binning::bin_matrix;
     use crate::constraints::{Constraint, ConstraintMap};
+    use crate::data::Matrix;
     use crate::histogram::NodeHistogramOwned;
-    use crate::objective::{LogLoss, ObjectiveFunction, SquaredLoss};
+    use crate::objective::{loss_callables, LogLoss, Objective, ObjectiveFunction, SquaredLoss};
     use crate::splitter::{MissingImputerSplitter, SplitInfo};
     use crate::utils::precision_round;
     use polars::datatypes::DataType;
@@ -606,8 +698,8 @@ mod tests {
         let file = fs::read_to_string("resources/performance.csv").expect("Something went wrong reading the file");
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap()).collect();
         let yhat = vec![0.5; y.len()];
-        let (mut g, mut h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
-        let loss = LogLoss::calc_loss(&y, &yhat, None, None);
+        let (mut g, mut h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
+        let loss = LogLoss::default().calc_loss(&y, &yhat, None, None);
 
         let data = Matrix::new(&data_vec, 891, 5);
         let splitter = MissingImputerSplitter::new(0.3, true, ConstraintMap::new());
@@ -635,9 +727,10 @@ mod tests {
         let split_info_slice = SplitInfoSlice::new(&mut split_info_vec);
 
         tree.fit(
-            &bdata,
+            &mut BinnedMatrix::Dense(&bdata),
             data.index.to_owned(),
             &col_index,
+            None,
             &mut g,
             h.as_deref_mut(),
             &splitter,
@@ -645,7 +738,7 @@ mod tests {
             Some(f32::MAX),
             &loss,
             &y,
-            LogLoss::calc_loss,
+            &loss_callables(&Objective::LogLoss).unwrap(),
             &yhat,
             None,
             None,
@@ -657,6 +750,10 @@ mod tests {
         );
 
         println!("{}", tree);
+        let names: Vec<String> = (0..data.cols).map(|i| format!("f{}", i)).collect();
+        let named = tree.to_string_with_names(Some(&names));
+        assert!(named.starts_with(&format!("0:[f{} < ", tree.nodes[&0].split_feature)));
+        assert_eq!(named.lines().count(), tree.to_string().lines().count());
         let preds = tree.predict(&data, false, &f64::NAN);
         println!("{:?}", &preds[0..10]);
         assert_eq!(27, tree.nodes.len());
@@ -700,8 +797,8 @@ mod tests {
         let file = fs::read_to_string("resources/performance.csv").expect("Something went wrong reading the file");
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap()).collect();
         let yhat = vec![0.5; y.len()];
-        let (mut g, mut h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
-        let loss = LogLoss::calc_loss(&y, &yhat, None, None);
+        let (mut g, mut h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
+        let loss = LogLoss::default().calc_loss(&y, &yhat, None, None);
         println!("GRADIENT -- {:?}", g);
 
         let data_ = Matrix::new(&data_vec, 891, 5);
@@ -732,9 +829,10 @@ mod tests {
         let split_info_slice = SplitInfoSlice::new(&mut split_info_vec);
 
         tree.fit(
-            &bdata,
+            &mut BinnedMatrix::Dense(&bdata),
             data.index.to_owned(),
             &col_index,
+            None,
             &mut g,
             h.as_deref_mut(),
             &splitter,
@@ -742,7 +840,7 @@ mod tests {
             Some(f32::MAX),
             &loss,
             &y,
-            LogLoss::calc_loss,
+            &loss_callables(&Objective::LogLoss).unwrap(),
             &yhat,
             None,
             None,
@@ -795,8 +893,8 @@ mod tests {
         let file = fs::read_to_string("resources/performance.csv").expect("Something went wrong reading the file");
         let y: Vec<f64> = file.lines().map(|x| x.parse::<f64>().unwrap()).collect();
         let yhat = vec![0.5; y.len()];
-        let (mut g, mut h) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
-        let loss = LogLoss::calc_loss(&y, &yhat, None, None);
+        let (mut g, mut h) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
+        let loss = LogLoss::default().calc_loss(&y, &yhat, None, None);
 
         let data = Matrix::new(&data_vec, 891, 5);
         let splitter = MissingImputerSplitter::new(0.3, true, ConstraintMap::new());
@@ -824,9 +922,10 @@ mod tests {
         let split_info_slice = SplitInfoSlice::new(&mut split_info_vec);
 
         tree.fit(
-            &bdata,
+            &mut BinnedMatrix::Dense(&bdata),
             data.index.to_owned(),
             &col_index,
+            None,
             &mut g,
             h.as_deref_mut(),
             &splitter,
@@ -834,7 +933,7 @@ mod tests {
             Some(f32::MAX),
             &loss,
             &y,
-            LogLoss::calc_loss,
+            &loss_callables(&Objective::LogLoss).unwrap(),
             &yhat,
             None,
             None,
@@ -938,8 +1037,8 @@ mod tests {
         );
         let y_test_avg = y_test.iter().sum::<f64>() / y_test.len() as f64;
         let yhat = vec![y_test_avg; y_test.len()];
-        let (mut g, mut h) = SquaredLoss::calc_grad_hess(&y_test, &yhat, None, None);
-        let loss = SquaredLoss::calc_loss(&y_test, &yhat, None, None);
+        let (mut g, mut h) = SquaredLoss::default().calc_grad_hess(&y_test, &yhat, None, None);
+        let loss = SquaredLoss::default().calc_loss(&y_test, &yhat, None, None);
 
         let splitter = MissingImputerSplitter::new(0.3, true, ConstraintMap::new());
 
@@ -967,9 +1066,10 @@ mod tests {
 
         let mut tree = Tree::new();
         tree.fit(
-            &bdata,
+            &mut BinnedMatrix::Dense(&bdata),
             data.index.to_owned(),
             &col_index,
+            None,
             &mut g,
             h.as_deref_mut(),
             &splitter,
@@ -977,7 +1077,7 @@ mod tests {
             Some(f32::MAX),
             &loss,
             &y_test,
-            SquaredLoss::calc_loss,
+            &loss_callables(&Objective::SquaredLoss).unwrap(),
             &yhat,
             None,
             None,
@@ -1010,8 +1110,8 @@ mod tests {
 
         let y_avg = y.iter().sum::<f64>() / y.len() as f64;
         let yhat = vec![y_avg; y.len()];
-        let (mut grad, mut hess) = LogLoss::calc_grad_hess(&y, &yhat, None, None);
-        let loss = LogLoss::calc_loss(&y, &yhat, None, None);
+        let (mut grad, mut hess) = LogLoss::default().calc_grad_hess(&y, &yhat, None, None);
+        let loss = LogLoss::default().calc_loss(&y, &yhat, None, None);
 
         let splitter = MissingImputerSplitter::new(0.3, true, ConstraintMap::new());
 
@@ -1040,9 +1140,10 @@ mod tests {
 
         let mut tree = Tree::new();
         tree.fit(
-            &bdata,
+            &mut BinnedMatrix::Dense(&bdata),
             data.index.to_owned(),
             &col_index,
+            None,
             &mut grad,
             hess.as_deref_mut(),
             &splitter,
@@ -1050,7 +1151,7 @@ mod tests {
             Some(f32::MAX),
             &loss,
             &y,
-            SquaredLoss::calc_loss,
+            &loss_callables(&Objective::SquaredLoss).unwrap(),
             &yhat,
             None,
             None,
@@ -3,7 +3,7 @@ use crate::constraints::Constraint;
 use crate::data::FloatData;
 use crate::errors::PerpetualError;
 use std::cmp::Ordering;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
 
 /// Create a string of all available items.
@@ -68,7 +68,7 @@ pub(crate) use validate_positive_float_field;
 
 /// Calculate if a value is missing.
 #[inline]
-pub fn is_missing(value: &f64, missing: &f64) -> bool {
+pub fn is_missing<T: FloatData<T>>(value: &T, missing: &T) -> bool {
     if missing.is_nan() {
         value.is_nan()
     } else if value.is_nan() {
@@ -78,6 +78,13 @@ pub fn is_missing(value: &f64, missing: &f64) -> bool {
     }
 }
 
+/// The missing value, rounded to the type of the data, so that it matches the missing
+/// values of data stored with less precision once they are converted to f64.
+#[inline]
+pub fn missing_as<T: FloatData<T>>(missing: &f64) -> f64 {
+    T::from_f64(*missing).to_f64()
+}
+
 /// Calculate the constraint weight given bounds
 /// and a constraint.
 #[allow(clippy::too_many_arguments)]
@@ -682,6 +689,21 @@ pub fn missing_compare(split_value: &u16, cmp_value: u16, missing_right: bool, l
     }
 }
 
+/// Group the row indices by query id, with the queries in the order
+/// they first appear. The rows of a query don't need to be contiguous.
+pub fn query_groups(group: &[u64]) -> Vec<Vec<usize>> {
+    let mut position: HashMap<u64, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, g) in group.iter().enumerate() {
+        let p = *position.entry(*g).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[p].push(i);
+    }
+    groups
+}
+
 #[inline]
 pub fn precision_round(n: f64, precision: i32) -> f64 {
     let p = (10.0_f64).powi(precision);